@@ -0,0 +1,11 @@
+#!/bin/sh
+
+# The bootloader talks XMODEM on the mini UART, so expose it as a PTY that
+# ttywrite can open instead of wiring it to this terminal.
+TOP=$(git rev-parse --show-toplevel)
+$TOP/bin/qemu-system-aarch64 \
+    -nographic \
+    -M raspi3 \
+    -serial null -serial pty \
+    -kernel \
+    "$@"
@@ -0,0 +1,31 @@
+use volatile::prelude::*;
+use volatile::Volatile;
+
+mod panic;
+
+use crate::kmain;
+
+global_asm!(include_str!("init/init.s"));
+
+unsafe fn zeros_bss() {
+    extern "C" {
+        static mut __bss_beg: u64;
+        static mut __bss_end: u64;
+    }
+
+    let mut iter: *mut u64 = &mut __bss_beg;
+    let end: *mut u64 = &mut __bss_end;
+
+    while iter < end {
+        Volatile::from_ptr(iter).write(0);
+        iter = iter.add(1);
+    }
+}
+
+/// The entry point from `_start`. `dtb` is the address of the device tree
+/// blob the firmware passed, or 0; it is handed on to the loaded binary.
+#[no_mangle]
+unsafe fn kinit(dtb: usize) -> ! {
+    zeros_bss();
+    kmain(dtb);
+}
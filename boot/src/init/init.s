@@ -0,0 +1,28 @@
+.section .text.init
+
+.global _start
+
+_start:
+    // keep x0 for kinit: the firmware passes the device tree blob's
+    // address there, if it passes one
+    mov     x19, x0
+
+    // read cpu affinity, start core 0, halt rest
+    mrs     x1, mpidr_el1
+    and     x1, x1, #3
+    cbz     x1, 2f
+
+1:
+    // core affinity != 0, halt it
+    wfe
+    b       1b
+
+2:
+    // set the stack to start before our boot code
+    adr     x1, _start
+    mov     sp, x1
+
+    // jump to kinit(dtb), which shouldn't return. halt if it does
+    mov     x0, x19
+    bl      kinit
+    b       1b
@@ -0,0 +1,10 @@
+use core::panic::PanicInfo;
+
+/// The bootloader has no console of its own to report through; park the
+/// core so the board can be reset and the transfer retried.
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    loop {
+        aarch64::wfe();
+    }
+}
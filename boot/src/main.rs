@@ -0,0 +1,99 @@
+//! A serial bootloader.
+//!
+//! The bootloader links itself at `BOOTLOADER_START_ADDR`, out of the way of
+//! the usual kernel load address, listens on the mini UART for a kernel sent
+//! over XMODEM (e.g. with `ttywrite`), writes it to `BINARY_START_ADDR` and
+//! branches to it with the firmware's device tree blob address still in
+//! `x0`, as if the firmware had started it directly. On the Pi the firmware
+//! must be told to load us there by putting `kernel_address=0x4000000` in
+//! `config.txt`.
+
+#![feature(asm)]
+#![feature(global_asm)]
+
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+
+#[cfg(not(test))]
+mod init;
+
+use core::fmt::Write;
+use core::slice;
+use core::time::Duration;
+
+use pi::uart::MiniUart;
+use shim::io;
+use xmodem::Xmodem;
+
+/// Start address of the binary to load and of the bootloader.
+const BINARY_START_ADDR: usize = 0x80000;
+const BOOTLOADER_START_ADDR: usize = 0x4000000;
+
+/// Pointer to where the loaded binary expects to be loaded.
+const BINARY_START: *mut u8 = BINARY_START_ADDR as *mut u8;
+
+/// Space reserved for the bootloader's stack, which grows down from
+/// `BOOTLOADER_START_ADDR` into the top of the binary's region.
+const STACK_SIZE: usize = 64 * 1024;
+
+/// Free space between the start of the binary and the bootloader's stack.
+const MAX_BINARY_SIZE: usize = BOOTLOADER_START_ADDR - BINARY_START_ADDR - STACK_SIZE;
+
+/// How long to wait for the sender before giving up on an attempt and
+/// trying again.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Branches to the address `addr` unconditionally, with `dtb` in `x0` where
+/// the binary expects the firmware's device tree blob.
+unsafe fn jump_to(addr: *mut u8, dtb: usize) -> ! {
+    // Make sure the binary we just wrote is visible to instruction fetch.
+    asm!("dsb sy
+          isb" :::: "volatile");
+    asm!("br $0" : : "r"(addr as usize), "{x0}"(dtb) : : "volatile");
+    loop {
+        aarch64::wfe();
+    }
+}
+
+/// Discards bytes from `uart` until none arrive within its read timeout.
+fn drain(uart: &mut MiniUart) {
+    while uart.wait_for_byte().is_ok() {
+        uart.read_byte();
+    }
+}
+
+fn kmain(dtb: usize) -> ! {
+    let mut uart = MiniUart::new();
+    uart.set_read_timeout(TRANSFER_TIMEOUT);
+
+    let _ = write!(uart, "\r\nboot: waiting for XMODEM transfer to {:#x}\r\n", BINARY_START_ADDR);
+
+    let mut attempt: u32 = 0;
+    loop {
+        attempt = attempt.wrapping_add(1);
+        let binary = unsafe { slice::from_raw_parts_mut(BINARY_START, MAX_BINARY_SIZE) };
+        match Xmodem::receive(&mut uart, binary) {
+            Ok(len) => {
+                let _ = write!(uart, "\r\nboot: received {} bytes; jumping to {:#x}\r\n", len, BINARY_START_ADDR);
+                let _ = io::Write::flush(&mut uart);
+                unsafe { jump_to(BINARY_START, dtb) }
+            }
+            Err(e) => match e.kind() {
+                // Nobody is sending yet. Rewrite a single status line rather
+                // than flooding a terminal that isn't running `ttywrite`.
+                io::ErrorKind::TimedOut => {
+                    let _ = write!(uart, "\rboot: no transfer started; retrying (attempt {})", attempt);
+                }
+                // The transfer was cancelled, but the sender may still be
+                // midway through a packet. Let it finish before writing.
+                io::ErrorKind::WriteZero => {
+                    drain(&mut uart);
+                    let _ = write!(uart, "\r\nboot: binary is larger than {} bytes; retrying\r\n", MAX_BINARY_SIZE);
+                }
+                _ => {
+                    let _ = write!(uart, "\r\nboot: transfer failed ({:?}); retrying\r\n", e.kind());
+                }
+            },
+        }
+    }
+}
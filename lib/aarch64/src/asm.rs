@@ -0,0 +1,80 @@
+/// Wait for event not to burn CPU.
+#[inline(always)]
+pub fn wfe() {
+    unsafe { asm!("wfe" :::: "volatile") };
+}
+
+/// Wait for interrupt not to burn CPU.
+#[inline(always)]
+pub fn wfi() {
+    unsafe { asm!("wfi" :::: "volatile") };
+}
+
+/// A NOOP that won't be optimized out.
+#[inline(always)]
+pub fn nop() {
+    unsafe { asm!("nop" :::: "volatile") };
+}
+
+/// Returns the frame pointer (`x29`) of the function this is inlined into.
+///
+/// Only meaningful when the crate is built with frame pointers enabled.
+#[inline(always)]
+pub fn fp() -> usize {
+    let fp: usize;
+    unsafe { asm!("mov $0, x29" : "=r"(fp) ::: "volatile") };
+    fp
+}
+
+/// Send an event to every core, waking any that are waiting in `wfe`.
+#[inline(always)]
+pub fn sev() {
+    unsafe { asm!("sev" :::: "volatile") };
+}
+
+/// Data synchronization barrier: completes once every memory access before
+/// it has completed.
+#[inline(always)]
+pub fn dsb() {
+    unsafe { asm!("dsb sy" ::: "memory" : "volatile") };
+}
+
+/// Unmask IRQs at the current exception level.
+#[inline(always)]
+pub fn enable_irq() {
+    unsafe { asm!("msr daifclr, #2" ::: "memory" : "volatile") };
+}
+
+/// Mask IRQs at the current exception level. A pending IRQ still wakes the
+/// core from `wfi`; it is taken once IRQs are unmasked again.
+#[inline(always)]
+pub fn disable_irq() {
+    unsafe { asm!("msr daifset, #2" ::: "memory" : "volatile") };
+}
+
+/// Instruction synchronization barrier: makes the effects of preceding
+/// system register writes visible to the instructions that follow.
+#[inline(always)]
+pub fn isb() {
+    unsafe { asm!("isb" ::: "memory" : "volatile") };
+}
+
+/// Cleans and invalidates the data cache line holding `addr` to the point
+/// of coherency, so that observers that bypass the cache, like a core with
+/// its MMU off, see what was written there.
+#[inline(always)]
+pub fn clean_dcache_line(addr: usize) {
+    unsafe { asm!("dc civac, $0" :: "r"(addr) : "memory" : "volatile") };
+}
+
+/// Invalidates every EL1&0 TLB entry on every core in the inner shareable
+/// domain and waits for the invalidation to complete.
+#[inline(always)]
+pub fn tlb_flush_all() {
+    unsafe {
+        asm!("dsb ishst
+              tlbi vmalle1is
+              dsb ish
+              isb" ::: "memory" : "volatile")
+    };
+}
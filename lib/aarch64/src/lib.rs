@@ -0,0 +1,12 @@
+#![feature(asm)]
+#![no_std]
+
+//! Thin wrappers around AArch64 instructions and registers that have no
+//! stable Rust equivalent.
+
+pub mod asm;
+pub mod regs;
+pub mod vmsa;
+
+pub use self::asm::*;
+pub use self::regs::*;
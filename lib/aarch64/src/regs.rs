@@ -0,0 +1,112 @@
+/// Returns the affinity level 0 field of `MPIDR_EL1`: the index of the core
+/// this is running on, `0` through `3` on the Raspberry Pi 3.
+#[inline(always)]
+pub fn affinity() -> usize {
+    let mpidr: usize;
+    unsafe { asm!("mrs $0, mpidr_el1" : "=r"(mpidr) ::: "volatile") };
+    mpidr & 0b11
+}
+
+/// Returns the exception level this is running at, `0` through `3`.
+#[inline(always)]
+pub fn current_el() -> u8 {
+    let el: u64;
+    unsafe { asm!("mrs $0, CurrentEL" : "=r"(el) ::: "volatile") };
+    ((el >> 2) & 0b11) as u8
+}
+
+/// Returns `FAR_EL1`: the faulting virtual address of the last data or
+/// instruction abort taken to EL1.
+#[inline(always)]
+pub fn far() -> usize {
+    let far: usize;
+    unsafe { asm!("mrs $0, far_el1" : "=r"(far) ::: "volatile") };
+    far
+}
+
+/// Returns `true` if IRQs are masked at the current exception level.
+#[inline(always)]
+pub fn irq_masked() -> bool {
+    let daif: u64;
+    unsafe { asm!("mrs $0, daif" : "=r"(daif) ::: "volatile") };
+    daif & (1 << 7) != 0
+}
+
+/// `SCTLR_EL1` bits controlling the MMU and caches.
+pub mod sctlr {
+    /// Stage 1 address translation for EL1&0.
+    pub const M: u64 = 1 << 0;
+    /// Data and unified caches.
+    pub const C: u64 = 1 << 2;
+    /// Instruction cache.
+    pub const I: u64 = 1 << 12;
+}
+
+/// Returns `SCTLR_EL1`.
+#[inline(always)]
+pub fn sctlr_el1() -> u64 {
+    let sctlr: u64;
+    unsafe { asm!("mrs $0, sctlr_el1" : "=r"(sctlr) ::: "volatile") };
+    sctlr
+}
+
+/// Writes `SCTLR_EL1` and synchronizes the change.
+///
+/// # Safety
+///
+/// Turning the MMU on requires `MAIR_EL1`, `TCR_EL1` and `TTBR0_EL1` to
+/// describe valid tables that identity map the code doing it.
+#[inline(always)]
+pub unsafe fn set_sctlr_el1(sctlr: u64) {
+    asm!("msr sctlr_el1, $0
+          isb" :: "r"(sctlr) : "memory" : "volatile");
+}
+
+/// Returns `true` if the MMU is on for EL1&0.
+#[inline(always)]
+pub fn is_mmu_enabled() -> bool {
+    sctlr_el1() & sctlr::M != 0
+}
+
+/// Writes `MAIR_EL1`, the memory attributes translation table entries
+/// index into.
+///
+/// # Safety
+///
+/// Changing an attribute in use by live mappings is unpredictable.
+#[inline(always)]
+pub unsafe fn set_mair_el1(mair: u64) {
+    asm!("msr mair_el1, $0" :: "r"(mair) :: "volatile");
+}
+
+/// Writes `TCR_EL1`, the translation control register.
+///
+/// # Safety
+///
+/// The new configuration must match the live translation tables.
+#[inline(always)]
+pub unsafe fn set_tcr_el1(tcr: u64) {
+    asm!("msr tcr_el1, $0" :: "r"(tcr) :: "volatile");
+}
+
+/// Returns `TTBR0_EL1`, the base address of the lower half's translation
+/// table.
+#[inline(always)]
+pub fn ttbr0_el1() -> u64 {
+    let ttbr: u64;
+    unsafe { asm!("mrs $0, ttbr0_el1" : "=r"(ttbr) ::: "volatile") };
+    ttbr
+}
+
+/// Writes `TTBR0_EL1` and flushes the TLB.
+///
+/// # Safety
+///
+/// `ttbr` must point to a valid translation table that maps the running
+/// code and stack.
+#[inline(always)]
+pub unsafe fn set_ttbr0_el1(ttbr: u64) {
+    asm!("msr ttbr0_el1, $0
+          isb" :: "r"(ttbr) : "memory" : "volatile");
+    crate::tlb_flush_all();
+}
@@ -0,0 +1,160 @@
+//! Translation table formats for the VMSAv8-64 64KiB translation granule.
+//!
+//! With a 64KiB granule and at most 42 bits of virtual address, a walk
+//! starts at level 2: an L2 table of 8192 entries, each covering 512MiB and
+//! pointing to an L3 table of 8192 entries, each mapping one 64KiB page
+//! (ref: D5.2.6).
+
+/// The size of a page and of every translation table.
+pub const PAGE_SIZE: usize = 64 * 1024;
+
+/// The number of entries in a translation table.
+pub const ENTRIES: usize = 8192;
+
+/// The size of the region one L2 entry covers.
+pub const L2_REGION_SIZE: usize = PAGE_SIZE * ENTRIES;
+
+/// `MAIR_EL1` attribute indices, for the `ATTR` field of an L3 entry. See
+/// `MAIR_VALUE` for what each one means.
+pub const ATTR_MEM: u64 = 0;
+pub const ATTR_DEV: u64 = 1;
+pub const ATTR_NC: u64 = 2;
+
+/// `MAIR_EL1` value: index 0 is normal write-back cacheable memory, index 1
+/// is nGnRE device memory and index 2 is normal non-cacheable memory.
+pub const MAIR_VALUE: u64 = 0xFF | (0x04 << 8) | (0x44 << 16);
+
+macro_rules! raw_entry {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        #[derive(Copy, Clone, Default, PartialEq, Eq)]
+        pub struct $name(u64);
+
+        impl $name {
+            /// Returns an invalid entry with every bit clear.
+            pub const fn new() -> $name {
+                $name(0)
+            }
+
+            /// Returns the raw value of the entry.
+            pub fn get(&self) -> u64 {
+                self.0
+            }
+
+            /// Sets the raw value of the entry.
+            pub fn set(&mut self, value: u64) {
+                self.0 = value;
+            }
+
+            /// Returns the field `mask`, shifted down to bit 0.
+            pub fn get_value(&self, mask: u64) -> u64 {
+                (self.0 & mask) >> mask.trailing_zeros()
+            }
+
+            /// Sets the field `mask` to `value`, given starting at bit 0.
+            pub fn set_value(&mut self, value: u64, mask: u64) -> &mut $name {
+                self.0 = (self.0 & !mask) | ((value << mask.trailing_zeros()) & mask);
+                self
+            }
+
+            /// Returns the field `mask` in place.
+            pub fn get_masked(&self, mask: u64) -> u64 {
+                self.0 & mask
+            }
+
+            /// Sets the field `mask` to the bits of `value` in place.
+            pub fn set_masked(&mut self, value: u64, mask: u64) -> &mut $name {
+                self.0 = (self.0 & !mask) | (value & mask);
+                self
+            }
+
+            /// Returns `true` if the entry's valid bit is set.
+            pub fn is_valid(&self) -> bool {
+                self.0 & Self::VALID != 0
+            }
+        }
+
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "{}({:#018x})", stringify!($name), self.0)
+            }
+        }
+    };
+}
+
+raw_entry!(
+    /// An L2 table descriptor, pointing to an L3 table (ref: D5.3.1).
+    RawL2Entry
+);
+
+impl RawL2Entry {
+    pub const VALID: u64 = 1 << 0;
+    pub const TYPE: u64 = 1 << 1;
+    pub const ADDR: u64 = 0xFFFF_FFFF << 16;
+
+    pub const TYPE_BLOCK: u64 = 0;
+    pub const TYPE_TABLE: u64 = 1;
+}
+
+raw_entry!(
+    /// An L3 page descriptor, mapping one page (ref: D5.3.2).
+    RawL3Entry
+);
+
+impl RawL3Entry {
+    pub const VALID: u64 = 1 << 0;
+    pub const TYPE: u64 = 1 << 1;
+    pub const ATTR: u64 = 0b111 << 2;
+    pub const NS: u64 = 1 << 5;
+    pub const AP: u64 = 0b11 << 6;
+    pub const SH: u64 = 0b11 << 8;
+    pub const AF: u64 = 1 << 10;
+    pub const ADDR: u64 = 0xFFFF_FFFF << 16;
+    pub const PXN: u64 = 1 << 53;
+    pub const UXN: u64 = 1 << 54;
+
+    pub const TYPE_PAGE: u64 = 1;
+
+    /// Access permissions (ref: D5.4.4): read/write at EL1 only, read/write
+    /// at EL1 and EL0, read-only at EL1 only, read-only at EL1 and EL0.
+    pub const AP_KERN_RW: u64 = 0b00;
+    pub const AP_USER_RW: u64 = 0b01;
+    pub const AP_KERN_RO: u64 = 0b10;
+    pub const AP_USER_RO: u64 = 0b11;
+
+    /// Shareability: non-, outer and inner shareable.
+    pub const SH_NS: u64 = 0b00;
+    pub const SH_OSH: u64 = 0b10;
+    pub const SH_ISH: u64 = 0b11;
+}
+
+/// `TCR_EL1` fields used to describe the tables above (ref: D13.2.120).
+pub mod tcr {
+    /// Size offset of the TTBR0 region: 64 - 22 = 42-bit virtual addresses.
+    pub const T0SZ: u64 = 22;
+    /// Walks of TTBR0 tables are inner and outer write-back cacheable and
+    /// inner shareable.
+    pub const IRGN0_WB: u64 = 0b01 << 8;
+    pub const ORGN0_WB: u64 = 0b01 << 10;
+    pub const SH0_ISH: u64 = 0b11 << 12;
+    /// TTBR0 uses the 64KiB granule.
+    pub const TG0_64K: u64 = 0b01 << 14;
+    /// Walks of TTBR1 are disabled; only the lower half is mapped.
+    pub const EPD1: u64 = 1 << 23;
+    /// TTBR1 uses the 64KiB granule. Unused, but must be a valid encoding.
+    pub const TG1_64K: u64 = 0b11 << 30;
+    /// 32-bit (4GiB) physical addresses, enough for all of the Pi's RAM and
+    /// peripherals.
+    pub const IPS_32: u64 = 0b000 << 32;
+}
+
+/// The `TCR_EL1` value for the tables above.
+pub const TCR_VALUE: u64 = tcr::T0SZ
+    | tcr::IRGN0_WB
+    | tcr::ORGN0_WB
+    | tcr::SH0_ISH
+    | tcr::TG0_64K
+    | tcr::EPD1
+    | tcr::TG1_64K
+    | tcr::IPS_32;
@@ -0,0 +1,162 @@
+use core::alloc::Layout;
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+use crate::util::align_up;
+use crate::LocalAlloc;
+
+/// log2 of the smallest block: one `usize`, enough to link a free block.
+const MIN_SHIFT: usize = mem::size_of::<usize>().trailing_zeros() as usize;
+
+/// log2 of the largest block.
+const MAX_SHIFT: usize = 31;
+
+/// The number of bins. Bin `k` holds free blocks of `1 << (MIN_SHIFT + k)`
+/// bytes.
+const NUM_BINS: usize = MAX_SHIFT - MIN_SHIFT + 1;
+
+/// Returns the size of the blocks in bin `bin`.
+fn bin_size(bin: usize) -> usize {
+    1 << (MIN_SHIFT + bin)
+}
+
+/// A power-of-two "bin" allocator.
+///
+/// Every block is a power of two in size and aligned to its own size, so a
+/// block of the right size satisfies any alignment up to that size. Freed
+/// blocks go on the free list of their bin and are handed out again first.
+/// Fresh blocks are carved off the front of the region; once it runs out,
+/// a free block from a larger bin is split in halves down to the size
+/// needed. Memory skipped to align a fresh block is split into blocks for
+/// the smaller bins rather than wasted.
+pub struct Allocator {
+    /// The address of the first free block in each bin, or 0. Each free
+    /// block holds the address of the next in its first word.
+    bins: [usize; NUM_BINS],
+    start: usize,
+    current: usize,
+    end: usize,
+}
+
+impl Allocator {
+    /// Creates a new bin allocator that will allocate memory from the region
+    /// starting at address `start` and ending at address `end`.
+    pub fn new(start: usize, end: usize) -> Allocator {
+        let start = align_up(start, bin_size(0)).unwrap_or(end).min(end);
+        Allocator { bins: [0; NUM_BINS], start, current: start, end }
+    }
+
+    /// Returns the bin whose blocks fit `layout`, if any.
+    fn bin_for(layout: Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align()).checked_next_power_of_two()?;
+        let shift = size.trailing_zeros() as usize;
+        if shift > MAX_SHIFT {
+            return None;
+        }
+        Some(shift.saturating_sub(MIN_SHIFT))
+    }
+
+    unsafe fn push(&mut self, bin: usize, block: usize) {
+        (block as *mut usize).write(self.bins[bin]);
+        self.bins[bin] = block;
+    }
+
+    unsafe fn pop(&mut self, bin: usize) -> Option<usize> {
+        match self.bins[bin] {
+            0 => None,
+            block => {
+                self.bins[bin] = (block as *const usize).read();
+                Some(block)
+            }
+        }
+    }
+
+    /// Adds the memory from `start` to `end` to the bins, as the largest
+    /// aligned blocks that fit.
+    unsafe fn free_range(&mut self, mut start: usize, end: usize) {
+        while end - start >= bin_size(0) {
+            let bin = (0..NUM_BINS)
+                .rev()
+                .find(|&bin| start & (bin_size(bin) - 1) == 0 && end - start >= bin_size(bin))
+                .unwrap_or(0);
+            self.push(bin, start);
+            start += bin_size(bin);
+        }
+    }
+
+    /// Carves a block for `bin` off the front of the unused region.
+    unsafe fn carve(&mut self, bin: usize) -> Option<usize> {
+        let size = bin_size(bin);
+        let block = align_up(self.current, size)?;
+        if block.checked_add(size)? > self.end {
+            return None;
+        }
+
+        self.free_range(self.current, block);
+        self.current = block + size;
+        Some(block)
+    }
+
+    /// Splits a block from the smallest non-empty bin larger than `bin`,
+    /// keeping the lower half and freeing the upper one at each step.
+    unsafe fn split(&mut self, bin: usize) -> Option<usize> {
+        let larger = (bin + 1..NUM_BINS).find(|&larger| self.bins[larger] != 0)?;
+        let block = self.pop(larger)?;
+        for half in (bin..larger).rev() {
+            self.push(half, block + bin_size(half));
+        }
+        Some(block)
+    }
+
+    /// Returns the number of free blocks in `bin`.
+    fn free_blocks(&self, bin: usize) -> usize {
+        let mut count = 0;
+        let mut block = self.bins[bin];
+        while block != 0 {
+            count += 1;
+            block = unsafe { (block as *const usize).read() };
+        }
+        count
+    }
+}
+
+impl LocalAlloc for Allocator {
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        let bin = match Allocator::bin_for(layout) {
+            Some(bin) => bin,
+            None => return ptr::null_mut(),
+        };
+
+        self.pop(bin)
+            .or_else(|| self.carve(bin))
+            .or_else(|| self.split(bin))
+            .map_or(ptr::null_mut(), |block| block as *mut u8)
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        if let Some(bin) = Allocator::bin_for(layout) {
+            self.push(bin, ptr as usize);
+        }
+    }
+}
+
+impl fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "bin allocator: {:#x}..{:#x}, {} of {} bytes carved",
+            self.start,
+            self.end,
+            self.current - self.start,
+            self.end - self.start
+        )?;
+        for bin in 0..NUM_BINS {
+            let count = self.free_blocks(bin);
+            if count > 0 {
+                writeln!(f, "  {:>10} bytes: {} free", bin_size(bin), count)?;
+            }
+        }
+        Ok(())
+    }
+}
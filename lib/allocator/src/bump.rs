@@ -0,0 +1,54 @@
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr;
+
+use crate::util::align_up;
+use crate::LocalAlloc;
+
+/// A "bump" allocator: hands out memory from the front of its region and
+/// never reuses it. Freeing does nothing.
+pub struct Allocator {
+    start: usize,
+    current: usize,
+    end: usize,
+}
+
+impl Allocator {
+    /// Creates a new bump allocator that will allocate memory from the
+    /// region starting at address `start` and ending at address `end`.
+    pub fn new(start: usize, end: usize) -> Allocator {
+        Allocator { start, current: start, end }
+    }
+}
+
+impl LocalAlloc for Allocator {
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        let start = match align_up(self.current, layout.align()) {
+            Some(start) => start,
+            None => return ptr::null_mut(),
+        };
+
+        match start.checked_add(layout.size()) {
+            Some(end) if end <= self.end => {
+                self.current = end;
+                start as *mut u8
+            }
+            _ => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&mut self, _ptr: *mut u8, _layout: Layout) {}
+}
+
+impl fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "bump allocator: {:#x}..{:#x}, {} of {} bytes used",
+            self.start,
+            self.end,
+            self.current - self.start,
+            self.end - self.start
+        )
+    }
+}
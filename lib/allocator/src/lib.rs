@@ -0,0 +1,37 @@
+#![cfg_attr(not(test), no_std)]
+
+//! Memory allocators for a fixed region of memory, for use as the backing
+//! store of a kernel heap.
+//!
+//! Each allocator manages the addresses between a start and an end it is
+//! given and hands out blocks of it satisfying a `Layout`. They are not
+//! thread-safe; wrap one in a lock to implement `GlobalAlloc`.
+
+#[cfg(test)]
+mod tests;
+
+pub mod bin;
+pub mod bump;
+pub mod util;
+
+use core::alloc::Layout;
+
+/// An allocator that manages memory it owns exclusively, through `&mut`.
+pub trait LocalAlloc {
+    /// Allocates memory fitting `layout`: at least `layout.size()` bytes,
+    /// aligned to `layout.align()`. Returns a null pointer if no such block
+    /// is available.
+    ///
+    /// # Safety
+    ///
+    /// `layout.size()` must be non-zero.
+    unsafe fn alloc(&mut self, layout: Layout) -> *mut u8;
+
+    /// Returns the block at `ptr` to the allocator.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `alloc` on this allocator with the
+    /// same `layout`, and must not be used afterwards.
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout);
+}
@@ -0,0 +1,234 @@
+use std::alloc::{self, Layout};
+
+use crate::util::{align_down, align_up};
+use crate::{bin, bump, LocalAlloc};
+
+/// A region of host memory for an allocator to manage, freed on drop.
+struct Region {
+    base: *mut u8,
+    layout: Layout,
+}
+
+impl Region {
+    /// Allocates `size` bytes aligned to 64KiB.
+    fn new(size: usize) -> Region {
+        let layout = Layout::from_size_align(size, 64 * 1024).unwrap();
+        let base = unsafe { alloc::alloc(layout) };
+        assert!(!base.is_null());
+        Region { base, layout }
+    }
+
+    fn start(&self) -> usize {
+        self.base as usize
+    }
+
+    fn end(&self) -> usize {
+        self.start() + self.layout.size()
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.base, self.layout) }
+    }
+}
+
+fn layout(size: usize, align: usize) -> Layout {
+    Layout::from_size_align(size, align).unwrap()
+}
+
+/// Allocates `layout` from `a`, checking that the block is aligned and
+/// inside `region`, and fills it with `fill`.
+fn alloc_checked<A: LocalAlloc>(a: &mut A, region: &Region, layout: Layout, fill: u8) -> *mut u8 {
+    let ptr = unsafe { a.alloc(layout) };
+    assert!(!ptr.is_null(), "allocation of {:?} failed", layout);
+
+    let addr = ptr as usize;
+    assert_eq!(addr % layout.align(), 0, "{:#x} is not aligned for {:?}", addr, layout);
+    assert!(addr >= region.start() && addr + layout.size() <= region.end());
+
+    unsafe { ptr.write_bytes(fill, layout.size()) };
+    ptr
+}
+
+/// Checks that the block at `ptr` still holds `fill`, i.e. that no other
+/// allocation overlapped it.
+fn check_fill(ptr: *mut u8, layout: Layout, fill: u8) {
+    let block = unsafe { std::slice::from_raw_parts(ptr, layout.size()) };
+    assert!(block.iter().all(|&b| b == fill), "block at {:?} was overwritten", ptr);
+}
+
+/// Layouts of every combination of a few sizes and alignments.
+fn layouts() -> Vec<Layout> {
+    let mut layouts = Vec::new();
+    for &size in &[1, 3, 8, 13, 64, 100, 4096, 5000] {
+        for &align in &[1, 2, 8, 16, 256, 4096, 64 * 1024] {
+            layouts.push(layout(size, align));
+        }
+    }
+    layouts
+}
+
+/// Makes every allocation in `layouts()` from `a`, checks none of them
+/// overlap, then frees them.
+fn alloc_all<A: LocalAlloc>(a: &mut A, region: &Region) {
+    let blocks: Vec<_> = layouts()
+        .into_iter()
+        .enumerate()
+        .map(|(i, layout)| (alloc_checked(a, region, layout, i as u8), layout, i as u8))
+        .collect();
+
+    for &(ptr, layout, fill) in &blocks {
+        check_fill(ptr, layout, fill);
+    }
+    for (ptr, layout, _) in blocks {
+        unsafe { a.dealloc(ptr, layout) };
+    }
+}
+
+#[test]
+fn align_util() {
+    assert_eq!(align_down(0x1234, 0x1000), 0x1000);
+    assert_eq!(align_down(0x1000, 0x1000), 0x1000);
+    assert_eq!(align_down(7, 1), 7);
+    assert_eq!(align_up(0x1234, 0x1000), Some(0x2000));
+    assert_eq!(align_up(0x1000, 0x1000), Some(0x1000));
+    assert_eq!(align_up(0, 8), Some(0));
+    assert_eq!(align_up(usize::max_value() - 2, 8), None);
+}
+
+#[test]
+#[should_panic]
+fn align_util_rejects_non_power_of_two() {
+    align_up(0x1000, 3);
+}
+
+#[test]
+fn bump_alignment() {
+    let region = Region::new(1 << 20);
+    // Start off any useful alignment.
+    let mut a = bump::Allocator::new(region.start() + 1, region.end());
+    alloc_all(&mut a, &region);
+}
+
+#[test]
+fn bump_is_sequential() {
+    let region = Region::new(1 << 16);
+    let mut a = bump::Allocator::new(region.start(), region.end());
+
+    let first = alloc_checked(&mut a, &region, layout(10, 1), 1);
+    let second = alloc_checked(&mut a, &region, layout(4, 4), 2);
+    let third = alloc_checked(&mut a, &region, layout(1, 1), 3);
+
+    assert_eq!(first as usize, region.start());
+    assert_eq!(second as usize, region.start() + 12);
+    assert_eq!(third as usize, region.start() + 16);
+}
+
+#[test]
+fn bump_never_reuses() {
+    let region = Region::new(1 << 16);
+    let mut a = bump::Allocator::new(region.start(), region.end());
+
+    let first = alloc_checked(&mut a, &region, layout(64, 8), 1);
+    unsafe { a.dealloc(first, layout(64, 8)) };
+    let second = alloc_checked(&mut a, &region, layout(64, 8), 2);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn bump_exhaustion() {
+    let region = Region::new(1 << 16);
+    let mut a = bump::Allocator::new(region.start(), region.end());
+
+    alloc_checked(&mut a, &region, layout(1 << 15, 1), 1);
+    alloc_checked(&mut a, &region, layout(1 << 15, 1), 2);
+    assert!(unsafe { a.alloc(layout(1, 1)) }.is_null());
+
+    // Alignment padding counts too.
+    let mut a = bump::Allocator::new(region.start() + 1, region.end());
+    assert!(unsafe { a.alloc(layout(1 << 15, 1 << 16)) }.is_null());
+}
+
+#[test]
+fn bin_alignment() {
+    let region = Region::new(1 << 20);
+    let mut a = bin::Allocator::new(region.start() + 1, region.end());
+    alloc_all(&mut a, &region);
+    // Everything was freed, so the same allocations fit again.
+    alloc_all(&mut a, &region);
+}
+
+#[test]
+fn bin_reuses_freed_blocks() {
+    let region = Region::new(1 << 16);
+    let mut a = bin::Allocator::new(region.start(), region.end());
+
+    let first = alloc_checked(&mut a, &region, layout(24, 8), 1);
+    unsafe { a.dealloc(first, layout(24, 8)) };
+
+    // Anything in the same size class gets the same block back.
+    let second = alloc_checked(&mut a, &region, layout(32, 16), 2);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn bin_reuses_alignment_padding() {
+    let region = Region::new(1 << 16);
+    let mut a = bin::Allocator::new(region.start(), region.end());
+
+    // Aligning the second block skips 0x1000 - 8 bytes, which should be
+    // handed out before anything new is carved.
+    let first = alloc_checked(&mut a, &region, layout(8, 8), 1);
+    let second = alloc_checked(&mut a, &region, layout(0x1000, 0x1000), 2);
+    assert_eq!(second as usize, region.start() + 0x1000);
+
+    let third = alloc_checked(&mut a, &region, layout(0x800, 8), 3);
+    assert_eq!(third as usize, region.start() + 0x800);
+    check_fill(first, layout(8, 8), 1);
+    check_fill(second, layout(0x1000, 0x1000), 2);
+}
+
+#[test]
+fn bin_splits_larger_blocks() {
+    let region = Region::new(1 << 16);
+    let mut a = bin::Allocator::new(region.start(), region.end());
+
+    // Take the whole region as one block, then give it back.
+    let whole = alloc_checked(&mut a, &region, layout(1 << 16, 8), 1);
+    assert!(unsafe { a.alloc(layout(8, 8)) }.is_null());
+    unsafe { a.dealloc(whole, layout(1 << 16, 8)) };
+
+    // Small allocations now come from splitting it.
+    let mut blocks = Vec::new();
+    for i in 0..(1 << 16) / 64 {
+        blocks.push(alloc_checked(&mut a, &region, layout(64, 64), i as u8));
+    }
+    assert!(unsafe { a.alloc(layout(64, 64)) }.is_null());
+    for (i, &block) in blocks.iter().enumerate() {
+        check_fill(block, layout(64, 64), i as u8);
+    }
+}
+
+#[test]
+fn bin_exhaustion() {
+    let region = Region::new(1 << 16);
+    let mut a = bin::Allocator::new(region.start(), region.end());
+
+    assert!(unsafe { a.alloc(layout((1 << 16) + 1, 8)) }.is_null());
+    assert!(unsafe { a.alloc(layout(1 << 40, 8)) }.is_null());
+    alloc_checked(&mut a, &region, layout(1 << 15, 8), 1);
+    alloc_checked(&mut a, &region, layout(1 << 15, 8), 2);
+    assert!(unsafe { a.alloc(layout(1, 1)) }.is_null());
+}
+
+#[test]
+fn bin_debug_lists_free_blocks() {
+    let region = Region::new(1 << 16);
+    let mut a = bin::Allocator::new(region.start(), region.end());
+
+    let block = alloc_checked(&mut a, &region, layout(100, 8), 1);
+    unsafe { a.dealloc(block, layout(100, 8)) };
+    let debug = format!("{:?}", a);
+    assert!(debug.contains("128 bytes: 1 free"), "{}", debug);
+}
@@ -0,0 +1,20 @@
+/// Aligns `addr` downwards to the nearest multiple of `align`.
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of 2.
+pub fn align_down(addr: usize, align: usize) -> usize {
+    assert!(align.is_power_of_two(), "alignment must be a power of 2");
+    addr & !(align - 1)
+}
+
+/// Aligns `addr` upwards to the nearest multiple of `align`. Returns `None`
+/// if that would overflow.
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of 2.
+pub fn align_up(addr: usize, align: usize) -> Option<usize> {
+    assert!(align.is_power_of_two(), "alignment must be a power of 2");
+    addr.checked_add(align - 1).map(|addr| addr & !(align - 1))
+}
@@ -0,0 +1,46 @@
+#!/bin/sh
+# Builds a FAT32 file system with dosfstools and fills it with mtools, so the
+# `fat32` crate's tests can check it against a volume it didn't lay out
+# itself. Unlike the images `mkimage.py` builds, the output has no partition
+# table: it is the file system alone.
+#
+# The `mkfs_volume` test runs this and asserts on the files it copies in, so
+# keep the two in sync. It needs `mkfs.vfat` (dosfstools) and `mmd` and
+# `mcopy` (mtools):
+#
+#     ./mkfs-image.sh fat32-mkfs.img
+
+set -eu
+
+if [ $# -ne 1 ]; then
+    echo "usage: $0 <output>" >&2
+    exit 1
+fi
+out=$1
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+# FAT32 needs at least 65525 clusters: with one 512-byte sector per cluster,
+# that takes a little over 32 MiB.
+rm -f "$out"
+mkfs.vfat -C -F 32 -S 512 -s 1 -n MKFS -i 5EED0001 "$out" 33792
+
+# mtools otherwise refuses volumes whose geometry doesn't match a floppy.
+export MTOOLS_SKIP_CHECK=1
+
+printf 'Hello from mtools!\n' > "$tmp/hello.txt"
+printf 'nested\n' > "$tmp/nested.txt"
+# The same bytes as `big.bin` in the images `mkimage.py` builds.
+python3 -c 'import sys; sys.stdout.buffer.write(bytes((i * 31 + i // 251) % 256 for i in range(40000)))' \
+    > "$tmp/big.bin"
+
+mmd -i "$out" "::/Some Directory" "::/Some Directory/deeper"
+mcopy -i "$out" "$tmp/hello.txt" "::/HELLO.TXT"
+mcopy -i "$out" "$tmp/big.bin" "::/A long file name.bin"
+mcopy -i "$out" "$tmp/nested.txt" "::/Some Directory/deeper/nested.txt"
+# Enough entries to spread the directory over several clusters.
+for i in $(seq -w 0 39); do
+    printf 'note %s\n' "$i" > "$tmp/note.txt"
+    mcopy -i "$out" "$tmp/note.txt" "::/Some Directory/NOTE-$i.TXT"
+done
@@ -0,0 +1,365 @@
+#!/usr/bin/env python3
+"""Builds the FAT32 disk images the `fat32` crate's tests read.
+
+Each image is an MBR-partitioned disk with a single FAT32 partition holding
+the same tree of files: short and long names, lower-case 8.3 names, a
+deleted entry, a volume label, attributes, timestamps, a directory spanning
+several clusters and a file whose cluster chain is fragmented. The tests in
+`src/tests.rs` assert on exactly this tree, so keep the two in sync.
+
+These images are laid out here rather than by a real formatter, so
+`mkfs-image.sh` builds one more volume with dosfstools and mtools for the
+`mkfs_volume` test.
+
+The output is deterministic. Regenerate the images with
+
+    ./mkimage.py [output-dir]
+"""
+
+import os
+import struct
+import sys
+
+MBR_SECTOR_SIZE = 512
+PARTITION_START = 63
+PARTITION_TYPE_FAT32_LBA = 0x0C
+
+RESERVED_SECTORS = 32
+NUM_FATS = 2
+ROOT_CLUSTER = 2
+FSINFO_SECTOR = 1
+BACKUP_BOOT_SECTOR = 6
+
+ATTR_READ_ONLY = 0x01
+ATTR_HIDDEN = 0x02
+ATTR_SYSTEM = 0x04
+ATTR_VOLUME_ID = 0x08
+ATTR_DIRECTORY = 0x10
+ATTR_ARCHIVE = 0x20
+ATTR_LFN = 0x0F
+
+LOWER_BASE = 0x08
+LOWER_EXT = 0x10
+
+EOC = 0x0FFFFFFF
+LFN_CHARS = 13
+
+
+def dos_date(year, month, day):
+    return ((year - 1980) << 9) | (month << 5) | day
+
+
+def dos_time(hour, minute, second):
+    return (hour << 11) | (minute << 5) | (second // 2)
+
+
+class Times:
+    def __init__(self, created, accessed, modified, tenths=0):
+        self.created = created
+        self.accessed = accessed
+        self.modified = modified
+        self.tenths = tenths
+
+
+DEFAULT_TIMES = Times(
+    created=(dos_date(2019, 1, 1), dos_time(0, 0, 0)),
+    accessed=dos_date(2019, 1, 1),
+    modified=(dos_date(2019, 1, 1), dos_time(0, 0, 0)),
+)
+
+HELLO_TIMES = Times(
+    created=(dos_date(2019, 3, 1), dos_time(12, 34, 56)),
+    accessed=dos_date(2019, 3, 3),
+    modified=(dos_date(2019, 3, 2), dos_time(8, 15, 30)),
+    tenths=100,
+)
+
+
+class Node:
+    """A file or directory. `short` is the 11-byte 8.3 name as stored on
+    disk; a long name entry is written whenever `name` differs from what the
+    8.3 name and the lower-case flags spell."""
+
+    def __init__(self, name, short, attrs=ATTR_ARCHIVE, lower=0, times=DEFAULT_TIMES, data=b"", children=None,
+                 deleted=False):
+        assert len(short) == 11
+        self.name = name
+        self.short = short.encode("ascii")
+        self.attrs = attrs
+        self.lower = lower
+        self.times = times
+        self.data = data
+        self.children = children
+        self.deleted = deleted
+        self.clusters = []
+
+    @property
+    def is_dir(self):
+        return self.children is not None
+
+    def short_display(self):
+        base = self.short[:8].decode().rstrip(" ")
+        ext = self.short[8:].decode().rstrip(" ")
+        if self.lower & LOWER_BASE:
+            base = base.lower()
+        if self.lower & LOWER_EXT:
+            ext = ext.lower()
+        return base + "." + ext if ext else base
+
+    def lfn_units(self):
+        if self.name == self.short_display():
+            return None
+        encoded = self.name.encode("utf-16-le")
+        units = list(struct.unpack("<%dH" % (len(encoded) // 2), encoded))
+        if len(units) % LFN_CHARS:
+            units.append(0x0000)
+        while len(units) % LFN_CHARS:
+            units.append(0xFFFF)
+        return units
+
+    def entry_count(self):
+        units = self.lfn_units()
+        return 1 + (len(units) // LFN_CHARS if units else 0)
+
+
+def big_data(length):
+    return bytes((i * 31 + i // 251) & 0xFF for i in range(length))
+
+
+def tree():
+    docs = [Node("note-%02d.txt" % i, "NOTE-%02d TXT" % i, lower=LOWER_BASE | LOWER_EXT,
+                 data=("note %02d\n" % i).encode()) for i in range(40)]
+    deep = Node("deep", "DEEP       ", attrs=ATTR_DIRECTORY, lower=LOWER_BASE, children=[
+        Node("file.txt", "FILE    TXT", lower=LOWER_BASE | LOWER_EXT, data=b"deep\n"),
+    ])
+    nested = Node("nested", "NESTED     ", attrs=ATTR_DIRECTORY, lower=LOWER_BASE, children=[deep])
+
+    return [
+        Node("RUSTOS", "RUSTOS     ", attrs=ATTR_VOLUME_ID),
+        Node("HELLO.TXT", "HELLO   TXT", times=HELLO_TIMES, data=b"Hello, world!\n"),
+        Node("README", "README     ", attrs=ATTR_READ_ONLY | ATTR_ARCHIVE, data=b"FAT32 test image\n"),
+        Node("Long File Name.txt", "LONGFI~1TXT", data=b"long\n"),
+        Node("gone.txt", "GONE    TXT", data=b"", deleted=True),
+        Node("A very long file name that needs four entries.md", "AVERYL~1MD ", data=b"# four\n"),
+        Node("ünïcødé.txt", "UNICOD~1TXT", data="ünïcødé\n".encode()),
+        Node("lower.txt", "LOWER   TXT", lower=LOWER_BASE | LOWER_EXT, data=b"lower\n"),
+        Node("mixed.TXT", "MIXED   TXT", lower=LOWER_BASE, data=b"mixed\n"),
+        Node("hidden.sys", "HIDDEN  SYS", attrs=ATTR_HIDDEN | ATTR_SYSTEM, lower=LOWER_BASE | LOWER_EXT, data=b""),
+        Node("empty.txt", "EMPTY   TXT", lower=LOWER_BASE | LOWER_EXT),
+        Node("big.bin", "BIG     BIN", lower=LOWER_BASE | LOWER_EXT, data=big_data(40000)),
+        Node("docs", "DOCS       ", attrs=ATTR_DIRECTORY, lower=LOWER_BASE, children=docs + [nested]),
+    ]
+
+
+def lfn_checksum(short):
+    total = 0
+    for byte in short:
+        total = (((total & 1) << 7) + (total >> 1) + byte) & 0xFF
+    return total
+
+
+def lfn_entries(node):
+    units = node.lfn_units()
+    if units is None:
+        return []
+
+    checksum = lfn_checksum(node.short)
+    count = len(units) // LFN_CHARS
+    entries = []
+    for seq in range(count, 0, -1):
+        chunk = units[(seq - 1) * LFN_CHARS:seq * LFN_CHARS]
+        order = seq | (0x40 if seq == count else 0)
+        if node.deleted:
+            order = 0xE5
+        entries.append(struct.pack("<B5HBBB6HH2H", order, *chunk[0:5], ATTR_LFN, 0, checksum, *chunk[5:11], 0,
+                                   *chunk[11:13]))
+    return entries
+
+
+def short_entry(short, attrs, lower, times, cluster, size, deleted=False):
+    if deleted:
+        short = b"\xe5" + short[1:]
+    created_date, created_time = times.created
+    modified_date, modified_time = times.modified
+    return struct.pack("<11sBBBHHHHHHHI", short, attrs, lower, times.tenths, created_time, created_date,
+                       times.accessed, cluster >> 16, modified_time, modified_date, cluster & 0xFFFF, size)
+
+
+class Volume:
+    def __init__(self, bytes_per_sector, sectors_per_cluster, total_sectors):
+        self.bps = bytes_per_sector
+        self.spc = sectors_per_cluster
+        self.total_sectors = total_sectors
+        self.cluster_size = bytes_per_sector * sectors_per_cluster
+
+        # The FAT has to describe every data cluster, and its size decides
+        # how many sectors are left for data clusters.
+        fat_sectors = 1
+        while True:
+            data_sectors = total_sectors - RESERVED_SECTORS - NUM_FATS * fat_sectors
+            self.clusters = data_sectors // sectors_per_cluster
+            needed = -(-(self.clusters + 2) * 4 // bytes_per_sector)
+            if needed <= fat_sectors:
+                break
+            fat_sectors = needed
+        self.fat_sectors = fat_sectors
+        self.data_start = RESERVED_SECTORS + NUM_FATS * fat_sectors
+
+        self.fat = [0] * (self.clusters + 2)
+        self.fat[0] = 0x0FFFFFF8
+        self.fat[1] = EOC
+        self.next = ROOT_CLUSTER
+        self.data = bytearray(total_sectors * bytes_per_sector)
+
+    def alloc(self, count):
+        clusters = list(range(self.next, self.next + count))
+        self.next += count
+        assert self.next <= self.clusters + 2, "volume is full"
+        return clusters
+
+    def chain(self, clusters):
+        for current, following in zip(clusters, clusters[1:] + [None]):
+            self.fat[current] = following if following is not None else EOC
+
+    def write_clusters(self, clusters, data):
+        for i, cluster in enumerate(clusters):
+            chunk = data[i * self.cluster_size:(i + 1) * self.cluster_size]
+            offset = (self.data_start + (cluster - 2) * self.spc) * self.bps
+            self.data[offset:offset + len(chunk)] = chunk
+
+    def clusters_for(self, size):
+        return -(-size // self.cluster_size)
+
+
+def dir_size(entries, is_root):
+    count = sum(node.entry_count() for node in entries) + (0 if is_root else 2)
+    return count * 32
+
+
+def allocate(volume, nodes):
+    """Assigns clusters to every node below a directory, depth first. The
+    big file takes every other cluster of a run so its chain has holes."""
+    for node in nodes:
+        if node.deleted or node.attrs & ATTR_VOLUME_ID:
+            continue
+        if node.is_dir:
+            node.clusters = volume.alloc(volume.clusters_for(dir_size(node.children, False)))
+            allocate(volume, node.children)
+        elif node.name == "big.bin":
+            node.clusters = volume.alloc(2 * volume.clusters_for(len(node.data)))[::2]
+        elif node.data:
+            node.clusters = volume.alloc(volume.clusters_for(len(node.data)))
+
+
+def first_cluster(node):
+    return node.clusters[0] if node.clusters else 0
+
+
+def write_dir(volume, clusters, nodes, parent=None, this=None):
+    entries = []
+    if this is not None:
+        dot_cluster = first_cluster(this)
+        # `..` of a directory in the root names cluster 0, not the root
+        # cluster.
+        dotdot_cluster = first_cluster(parent) if parent is not None else 0
+        entries.append(short_entry(b".          ", ATTR_DIRECTORY, 0, this.times, dot_cluster, 0))
+        entries.append(short_entry(b"..         ", ATTR_DIRECTORY, 0, this.times, dotdot_cluster, 0))
+
+    for node in nodes:
+        entries.extend(lfn_entries(node))
+        size = 0 if node.is_dir else len(node.data)
+        entries.append(short_entry(node.short, node.attrs, node.lower, node.times, first_cluster(node), size,
+                                   node.deleted))
+        if node.is_dir:
+            write_dir(volume, node.clusters, node.children, this, node)
+        elif node.clusters:
+            volume.chain(node.clusters)
+            volume.write_clusters(node.clusters, node.data)
+
+    volume.chain(clusters)
+    volume.write_clusters(clusters, b"".join(entries))
+
+
+def boot_sector(volume, label):
+    sector = bytearray(volume.bps)
+    struct.pack_into("<3s8sHBHBHHBHHHII", sector, 0, b"\xeb\x58\x90", b"RUSTOS  ", volume.bps, volume.spc,
+                     RESERVED_SECTORS, NUM_FATS, 0, 0, 0xF8, 0, 63, 255, PARTITION_START, volume.total_sectors)
+    struct.pack_into("<IHHIHH12sBBBI11s8s", sector, 0x24, volume.fat_sectors, 0, 0, ROOT_CLUSTER, FSINFO_SECTOR,
+                     BACKUP_BOOT_SECTOR, b"", 0x80, 0, 0x29, 0x1234ABCD, label, b"FAT32   ")
+    sector[510:512] = b"\x55\xaa"
+    return sector
+
+
+def fsinfo_sector(volume):
+    sector = bytearray(volume.bps)
+    free = sum(1 for entry in volume.fat[2:] if entry == 0)
+    struct.pack_into("<I", sector, 0, 0x41615252)
+    struct.pack_into("<IIII", sector, 484, 0x61417272, free, volume.next, 0)
+    struct.pack_into("<I", sector, 508, 0xAA550000)
+    return sector
+
+
+def build(bytes_per_sector, sectors_per_cluster, partition_bytes):
+    volume = Volume(bytes_per_sector, sectors_per_cluster, partition_bytes // bytes_per_sector)
+    nodes = tree()
+
+    # The root directory starts at cluster 2 but its second cluster is only
+    # allocated after everything else, so its chain isn't contiguous either.
+    root = volume.alloc(1)
+    allocate(volume, nodes)
+    root += volume.alloc(volume.clusters_for(dir_size(nodes, True)) - 1)
+    write_dir(volume, root, nodes)
+
+    bps = volume.bps
+    boot = boot_sector(volume, b"RUSTOS     ")
+    fsinfo = fsinfo_sector(volume)
+    for base in (0, BACKUP_BOOT_SECTOR):
+        volume.data[base * bps:(base + 1) * bps] = boot
+        volume.data[(base + 1) * bps:(base + 2) * bps] = fsinfo
+
+    fat = struct.pack("<%dI" % len(volume.fat), *volume.fat)
+    for i in range(NUM_FATS):
+        offset = (RESERVED_SECTORS + i * volume.fat_sectors) * bps
+        volume.data[offset:offset + len(fat)] = fat
+
+    return volume.data
+
+
+def chs(lba):
+    cylinder = lba // (255 * 63)
+    head = (lba // 63) % 255
+    sector = lba % 63 + 1
+    return bytes([head, sector | ((cylinder >> 2) & 0xC0), cylinder & 0xFF])
+
+
+def disk(partition):
+    sectors = len(partition) // MBR_SECTOR_SIZE
+    mbr = bytearray(MBR_SECTOR_SIZE)
+    struct.pack_into("<I", mbr, 0x1B8, 0x52535453)
+    entry = struct.pack("<B3sB3sII", 0x80, chs(PARTITION_START), PARTITION_TYPE_FAT32_LBA,
+                        chs(PARTITION_START + sectors - 1), PARTITION_START, sectors)
+    mbr[0x1BE:0x1BE + 16] = entry
+    mbr[510:512] = b"\x55\xaa"
+
+    gap = bytes((PARTITION_START - 1) * MBR_SECTOR_SIZE)
+    return bytes(mbr) + gap + bytes(partition)
+
+
+IMAGES = {
+    # name: (bytes per sector, sectors per cluster, partition size)
+    "fat32.img": (512, 1, 1 << 20),
+    "fat32-4k-clusters.img": (512, 8, 1 << 20),
+    # Logical sectors bigger than the 512-byte sectors of the disk.
+    "fat32-4k-sectors.img": (4096, 1, 1 << 20),
+}
+
+
+def main():
+    out = sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__))
+    for name, params in IMAGES.items():
+        with open(os.path.join(out, name), "wb") as f:
+            f.write(disk(build(*params)))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,39 @@
+#![cfg_attr(not(test), no_std)]
+
+//! A FAT32 filesystem for the SD card `install-kernel.py` writes to.
+//!
+//! A disk starts with a master boot record whose partition table points at
+//! a FAT32 partition. The partition starts with a BIOS parameter block
+//! describing the volume's geometry, followed by the file allocation table
+//! (FAT), usually twice, and the data region. The data region is divided
+//! into clusters; a file or directory is a chain of clusters, and the FAT
+//! entry for each cluster names the next one in its chain (ref: Microsoft
+//! "FAT: General Overview of On-Disk Format" v1.03).
+//!
+//! The filesystem is generic over the `BlockDevice` it stores sectors on
+//! and exposes files and directories through the traits in `traits`, so
+//! the same code runs against the SD card in the kernel and against disk
+//! images on the host.
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests;
+
+pub mod mbr;
+pub mod traits;
+pub mod vfat;
+
+pub use self::mbr::MasterBootRecord;
+pub use self::traits::BlockDevice;
+pub use self::vfat::{CachedPartition, Partition, VFat};
+
+/// Reads the little-endian `u16` at `offset` in `data`.
+fn le16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Reads the little-endian `u32` at `offset` in `data`.
+fn le32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
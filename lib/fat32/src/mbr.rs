@@ -0,0 +1,178 @@
+//! The master boot record: the first sector of a disk, holding the boot
+//! code and a table of up to four primary partitions.
+
+use alloc::vec;
+use core::fmt;
+
+use shim::io;
+
+use crate::traits::BlockDevice;
+use crate::{le16, le32};
+
+/// The offset of the partition table in the MBR.
+const TABLE_OFFSET: usize = 0x1BE;
+
+/// The size of a partition table entry.
+const ENTRY_SIZE: usize = 16;
+
+/// The number of entries in the partition table.
+pub const NUM_PARTITIONS: usize = 4;
+
+/// The signature the MBR ends with.
+const SIGNATURE: u16 = 0xAA55;
+
+/// The size of the MBR, which is also the smallest sector size it can be
+/// read from.
+const MBR_SIZE: usize = 512;
+
+/// Partition types identifying a FAT32 partition, addressed through CHS or
+/// LBA respectively.
+pub const PARTITION_TYPE_FAT32_CHS: u8 = 0x0B;
+pub const PARTITION_TYPE_FAT32_LBA: u8 = 0x0C;
+
+/// The values a partition's boot indicator may have.
+const INACTIVE: u8 = 0x00;
+const ACTIVE: u8 = 0x80;
+
+/// An error reading a master boot record.
+#[derive(Debug)]
+pub enum Error {
+    /// The device failed to read the first sector.
+    Io(io::Error),
+    /// The boot indicator of the partition with the given index is neither
+    /// 0 nor 0x80.
+    UnknownBootIndicator(u8),
+    /// The MBR doesn't end with `0x55 0xAA`.
+    BadSignature,
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "failed to read the MBR: {}", error),
+            Error::UnknownBootIndicator(index) => write!(f, "partition {} has an invalid boot indicator", index),
+            Error::BadSignature => f.write_str("MBR has an invalid signature"),
+        }
+    }
+}
+
+/// A cylinder-head-sector address. Modern disks are addressed by logical
+/// block address instead, but the fields are still filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chs {
+    head: u8,
+    sector: u8,
+    cylinder: u16,
+}
+
+impl Chs {
+    fn parse(bytes: &[u8]) -> Chs {
+        Chs { head: bytes[0], sector: bytes[1] & 0x3F, cylinder: u16::from(bytes[1] & 0xC0) << 2 | u16::from(bytes[2]) }
+    }
+
+    /// The head number.
+    pub fn head(&self) -> u8 {
+        self.head
+    }
+
+    /// The sector number, starting at 1.
+    pub fn sector(&self) -> u8 {
+        self.sector
+    }
+
+    /// The cylinder number.
+    pub fn cylinder(&self) -> u16 {
+        self.cylinder
+    }
+}
+
+/// An entry in the partition table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// 0x80 if the partition is bootable, 0 otherwise.
+    pub boot_indicator: u8,
+    /// The address of the partition's first sector.
+    pub start: Chs,
+    /// The kind of filesystem in the partition, or 0 if the entry is unused.
+    pub partition_type: u8,
+    /// The address of the partition's last sector.
+    pub end: Chs,
+    /// The sector the partition starts at.
+    pub relative_sector: u32,
+    /// The number of sectors in the partition.
+    pub total_sectors: u32,
+}
+
+impl PartitionEntry {
+    fn parse(bytes: &[u8]) -> PartitionEntry {
+        PartitionEntry {
+            boot_indicator: bytes[0],
+            start: Chs::parse(&bytes[1..4]),
+            partition_type: bytes[4],
+            end: Chs::parse(&bytes[5..8]),
+            relative_sector: le32(bytes, 8),
+            total_sectors: le32(bytes, 12),
+        }
+    }
+
+    /// Returns `true` if the partition is marked bootable.
+    pub fn is_bootable(&self) -> bool {
+        self.boot_indicator == ACTIVE
+    }
+
+    /// Returns `true` if the partition type says it holds FAT32.
+    pub fn is_fat32(&self) -> bool {
+        self.partition_type == PARTITION_TYPE_FAT32_CHS || self.partition_type == PARTITION_TYPE_FAT32_LBA
+    }
+}
+
+/// A parsed master boot record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MasterBootRecord {
+    /// The 32-bit disk signature that identifies the disk to operating
+    /// systems.
+    pub disk_id: u32,
+    /// The partition table.
+    pub partitions: [PartitionEntry; NUM_PARTITIONS],
+}
+
+impl MasterBootRecord {
+    /// Reads and parses the MBR from the first sector of `device`.
+    pub fn from<T: BlockDevice>(mut device: T) -> Result<MasterBootRecord, Error> {
+        let mut sector = vec![0; (device.sector_size() as usize).max(MBR_SIZE)];
+        if device.read_sector(0, &mut sector)? < MBR_SIZE {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "MBR sector is too short")));
+        }
+        MasterBootRecord::parse(&sector)
+    }
+
+    /// Parses the MBR at the start of `sector`, which must be at least 512
+    /// bytes long.
+    pub fn parse(sector: &[u8]) -> Result<MasterBootRecord, Error> {
+        if sector.len() < MBR_SIZE || le16(sector, MBR_SIZE - 2) != SIGNATURE {
+            return Err(Error::BadSignature);
+        }
+
+        let mut partitions = [PartitionEntry::parse(&[0; ENTRY_SIZE]); NUM_PARTITIONS];
+        for (i, partition) in partitions.iter_mut().enumerate() {
+            let offset = TABLE_OFFSET + i * ENTRY_SIZE;
+            *partition = PartitionEntry::parse(&sector[offset..offset + ENTRY_SIZE]);
+            if partition.boot_indicator != INACTIVE && partition.boot_indicator != ACTIVE {
+                return Err(Error::UnknownBootIndicator(i as u8));
+            }
+        }
+
+        Ok(MasterBootRecord { disk_id: le32(sector, 0x1B8), partitions })
+    }
+
+    /// Returns the first partition holding FAT32.
+    pub fn fat32_partition(&self) -> Option<&PartitionEntry> {
+        self.partitions.iter().find(|p| p.is_fat32())
+    }
+}
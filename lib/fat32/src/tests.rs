@@ -0,0 +1,1097 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::mbr::{self, MasterBootRecord, PARTITION_TYPE_FAT32_LBA};
+use crate::traits::{BlockDevice, Dir as _, Entry as _, File as _, FileSystem, Metadata as _, Timestamp as _};
+use crate::vfat::{self, CachedPartition, Entry, Partition, VFat};
+
+/// The sector the partition in the test images starts at.
+const PARTITION_START: u64 = 63;
+
+/// The entries of the root directory of the test images, in order.
+const ROOT: &[&str] = &[
+    "HELLO.TXT",
+    "README",
+    "Long File Name.txt",
+    "A very long file name that needs four entries.md",
+    "ünïcødé.txt",
+    "lower.txt",
+    "mixed.TXT",
+    "hidden.sys",
+    "empty.txt",
+    "big.bin",
+    "docs",
+];
+
+/// The test images, for tests run against each.
+const IMAGES: &[&str] = &["fat32.img", "fat32-4k-clusters.img", "fat32-4k-sectors.img"];
+
+/// Asserts that `$value` matches the pattern `$pattern`.
+macro_rules! assert_matches {
+    ($value:expr, $pattern:pat) => {
+        match $value {
+            $pattern => {}
+            ref value => panic!("{:?} doesn't match {}", value, stringify!($pattern)),
+        }
+    };
+}
+
+/// A disk held in memory.
+struct MemDevice {
+    data: Vec<u8>,
+    sector_size: u64,
+    /// The number of sectors read and written so far.
+    reads: usize,
+    writes: usize,
+}
+
+impl MemDevice {
+    fn new(data: Vec<u8>) -> MemDevice {
+        MemDevice { data, sector_size: 512, reads: 0, writes: 0 }
+    }
+}
+
+impl BlockDevice for MemDevice {
+    fn sector_size(&self) -> u64 {
+        self.sector_size
+    }
+
+    fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = (n * self.sector_size) as usize;
+        if start >= self.data.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "sector out of range"));
+        }
+
+        let len = buf.len().min(self.sector_size as usize).min(self.data.len() - start);
+        buf[..len].copy_from_slice(&self.data[start..start + len]);
+        self.reads += 1;
+        Ok(len)
+    }
+
+    fn write_sector(&mut self, n: u64, buf: &[u8]) -> io::Result<usize> {
+        let start = (n * self.sector_size) as usize;
+        if start >= self.data.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "sector out of range"));
+        }
+
+        let len = buf.len().min(self.sector_size as usize).min(self.data.len() - start);
+        self.data[start..start + len].copy_from_slice(&buf[..len]);
+        self.writes += 1;
+        Ok(len)
+    }
+}
+
+/// A disk in a file on the host.
+struct FileDevice(std::fs::File);
+
+impl BlockDevice for FileDevice {
+    fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(512);
+        self.0.seek(SeekFrom::Start(n * 512))?;
+        self.0.read_exact(&mut buf[..len])?;
+        Ok(len)
+    }
+
+    fn write_sector(&mut self, n: u64, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(512);
+        self.0.seek(SeekFrom::Start(n * 512))?;
+        self.0.write_all(&buf[..len])?;
+        Ok(len)
+    }
+}
+
+/// Returns the contents of the test image `name`.
+fn image(name: &str) -> Vec<u8> {
+    let path = format!("{}/images/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {}: {}", path, e))
+}
+
+fn vfat(name: &str) -> VFat<MemDevice> {
+    VFat::from(MemDevice::new(image(name))).expect("failed to open volume")
+}
+
+/// Returns the names of the entries at `path`, without `.` and `..`.
+fn list<D: BlockDevice>(fs: &VFat<D>, path: &str) -> Vec<String> {
+    fs.open_dir(path)
+        .unwrap()
+        .entries()
+        .unwrap()
+        .map(|entry| entry.name().to_string())
+        .filter(|name| name != "." && name != "..")
+        .collect()
+}
+
+fn read<D: BlockDevice>(fs: &VFat<D>, path: &str) -> Vec<u8> {
+    let mut file = fs.open_file(path).unwrap();
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len() as u64, file.size());
+    data
+}
+
+/// The contents of `big.bin`, as written by `mkimage.py`.
+fn big_data() -> Vec<u8> {
+    (0..40000usize).map(|i| (i * 31 + i / 251) as u8).collect()
+}
+
+/// Returns the offset in `data` of the 8.3 directory entry named `short`.
+fn find_entry(data: &[u8], short: &[u8; 11]) -> usize {
+    (0..data.len()).step_by(32).find(|&i| &data[i..i + 11] == short).expect("entry not found")
+}
+
+#[test]
+fn mbr() {
+    let mbr = MasterBootRecord::from(&mut MemDevice::new(image("fat32.img"))).unwrap();
+    let partition = &mbr.partitions[0];
+
+    assert_eq!(mbr.disk_id, 0x5253_5453);
+    assert!(partition.is_bootable() && partition.is_fat32());
+    assert_eq!(partition.partition_type, PARTITION_TYPE_FAT32_LBA);
+    assert_eq!(u64::from(partition.relative_sector), PARTITION_START);
+    assert_eq!(partition.total_sectors, 2048);
+    assert_eq!((partition.start.head(), partition.start.sector(), partition.start.cylinder()), (1, 1, 0));
+    assert!(mbr.partitions[1..].iter().all(|p| p.partition_type == 0));
+}
+
+#[test]
+fn mbr_errors() {
+    let mut data = image("fat32.img");
+    data[0x1BE + 16] = 0x12;
+    let err = MasterBootRecord::from(&mut MemDevice::new(data.clone())).unwrap_err();
+    assert_matches!(err, mbr::Error::UnknownBootIndicator(1));
+
+    data[511] = 0;
+    let err = MasterBootRecord::from(&mut MemDevice::new(data)).unwrap_err();
+    assert_matches!(err, mbr::Error::BadSignature);
+
+    let err = VFat::from(MemDevice::new(vec![0; 512])).unwrap_err();
+    assert_matches!(err, vfat::Error::Mbr(mbr::Error::BadSignature));
+}
+
+#[test]
+fn no_fat32_partition() {
+    let mut data = image("fat32.img");
+    data[0x1BE + 4] = 0x83;
+
+    let err = VFat::from(MemDevice::new(data)).unwrap_err();
+    assert_matches!(err, vfat::Error::NoPartition);
+}
+
+#[test]
+fn ebpb() {
+    let fs = vfat("fat32.img");
+    let ebpb = fs.ebpb();
+
+    assert_eq!(ebpb.bytes_per_sector, 512);
+    assert_eq!(ebpb.sectors_per_cluster, 1);
+    assert_eq!(ebpb.reserved_sectors, 32);
+    assert_eq!(ebpb.num_fats, 2);
+    assert_eq!(u64::from(ebpb.hidden_sectors), PARTITION_START);
+    assert_eq!(ebpb.total_sectors, 2048);
+    assert_eq!(ebpb.root_cluster, 2);
+    assert_eq!((ebpb.fsinfo_sector, ebpb.backup_boot_sector), (1, 6));
+    assert_eq!(ebpb.volume_id, 0x1234_ABCD);
+    assert_eq!(ebpb.label(), "RUSTOS");
+    assert_eq!(&ebpb.system_id, b"FAT32   ");
+    assert_eq!(ebpb.data_start(), 32 + 2 * u64::from(ebpb.sectors_per_fat));
+    assert_eq!(fs.cluster_size(), 512);
+
+    assert_eq!(vfat("fat32-4k-clusters.img").cluster_size(), 4096);
+
+    let fs = vfat("fat32-4k-sectors.img");
+    assert_eq!((fs.ebpb().bytes_per_sector, fs.ebpb().total_sectors), (4096, 256));
+    assert_eq!(fs.cluster_size(), 4096);
+}
+
+#[test]
+fn bad_boot_sector() {
+    let start = PARTITION_START as usize * 512;
+
+    let mut data = image("fat32.img");
+    data[start + 0x42] = 0;
+    let err = VFat::from(MemDevice::new(data)).unwrap_err();
+    assert_matches!(err, vfat::Error::BadSignature);
+
+    // A non-zero 16-bit FAT size means FAT12 or FAT16.
+    let mut data = image("fat32.img");
+    data[start + 0x16] = 1;
+    let err = VFat::from(MemDevice::new(data)).unwrap_err();
+    assert_matches!(err, vfat::Error::Invalid(_));
+
+    // Shift the partition to a 1024-byte boundary to read it in 1024-byte
+    // sectors.
+    let mut device = MemDevice::new([vec![0; 512], image("fat32.img")].concat());
+    device.sector_size = 1024;
+    let err = VFat::from_partition(device, 32).unwrap_err();
+    assert_matches!(err, vfat::Error::UnsupportedSectorSize(512));
+}
+
+#[test]
+fn root_entries() {
+    for name in IMAGES {
+        let fs = vfat(name);
+        assert_eq!(list(&fs, "/"), ROOT, "{}", name);
+
+        // The root has no `.` or `..` entries.
+        assert_eq!(fs.root().entries().unwrap().count(), ROOT.len());
+    }
+}
+
+#[test]
+fn file_contents() {
+    for name in IMAGES {
+        let fs = vfat(name);
+
+        assert_eq!(read(&fs, "/HELLO.TXT"), b"Hello, world!\n");
+        assert_eq!(read(&fs, "/README"), b"FAT32 test image\n");
+        assert_eq!(read(&fs, "/Long File Name.txt"), b"long\n");
+        assert_eq!(read(&fs, "/A very long file name that needs four entries.md"), b"# four\n");
+        assert_eq!(read(&fs, "/ünïcødé.txt"), "ünïcødé\n".as_bytes());
+        assert_eq!(read(&fs, "/mixed.TXT"), b"mixed\n");
+        assert_eq!(read(&fs, "/empty.txt"), b"");
+        assert_eq!(read(&fs, "/docs/note-17.txt"), b"note 17\n");
+        assert_eq!(read(&fs, "/docs/nested/deep/file.txt"), b"deep\n");
+    }
+}
+
+#[test]
+fn fragmented_file() {
+    for name in IMAGES {
+        let fs = vfat(name);
+        assert_eq!(read(&fs, "/big.bin"), big_data(), "{}", name);
+
+        let file = fs.open_file("/big.bin").unwrap();
+        let first = file.cluster().unwrap();
+        assert_eq!(fs.next_cluster(first).unwrap().unwrap().number(), first.number() + 2);
+    }
+}
+
+#[test]
+fn small_reads() {
+    let fs = vfat("fat32.img");
+    let mut file = fs.open_file("big.bin").unwrap();
+    let mut data = Vec::new();
+    let mut buf = [0; 77];
+    loop {
+        match file.read(&mut buf).unwrap() {
+            0 => break,
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    }
+    assert_eq!(data, big_data());
+}
+
+#[test]
+fn seek() {
+    let fs = vfat("fat32.img");
+    let expected = big_data();
+    let mut file = fs.open_file("/big.bin").unwrap();
+    let mut buf = [0; 1000];
+
+    assert_eq!(file.seek(SeekFrom::Start(30000)).unwrap(), 30000);
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &expected[30000..31000]);
+
+    // Backwards, across clusters, from the current position.
+    assert_eq!(file.seek(SeekFrom::Current(-20000)).unwrap(), 11000);
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &expected[11000..12000]);
+
+    assert_eq!(file.seek(SeekFrom::End(-10)).unwrap(), 39990);
+    assert_eq!(file.read(&mut buf).unwrap(), 10);
+    assert_eq!(&buf[..10], &expected[39990..]);
+
+    assert_eq!(file.seek(SeekFrom::End(10)).unwrap(), 40010);
+    assert_eq!(file.read(&mut buf).unwrap(), 0);
+
+    let err = file.seek(SeekFrom::Current(-50000)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn directory_spanning_clusters() {
+    let fs = vfat("fat32.img");
+    let mut expected: Vec<String> = (0..40).map(|i| format!("note-{:02}.txt", i)).collect();
+    expected.push("nested".to_string());
+    assert_eq!(list(&fs, "/docs"), expected);
+
+    let names: Vec<String> = fs.open_dir("/docs").unwrap().entries().unwrap().map(|e| e.name().to_string()).collect();
+    assert_eq!(&names[..2], &[".", ".."]);
+}
+
+#[test]
+fn paths() {
+    let fs = vfat("fat32.img");
+
+    assert_eq!(read(&fs, "hello.txt"), b"Hello, world!\n");
+    assert_eq!(read(&fs, "/DOCS/NESTED/../NOTE-03.TXT"), b"note 03\n");
+    assert_eq!(read(&fs, "//docs/./nested//deep/file.txt"), b"deep\n");
+    assert_eq!(read(&fs, "/docs/nested/deep/../../../../HELLO.TXT"), b"Hello, world!\n");
+    assert_eq!(read(&fs, "/LONG FILE NAME.TXT"), b"long\n");
+    assert_eq!(read(&fs, "/docs/nested/deep/file.txt/"), b"deep\n");
+
+    assert!(fs.open("/").unwrap().as_dir().unwrap().is_root());
+    assert!(fs.open("/docs/..").unwrap().as_dir().unwrap().is_root());
+    assert_eq!(list(&fs, "/docs/nested/.."), list(&fs, "/docs"));
+
+    let not_found = |path| fs.open(path).unwrap_err().kind();
+    assert_eq!(not_found("/missing.txt"), io::ErrorKind::NotFound);
+    assert_eq!(not_found("/gone.txt"), io::ErrorKind::NotFound);
+    assert_eq!(not_found("/RUSTOS"), io::ErrorKind::NotFound);
+    assert_eq!(not_found("/docs/missing/file.txt"), io::ErrorKind::NotFound);
+    assert_eq!(not_found("/HELLO.TXT/file"), io::ErrorKind::InvalidInput);
+
+    assert_eq!(fs.open_file("/docs").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs.open_dir("/README").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn metadata() {
+    let fs = vfat("fat32.img");
+
+    let hello = fs.open("/HELLO.TXT").unwrap();
+    let metadata = hello.metadata();
+    let created = metadata.created();
+    assert_eq!((created.year(), created.month(), created.day()), (2019, 3, 1));
+    // 12:34:56 plus the 100 hundredths recorded with the creation time.
+    assert_eq!((created.hour(), created.minute(), created.second()), (12, 34, 57));
+    assert_eq!(metadata.modified().to_string(), "2019-03-02 08:15:30");
+    assert_eq!(metadata.accessed().to_string(), "2019-03-03 00:00:00");
+    assert!(!metadata.read_only() && !metadata.hidden());
+    assert!(metadata.attributes.archive());
+
+    let readme = fs.open("/README").unwrap();
+    assert!(readme.metadata().read_only());
+
+    let hidden = fs.open("/hidden.sys").unwrap();
+    assert!(hidden.metadata().hidden() && hidden.metadata().attributes.system());
+    assert_eq!(format!("{:?}", hidden.metadata().attributes), "-hs---");
+
+    let docs = fs.open("/docs").unwrap();
+    assert!(docs.is_dir() && !docs.is_file());
+    assert!(docs.metadata().attributes.directory());
+    assert_matches!(docs, Entry::Dir(_));
+}
+
+#[test]
+fn orphaned_long_name() {
+    // With a checksum that doesn't match the 8.3 name, the long name is
+    // ignored and the 8.3 name used instead.
+    let mut data = image("fat32.img");
+    let short = find_entry(&data, b"LONGFI~1TXT");
+    data[short - 32 + 13] ^= 0xFF;
+    let fs = VFat::from(MemDevice::new(data)).unwrap();
+
+    assert!(list(&fs, "/").contains(&"LONGFI~1.TXT".to_string()));
+    assert_eq!(read(&fs, "/LONGFI~1.TXT"), b"long\n");
+
+    // Likewise with a part of the long name missing.
+    let mut data = image("fat32.img");
+    let short = find_entry(&data, b"AVERYL~1MD ");
+    data[short - 2 * 32] = 0xE5;
+    let fs = VFat::from(MemDevice::new(data)).unwrap();
+    assert!(list(&fs, "/").contains(&"AVERYL~1.MD".to_string()));
+}
+
+#[test]
+fn cluster_chain_loop() {
+    let mut data = image("fat32.img");
+    let fs = vfat("fat32.img");
+    let docs = fs.open_dir("/docs").unwrap().cluster().number() as usize;
+
+    // Point the last cluster of `docs` back at its first.
+    let fat = (PARTITION_START as usize + 32) * 512;
+    let mut cluster = docs;
+    loop {
+        let entry = fat + cluster * 4;
+        let next = u32::from_le_bytes([data[entry], data[entry + 1], data[entry + 2], data[entry + 3]]) as usize;
+        if next >= 0x0FFF_FFF8 {
+            data[entry..entry + 4].copy_from_slice(&(docs as u32).to_le_bytes());
+            break;
+        }
+        cluster = next;
+    }
+
+    let fs = VFat::from(MemDevice::new(data)).unwrap();
+    let err = fs.open_dir("/docs").unwrap().entries().err().expect("loop not detected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn broken_cluster_chain() {
+    let mut data = image("fat32.img");
+    let fs = vfat("fat32.img");
+    let big = fs.open_file("/big.bin").unwrap().cluster().unwrap().number() as usize;
+
+    // Mark the second cluster of `big.bin` free.
+    let fat = (PARTITION_START as usize + 32) * 512;
+    let second = (big + 2) * 4;
+    data[fat + second..fat + second + 4].copy_from_slice(&[0; 4]);
+
+    let fs = VFat::from(MemDevice::new(data)).unwrap();
+    let mut file = fs.open_file("/big.bin").unwrap();
+    let mut buf = vec![0; 2048];
+    assert_eq!(file.read(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+fn le32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+thread_local! {
+    /// Whether `verify` runs `fsck.vfat` as well as `check`. Only
+    /// `fsck_accepts_written_volumes` sets this.
+    static FSCK: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f` on the volume in the test image `name`, checks the volume is
+/// consistent afterwards, and returns the disk.
+fn modify<F: FnOnce(&VFat<&mut MemDevice>)>(name: &str, f: F) -> Vec<u8> {
+    let mut device = MemDevice::new(image(name));
+    let fs = VFat::from(&mut device).expect("failed to open volume");
+    f(&fs);
+    drop(fs);
+    verify(&device.data);
+    device.data
+}
+
+/// Checks the volume on `disk` is consistent with `check`, and with `fsck`
+/// when `FSCK` is set.
+fn verify(disk: &[u8]) {
+    check(disk);
+    if FSCK.with(Cell::get) {
+        fsck(disk);
+    }
+}
+
+/// A volume laid out in raw bytes, for `check`.
+struct Volume<'a> {
+    data: &'a [u8],
+    cluster_size: usize,
+    data_start: usize,
+    fat: &'a [u8],
+    cluster_count: u32,
+    used: Vec<bool>,
+}
+
+impl<'a> Volume<'a> {
+    fn next(&self, cluster: u32) -> u32 {
+        le32(self.fat, cluster as usize * 4) & 0x0FFF_FFFF
+    }
+
+    /// Returns the chain starting at `start`, marking its clusters used.
+    fn chain(&mut self, start: u32) -> Vec<u32> {
+        let mut chain = Vec::new();
+        let mut cluster = start;
+        loop {
+            assert!(cluster >= 2 && cluster < self.cluster_count + 2, "cluster {:#x} out of range", cluster);
+            assert!(!self.used[cluster as usize], "cluster {:#x} is cross-linked", cluster);
+            self.used[cluster as usize] = true;
+            chain.push(cluster);
+
+            match self.next(cluster) {
+                0 => panic!("chain runs into free cluster {:#x}", cluster),
+                next if next >= 0x0FFF_FFF8 => return chain,
+                next => cluster = next,
+            }
+        }
+    }
+
+    fn read_chain(&mut self, start: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for cluster in self.chain(start) {
+            let offset = self.data_start + (cluster as usize - 2) * self.cluster_size;
+            data.extend_from_slice(&self.data[offset..offset + self.cluster_size]);
+        }
+        data
+    }
+
+    /// Checks the directory starting at `start` and everything in it.
+    fn check_dir(&mut self, start: u32, parent: Option<u32>) {
+        let data = self.read_chain(start);
+        let mut long_names = HashSet::new();
+        let mut short_names = HashSet::new();
+        // The LFN entries seen since the last 8.3 entry: the checksum, the
+        // next sequence number expected and the name so far.
+        let mut pending: Option<(u8, u8, Vec<u16>)> = None;
+
+        for (index, raw) in data.chunks(32).enumerate() {
+            match raw[0] {
+                0 => break,
+                0xE5 => {
+                    assert!(pending.is_none(), "deleted entry after LFN entries in {:#x}", start);
+                    continue;
+                }
+                _ => {}
+            }
+
+            if raw[11] & 0x3F == 0x0F {
+                let sequence = raw[0] & 0x1F;
+                let mut units: Vec<u16> = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30]
+                    .iter()
+                    .map(|&i| u16::from_le_bytes([raw[i], raw[i + 1]]))
+                    .collect();
+                pending = match pending.take() {
+                    None => {
+                        assert!(raw[0] & 0x40 != 0, "orphaned LFN entry in {:#x}", start);
+                        Some((raw[13], sequence - 1, units))
+                    }
+                    Some((checksum, next, name)) => {
+                        assert!(raw[0] & 0x40 == 0 && sequence == next && raw[13] == checksum, "bad LFN entry");
+                        units.extend(name);
+                        Some((checksum, next - 1, units))
+                    }
+                };
+                continue;
+            }
+
+            let checksum =
+                raw[..11].iter().fold(0u8, |sum, &b| ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(b));
+            if let Some((lfn_checksum, next, units)) = pending.take() {
+                assert!(next == 0 && lfn_checksum == checksum, "LFN entries don't match {:?}", &raw[..11]);
+                let len = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+                let name = String::from_utf16(&units[..len]).unwrap();
+                assert!(long_names.insert(name.to_lowercase()), "duplicate name {:?}", name);
+            }
+            if raw[11] & 0x08 != 0 {
+                continue;
+            }
+            assert!(short_names.insert(raw[..11].to_vec()), "duplicate 8.3 name {:?}", &raw[..11]);
+
+            let cluster = u32::from(u16::from_le_bytes([raw[20], raw[21]])) << 16
+                | u32::from(u16::from_le_bytes([raw[26], raw[27]]));
+            let size = le32(raw, 28) as usize;
+            if let Some(parent) = parent {
+                match index {
+                    0 => assert!(&raw[..11] == b".          " && cluster == start, "bad `.` in {:#x}", start),
+                    1 => assert!(&raw[..11] == b"..         " && cluster == parent, "bad `..` in {:#x}", start),
+                    _ => {}
+                }
+                if index < 2 {
+                    continue;
+                }
+            }
+
+            if raw[11] & 0x10 != 0 {
+                assert_eq!(size, 0, "directory with a size");
+                self.check_dir(cluster, Some(if start == 2 { 0 } else { start }));
+            } else if cluster == 0 {
+                assert_eq!(size, 0, "file with data but no clusters");
+            } else {
+                let clusters = self.chain(cluster).len();
+                assert_eq!(clusters, (size + self.cluster_size - 1) / self.cluster_size, "chain length of {:?}", raw);
+            }
+        }
+        assert!(pending.is_none(), "LFN entries at the end of {:#x}", start);
+    }
+}
+
+/// Checks the FAT32 volume on `disk` is consistent: what `fsck.vfat` checks
+/// that matters for writes, for hosts without it.
+fn check(disk: &[u8]) {
+    let data = &disk[PARTITION_START as usize * 512..];
+    let sector_size = usize::from(u16::from_le_bytes([data[11], data[12]]));
+    let reserved = usize::from(u16::from_le_bytes([data[14], data[15]]));
+    let fat_size = le32(data, 36) as usize * sector_size;
+    let fats: Vec<&[u8]> =
+        (0..data[16] as usize).map(|i| &data[reserved * sector_size + i * fat_size..][..fat_size]).collect();
+    assert!(fats.iter().all(|fat| fat == &fats[0]), "FATs differ");
+
+    let cluster_size = sector_size * usize::from(data[13]);
+    let data_start = reserved * sector_size + fats.len() * fat_size;
+    let cluster_count = ((le32(data, 32) as usize * sector_size - data_start) / cluster_size) as u32;
+    let mut volume = Volume {
+        data,
+        cluster_size,
+        data_start,
+        fat: fats[0],
+        cluster_count,
+        used: vec![false; cluster_count as usize + 2],
+    };
+    volume.check_dir(le32(data, 44), None);
+
+    let mut free = 0;
+    for cluster in 2..cluster_count + 2 {
+        let next = volume.next(cluster);
+        assert!(next == 0 || volume.used[cluster as usize], "cluster {:#x} is lost", cluster);
+        free += (next == 0) as u32;
+    }
+
+    let fsinfo = &data[usize::from(u16::from_le_bytes([data[48], data[49]])) * sector_size..];
+    assert_eq!(le32(fsinfo, 488), free, "FSInfo free cluster count");
+}
+
+/// Runs `fsck.vfat` on the partition on `disk`.
+///
+/// # Panics
+///
+/// Panics if `fsck.vfat` isn't installed or finds a problem.
+fn fsck(disk: &[u8]) {
+    let path =
+        std::env::temp_dir().join(format!("fat32-fsck-{}-{:?}.img", std::process::id(), std::thread::current().id()));
+    std::fs::write(&path, &disk[PARTITION_START as usize * 512..]).unwrap();
+    let output = std::process::Command::new("fsck.vfat").arg("-n").arg(&path).output();
+    std::fs::remove_file(&path).unwrap();
+
+    match output {
+        Ok(output) => assert!(
+            output.status.success(),
+            "fsck.vfat failed:\n{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => panic!("fsck.vfat not found; install dosfstools"),
+        Err(e) => panic!("failed to run fsck.vfat: {}", e),
+    }
+}
+
+#[test]
+fn unmodified_images_are_consistent() {
+    for name in IMAGES {
+        verify(&image(name));
+    }
+}
+
+/// Repeats the tests that write to a volume, checking every volume they
+/// write with `fsck.vfat` as well. This needs dosfstools installed, so it
+/// only runs when asked for: `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn fsck_accepts_written_volumes() {
+    FSCK.with(|fsck| fsck.set(true));
+    unmodified_images_are_consistent();
+    create_and_append();
+    write_past_end();
+    long_names();
+    truncate_and_extend();
+    remove();
+    rename();
+    create_dir();
+    volume_full();
+    file_backed_device();
+}
+
+#[test]
+fn create_and_append() {
+    for name in IMAGES {
+        let disk = modify(name, |fs| {
+            let free = fs.free_clusters().unwrap();
+            let mut file = fs.create_file("/log.txt").unwrap();
+            file.write_all(b"first line\n").unwrap();
+            assert_eq!(file.size(), 11);
+
+            let mut file = fs.open_file("/LOG.TXT").unwrap();
+            file.seek(SeekFrom::End(0)).unwrap();
+            file.write_all(&big_data()).unwrap();
+            assert_eq!(
+                fs.free_clusters().unwrap(),
+                free - ((40011 + fs.cluster_size() - 1) / fs.cluster_size()) as u32
+            );
+
+            // Overwrite in the middle without changing the size.
+            file.seek(SeekFrom::Start(6)).unwrap();
+            file.write_all(b"LINE").unwrap();
+            assert_eq!(file.size(), 40011);
+
+            let err = fs.create_file("/Log.txt").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        });
+
+        let fs = VFat::from(MemDevice::new(disk.clone())).unwrap();
+        let data = read(&fs, "/log.txt");
+        assert_eq!(&data[..11], b"first LINE\n");
+        assert!(data[11..] == big_data()[..], "{}", name);
+        assert!(list(&fs, "/").contains(&"log.txt".to_string()));
+
+        // `log.txt` is a valid 8.3 name, so it needs no LFN entries.
+        let entry = find_entry(&disk, b"LOG     TXT");
+        assert_eq!(disk[entry + 12], 0x18);
+        assert_ne!(disk[entry - 32 + 11], 0x0F);
+        assert_eq!(disk[entry + 11], 0x20);
+    }
+}
+
+#[test]
+fn write_past_end() {
+    modify("fat32.img", |fs| {
+        let mut file = fs.create_file("/sparse").unwrap();
+        file.seek(SeekFrom::Start(1500)).unwrap();
+        file.write_all(b"end").unwrap();
+        assert_eq!(file.size(), 1503);
+
+        let data = read(fs, "/sparse");
+        assert!(data[..1500].iter().all(|&b| b == 0));
+        assert_eq!(&data[1500..], b"end");
+    });
+}
+
+#[test]
+fn long_names() {
+    let disk = modify("fat32.img", |fs| {
+        fs.create_file("/Long File Name 2.txt").unwrap();
+        fs.create_file("/Mixed Case.TXT").unwrap();
+        fs.create_file("/ünïcødé 2.txt").unwrap();
+        fs.create_file("/.profile").unwrap();
+        let name = "n".repeat(255);
+        fs.create_file(&format!("/{}", name)).unwrap();
+
+        let err = fs.create_file(&format!("/{}n", name)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        for bad in &["/a:b", "/a*", "/trailing.", "/..", "/tab\t"] {
+            assert_eq!(fs.create_file(bad).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}", bad);
+        }
+
+        let names = list(fs, "/");
+        for created in &["Long File Name 2.txt", "Mixed Case.TXT", "ünïcødé 2.txt", ".profile", &name] {
+            assert!(names.iter().any(|n| n == created), "{} missing", created);
+        }
+    });
+
+    // `LONGFI~1.TXT` is taken by `Long File Name.txt`.
+    find_entry(&disk, b"LONGFI~2TXT");
+    find_entry(&disk, b"MIXEDC~1TXT");
+    find_entry(&disk, b"_N_C_D~1TXT");
+    find_entry(&disk, b"PROFIL~1   ");
+    find_entry(&disk, b"NNNNNN~1   ");
+}
+
+#[test]
+fn truncate_and_extend() {
+    for name in IMAGES {
+        modify(name, |fs| {
+            let free = fs.free_clusters().unwrap();
+            let mut file = fs.open_file("/big.bin").unwrap();
+            file.set_len(1000).unwrap();
+            assert_eq!(file.size(), 1000);
+            let kept = (1000 + fs.cluster_size() - 1) / fs.cluster_size();
+            let had = (40000 + fs.cluster_size() - 1) / fs.cluster_size();
+            assert_eq!(fs.free_clusters().unwrap(), free + (had - kept) as u32);
+
+            // Extending exposes zeroes, not what used to be there.
+            file.set_len(5000).unwrap();
+            let data = read(fs, "/big.bin");
+            assert!(data[..1000] == big_data()[..1000]);
+            assert!(data[1000..].iter().all(|&b| b == 0));
+
+            let mut file = fs.open_file("/HELLO.TXT").unwrap();
+            file.set_len(0).unwrap();
+            assert_eq!(file.cluster(), None);
+            assert_eq!(read(fs, "/HELLO.TXT"), b"");
+
+            let mut file = fs.open_file("/README").unwrap();
+            assert_eq!(file.set_len(0).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+            assert_eq!(file.write(b"x").unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        });
+    }
+}
+
+#[test]
+fn remove() {
+    for name in IMAGES {
+        modify(name, |fs| {
+            let free = fs.free_clusters().unwrap();
+            fs.remove("/big.bin").unwrap();
+            fs.remove("/Long File Name.txt").unwrap();
+            fs.remove("/empty.txt").unwrap();
+            assert!(fs.free_clusters().unwrap() > free);
+
+            let err = fs.remove("/docs").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Other);
+            assert_eq!(fs.remove("/big.bin").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+            fs.remove("/docs/nested/deep/file.txt").unwrap();
+            fs.remove("/docs/nested/deep").unwrap();
+            fs.remove("/docs/nested/").unwrap();
+            for i in 0..40 {
+                fs.remove(&format!("/docs/note-{:02}.txt", i)).unwrap();
+            }
+            fs.remove("/docs").unwrap();
+
+            assert_eq!(
+                list(fs, "/"),
+                [
+                    "HELLO.TXT",
+                    "README",
+                    "A very long file name that needs four entries.md",
+                    "ünïcødé.txt",
+                    "lower.txt",
+                    "mixed.TXT",
+                    "hidden.sys"
+                ]
+            );
+
+            // Freed entries are reused.
+            fs.create_file("/Another long name.txt").unwrap();
+            assert_eq!(list(fs, "/")[2], "Another long name.txt");
+        });
+    }
+}
+
+#[test]
+fn rename() {
+    for name in IMAGES {
+        modify(name, |fs| {
+            fs.rename("/HELLO.TXT", "/greeting.txt").unwrap();
+            assert_eq!(read(fs, "/greeting.txt"), b"Hello, world!\n");
+            assert_eq!(fs.open("/HELLO.TXT").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+            // Only the case changes.
+            fs.rename("/greeting.txt", "/Greeting.txt").unwrap();
+            assert!(list(fs, "/").contains(&"Greeting.txt".to_string()));
+
+            let err = fs.rename("/README", "/lower.txt").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+            // Metadata moves with the entry.
+            fs.rename("/README", "/docs/nested/README.md").unwrap();
+            let moved = fs.open_file("/docs/nested/README.md").unwrap();
+            assert!(moved.metadata().read_only());
+            assert_eq!(read(fs, "/docs/nested/README.md"), b"FAT32 test image\n");
+
+            // Moving a directory updates its `..`.
+            fs.rename("/docs/nested/deep", "/deep").unwrap();
+            assert_eq!(read(fs, "/deep/../deep/file.txt"), b"deep\n");
+            assert_eq!(fs.open_dir("/deep/..").unwrap().cluster(), fs.root().cluster());
+
+            let err = fs.rename("/docs", "/docs/nested/docs").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+}
+
+#[test]
+fn create_dir() {
+    for name in IMAGES {
+        modify(name, |fs| {
+            let dir = fs.create_dir("/logs").unwrap();
+            assert!(dir.metadata().attributes.directory());
+            assert_eq!(fs.create_dir("/LOGS").unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+            fs.create_dir("/logs/2019").unwrap();
+            let sub = fs.open_dir("/logs/2019/..").unwrap();
+            assert_eq!(sub.cluster(), dir.cluster());
+
+            // Enough entries to grow the directory by several clusters.
+            for i in 0..150 {
+                let mut file = fs.create_file(&format!("/logs/2019/Log file number {}.txt", i)).unwrap();
+                write!(file, "entry {}", i).unwrap();
+            }
+            let names = list(fs, "/logs/2019");
+            assert_eq!(names.len(), 150);
+            assert_eq!(read(fs, "/logs/2019/Log file number 149.txt"), b"entry 149");
+        });
+    }
+}
+
+#[test]
+fn volume_full() {
+    modify("fat32.img", |fs| {
+        let mut file = fs.create_file("/filler").unwrap();
+        let chunk = vec![0xA5; 4096];
+        let err = loop {
+            if let Err(e) = file.write_all(&chunk) {
+                break e;
+            }
+        };
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+
+        // The failed write allocated nothing; what is left still fits.
+        let free = fs.free_clusters().unwrap();
+        assert!(free < 8);
+        file.write_all(&chunk[..free as usize * 512]).unwrap();
+        assert_eq!(fs.free_clusters().unwrap(), 0);
+        assert_eq!(fs.fsinfo().unwrap().free_count, 0);
+        assert_eq!(file.write(b"x").unwrap_err().kind(), io::ErrorKind::Other);
+
+        // Freeing space makes it usable again.
+        fs.remove("/big.bin").unwrap();
+        fs.create_dir("/more").unwrap();
+    });
+}
+
+/// Returns a device of `sectors` 512-byte sectors, each filled with its own
+/// number.
+fn numbered_device(sectors: u8) -> MemDevice {
+    MemDevice::new((0..sectors).flat_map(|n| vec![n; 512]).collect())
+}
+
+#[test]
+fn cached_partition_translates_sectors() {
+    let partition = Partition { start: 4, num_sectors: 3, sector_size: 1024 };
+    let mut device = numbered_device(16);
+    let mut cache = CachedPartition::new(&mut device, partition);
+    assert_eq!(cache.sector_size(), 1024);
+
+    assert_eq!(cache.get(0).unwrap(), &[vec![4; 512], vec![5; 512]].concat()[..]);
+    assert_eq!(cache.get(2).unwrap(), &[vec![8; 512], vec![9; 512]].concat()[..]);
+    assert_eq!(cache.get(3).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let mut buf = [0; 1024];
+    assert_eq!(cache.read_sector(1, &mut buf).unwrap(), 1024);
+    assert_eq!(&buf[..], &[vec![6; 512], vec![7; 512]].concat()[..]);
+
+    // Each logical sector was read from the device once.
+    cache.get(0).unwrap();
+    cache.read_sector(1, &mut buf).unwrap();
+    drop(cache);
+    assert_eq!(device.reads, 6);
+}
+
+#[test]
+fn cached_partition_writes_back_on_flush() {
+    let partition = Partition { start: 2, num_sectors: 4, sector_size: 1024 };
+    let mut device = numbered_device(16);
+    let mut cache = CachedPartition::new(&mut device, partition);
+
+    cache.get_mut(0).unwrap()[0] = 0xAA;
+    cache.get(1).unwrap();
+    // A partial write has to read the rest of the sector first.
+    assert_eq!(cache.write_sector(2, &[0xBB; 10]).unwrap(), 10);
+    // A whole one doesn't.
+    assert_eq!(cache.write_sector(3, &[0xCC; 1024]).unwrap(), 1024);
+    assert_eq!(cache.get(3).unwrap(), &[0xCC; 1024][..]);
+    cache.flush().unwrap();
+    cache.flush().unwrap();
+    drop(cache);
+
+    // Only the three changed sectors were written, once each.
+    assert_eq!((device.reads, device.writes), (6, 6));
+    assert_eq!(&device.data[2 * 512..2 * 512 + 2], &[0xAA, 2]);
+    assert!(device.data[3 * 512..4 * 512].iter().all(|&b| b == 3));
+    assert_eq!(&device.data[6 * 512..6 * 512 + 11], &[0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 6]);
+    assert!(device.data[7 * 512..8 * 512].iter().all(|&b| b == 7));
+    assert!(device.data[8 * 512..10 * 512].iter().all(|&b| b == 0xCC));
+    assert!(device.data[10 * 512..].iter().all(|&b| b != 0xCC));
+}
+
+#[test]
+fn cached_partition_writes_back_on_drop() {
+    let partition = Partition { start: 0, num_sectors: 2, sector_size: 512 };
+    let mut device = numbered_device(2);
+    let mut cache = CachedPartition::new(&mut device, partition);
+    cache.get_mut(1).unwrap()[0] = 0xAA;
+    drop(cache);
+
+    assert_eq!(device.writes, 1);
+    assert_eq!(&device.data[512..514], &[0xAA, 1]);
+}
+
+#[test]
+fn cached_partition_evicts_least_recently_used() {
+    let partition = Partition { start: 0, num_sectors: 8, sector_size: 512 };
+    let mut device = numbered_device(8);
+    let mut cache = CachedPartition::with_capacity(&mut device, partition, 2);
+
+    // Sector 1 is the least recently used, so it makes room for sector 2.
+    cache.get(0).unwrap();
+    cache.get(1).unwrap();
+    cache.get(0).unwrap();
+    cache.get(2).unwrap();
+    cache.get(0).unwrap();
+    assert_eq!(cache.get(1).unwrap(), &[1; 512][..]);
+
+    // A changed sector is kept over an older unchanged one.
+    cache.get_mut(1).unwrap()[0] = 0xAA;
+    cache.get(3).unwrap();
+    assert_eq!(cache.get(1).unwrap()[0], 0xAA);
+
+    // With every sector changed, they are all written back before one is
+    // dropped.
+    cache.get_mut(3).unwrap()[0] = 0xBB;
+    cache.get(4).unwrap();
+    drop(cache);
+
+    assert_eq!((device.reads, device.writes), (6, 2));
+    assert_eq!(&device.data[512..514], &[0xAA, 1]);
+    assert_eq!(&device.data[3 * 512..3 * 512 + 2], &[0xBB, 3]);
+}
+
+#[test]
+#[should_panic]
+fn cached_partition_sector_size() {
+    CachedPartition::new(numbered_device(4), Partition { start: 0, num_sectors: 1, sector_size: 768 });
+}
+
+#[test]
+fn file_backed_device() {
+    let path = std::env::temp_dir().join(format!("fat32-file-device-{}.img", std::process::id()));
+    std::fs::write(&path, image("fat32-4k-sectors.img")).unwrap();
+    let open = || {
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        VFat::from(FileDevice(file)).unwrap()
+    };
+
+    let fs = open();
+    assert_eq!(read(&fs, "/docs/nested/deep/file.txt"), b"deep\n");
+    let mut file = fs.create_file("/docs/Output Log.txt").unwrap();
+    file.write_all(&big_data()).unwrap();
+    file.flush().unwrap();
+    drop(fs);
+
+    let fs = open();
+    assert!(read(&fs, "/docs/output log.txt") == big_data());
+    drop(fs);
+
+    let disk = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    verify(&disk);
+}
+
+/// Returns a disk holding `volume` in a single FAT32 partition starting at
+/// `PARTITION_START`.
+fn partitioned(volume: &[u8]) -> Vec<u8> {
+    let mut disk = vec![0; PARTITION_START as usize * 512];
+    let entry = &mut disk[0x1BE..0x1BE + 16];
+    entry[0] = 0x80;
+    entry[4] = PARTITION_TYPE_FAT32_LBA;
+    entry[8..12].copy_from_slice(&(PARTITION_START as u32).to_le_bytes());
+    entry[12..16].copy_from_slice(&((volume.len() / 512) as u32).to_le_bytes());
+    disk[510..512].copy_from_slice(&[0x55, 0xAA]);
+    disk.extend_from_slice(volume);
+    disk
+}
+
+/// Builds a volume with dosfstools and mtools using `images/mkfs-image.sh`,
+/// then reads it and writes to it. This needs both installed, so it only
+/// runs when asked for: `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn mkfs_volume() {
+    let path = std::env::temp_dir().join(format!("fat32-mkfs-{}.img", std::process::id()));
+    let status = std::process::Command::new(concat!(env!("CARGO_MANIFEST_DIR"), "/images/mkfs-image.sh"))
+        .arg(&path)
+        .status()
+        .expect("failed to run mkfs-image.sh");
+    assert!(status.success(), "mkfs-image.sh failed");
+    let volume = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    FSCK.with(|fsck| fsck.set(true));
+    let mut device = MemDevice::new(partitioned(&volume));
+    let fs = VFat::from(&mut device).expect("failed to open volume");
+    assert_eq!(fs.ebpb().label(), "MKFS");
+    assert_eq!(fs.free_clusters().unwrap(), fs.fsinfo().unwrap().free_count);
+
+    assert_eq!(read(&fs, "/HELLO.TXT"), b"Hello from mtools!\n");
+    assert!(read(&fs, "/A long file name.bin") == big_data());
+    assert_eq!(read(&fs, "/some directory/deeper/nested.txt"), b"nested\n");
+    let notes = list(&fs, "/Some Directory");
+    assert_eq!(notes.len(), 41);
+    for i in 0..40 {
+        let name = format!("NOTE-{:02}.TXT", i);
+        assert!(notes.iter().any(|n| n.eq_ignore_ascii_case(&name)), "{} missing", name);
+        assert_eq!(read(&fs, &format!("/Some Directory/{}", name)), format!("note {:02}\n", i).as_bytes());
+    }
+
+    fs.remove("/A long file name.bin").unwrap();
+    fs.rename("/HELLO.TXT", "/Some Directory/deeper/Greeting.txt").unwrap();
+    let mut file = fs.create_file("/Some Directory/Written by the fat32 crate.bin").unwrap();
+    file.write_all(&big_data()).unwrap();
+    drop(fs);
+
+    verify(&device.data);
+    let fs = VFat::from(MemDevice::new(device.data)).unwrap();
+    assert_eq!(read(&fs, "/Some Directory/deeper/greeting.txt"), b"Hello from mtools!\n");
+    assert!(read(&fs, "/some directory/written by the fat32 crate.bin") == big_data());
+}
@@ -0,0 +1,194 @@
+//! The interfaces the filesystem reads devices through and exposes files
+//! and directories with.
+//!
+//! The `VFat` types implement these under the same names, so import the
+//! traits anonymously, e.g. `use fat32::traits::{Dir as _, Entry as _}`, or
+//! refer to them through the module.
+
+use shim::io;
+
+/// A device that reads and writes fixed-size sectors, such as an SD card.
+pub trait BlockDevice {
+    /// Returns the size of a sector in bytes.
+    fn sector_size(&self) -> u64 {
+        512
+    }
+
+    /// Reads sector `n` into `buf`, returning the number of bytes read. At
+    /// most `sector_size()` bytes are read; a shorter `buf` receives the
+    /// start of the sector.
+    fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes `buf` to sector `n`, returning the number of bytes written. At
+    /// most `sector_size()` bytes are written; a shorter `buf` overwrites
+    /// the start of the sector.
+    fn write_sector(&mut self, n: u64, buf: &[u8]) -> io::Result<usize>;
+}
+
+impl<T: BlockDevice + ?Sized> BlockDevice for &mut T {
+    fn sector_size(&self) -> u64 {
+        (**self).sector_size()
+    }
+
+    fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_sector(n, buf)
+    }
+
+    fn write_sector(&mut self, n: u64, buf: &[u8]) -> io::Result<usize> {
+        (**self).write_sector(n, buf)
+    }
+}
+
+/// A point in time as recorded by the filesystem.
+pub trait Timestamp: Copy + Clone + Sized {
+    /// The calendar year.
+    fn year(&self) -> usize;
+
+    /// The month, from 1 to 12.
+    fn month(&self) -> u8;
+
+    /// The day of the month, from 1 to 31.
+    fn day(&self) -> u8;
+
+    /// The hour, from 0 to 23.
+    fn hour(&self) -> u8;
+
+    /// The minute, from 0 to 59.
+    fn minute(&self) -> u8;
+
+    /// The second, from 0 to 59.
+    fn second(&self) -> u8;
+}
+
+/// The attributes and timestamps of a file or directory.
+pub trait Metadata: Sized {
+    /// The type timestamps are reported in.
+    type Timestamp: Timestamp;
+
+    /// Whether the entry may not be written to.
+    fn read_only(&self) -> bool;
+
+    /// Whether the entry should be left out of ordinary listings.
+    fn hidden(&self) -> bool;
+
+    /// When the entry was created.
+    fn created(&self) -> Self::Timestamp;
+
+    /// When the entry was last accessed.
+    fn accessed(&self) -> Self::Timestamp;
+
+    /// When the entry was last modified.
+    fn modified(&self) -> Self::Timestamp;
+}
+
+/// An open regular file.
+pub trait File: io::Read + io::Write + io::Seek + Sized {
+    /// Returns the size of the file in bytes.
+    fn size(&self) -> u64;
+
+    /// Truncates or extends the file to `len` bytes. Extending the file
+    /// fills it with zeroes. The position of the next read or write is left
+    /// where it is.
+    fn set_len(&mut self, len: u64) -> io::Result<()>;
+}
+
+/// An open directory.
+pub trait Dir: Sized {
+    /// The type of the directory's entries.
+    type Entry: Entry;
+
+    /// An iterator over the directory's entries.
+    type Iter: Iterator<Item = Self::Entry>;
+
+    /// Returns an iterator over the entries in this directory.
+    fn entries(&self) -> io::Result<Self::Iter>;
+}
+
+/// A directory entry: either a file or a directory.
+pub trait Entry: Sized {
+    /// The type of a file entry.
+    type File: File;
+
+    /// The type of a directory entry.
+    type Dir: Dir;
+
+    /// The type of the entry's metadata.
+    type Metadata: Metadata;
+
+    /// Returns the entry's name, without any leading path.
+    fn name(&self) -> &str;
+
+    /// Returns the entry's metadata.
+    fn metadata(&self) -> &Self::Metadata;
+
+    /// Returns the entry as a file, if it is one.
+    fn as_file(&self) -> Option<&Self::File>;
+
+    /// Returns the entry as a directory, if it is one.
+    fn as_dir(&self) -> Option<&Self::Dir>;
+
+    /// Converts the entry into a file, if it is one.
+    fn into_file(self) -> Option<Self::File>;
+
+    /// Converts the entry into a directory, if it is one.
+    fn into_dir(self) -> Option<Self::Dir>;
+
+    /// Returns `true` if the entry is a file.
+    fn is_file(&self) -> bool {
+        self.as_file().is_some()
+    }
+
+    /// Returns `true` if the entry is a directory.
+    fn is_dir(&self) -> bool {
+        self.as_dir().is_some()
+    }
+}
+
+/// A filesystem whose files are named by `/`-separated paths.
+pub trait FileSystem: Sized {
+    /// The type of files in this filesystem.
+    type File: File;
+
+    /// The type of directories in this filesystem.
+    type Dir: Dir<Entry = Self::Entry>;
+
+    /// The type of directory entries in this filesystem.
+    type Entry: Entry<File = Self::File, Dir = Self::Dir>;
+
+    /// Opens the entry at `path`, relative to the root directory. Empty
+    /// components and `.` are ignored, and `..` names the parent directory.
+    ///
+    /// Returns an error of kind `NotFound` if there is no entry at `path`,
+    /// or of kind `InvalidInput` if a component other than the last names a
+    /// file.
+    fn open(self, path: &str) -> io::Result<Self::Entry>;
+
+    /// Opens the file at `path`. Returns an error of kind `InvalidInput` if
+    /// `path` names a directory.
+    fn open_file(self, path: &str) -> io::Result<Self::File> {
+        self.open(path)?.into_file().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "is a directory"))
+    }
+
+    /// Opens the directory at `path`. Returns an error of kind
+    /// `InvalidInput` if `path` names a file.
+    fn open_dir(self, path: &str) -> io::Result<Self::Dir> {
+        self.open(path)?.into_dir().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a directory"))
+    }
+
+    /// Creates an empty file at `path` and opens it. Returns an error of
+    /// kind `AlreadyExists` if there is already an entry at `path`.
+    fn create_file(self, path: &str) -> io::Result<Self::File>;
+
+    /// Creates an empty directory at `path` and opens it. Returns an error
+    /// of kind `AlreadyExists` if there is already an entry at `path`.
+    fn create_dir(self, path: &str) -> io::Result<Self::Dir>;
+
+    /// Moves the entry at `from` to `to`, which may be in another
+    /// directory. Returns an error of kind `AlreadyExists` if there is
+    /// already a different entry at `to`; existing entries are never
+    /// replaced.
+    fn rename(self, from: &str, to: &str) -> io::Result<()>;
+
+    /// Removes the file or empty directory at `path`.
+    fn remove(self, path: &str) -> io::Result<()>;
+}
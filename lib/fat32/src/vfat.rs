@@ -0,0 +1,526 @@
+//! The FAT32 filesystem proper.
+//!
+//! `VFat` reads and writes a volume through a `CachedPartition` of a
+//! `BlockDevice`. Files and directories borrow the `VFat` they were opened
+//! from, so several can be open at once; the cache itself is behind a
+//! `RefCell` and only borrowed for the duration of each sector access.
+//!
+//! Changes stay in the cache until `VFat::flush` or `File::flush` writes
+//! them to the device.
+//!
+//! Open files and directories don't see each other's changes: a `File`
+//! keeps the size and first cluster it was opened with, so don't modify a
+//! file through one handle while another is open.
+
+mod cache;
+mod dir;
+mod ebpb;
+mod entry;
+mod fat;
+mod file;
+mod fsinfo;
+mod metadata;
+mod name;
+
+pub use self::cache::{CachedPartition, Partition, DEFAULT_CACHE_SECTORS};
+pub use self::dir::{Dir, EntryIter};
+pub use self::ebpb::BiosParameterBlock;
+pub use self::entry::Entry;
+pub use self::fat::{Cluster, FatEntry, Status};
+pub use self::file::File;
+pub use self::fsinfo::FsInfo;
+pub use self::metadata::{Attributes, Date, Metadata, Time, Timestamp};
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+
+use shim::io;
+
+use self::dir::Location;
+use self::fat::{EOC, FIRST_DATA_CLUSTER, FREE};
+use self::name::ENTRY_SIZE;
+use crate::mbr::{self, MasterBootRecord};
+use crate::traits::{self, BlockDevice};
+
+/// The size of a FAT32 FAT entry.
+const FAT_ENTRY_SIZE: u64 = 4;
+
+/// An error opening a FAT32 volume.
+#[derive(Debug)]
+pub enum Error {
+    /// The device failed to read a sector.
+    Io(io::Error),
+    /// The disk's master boot record is invalid.
+    Mbr(mbr::Error),
+    /// The partition table has no FAT32 partition.
+    NoPartition,
+    /// The partition doesn't start with a FAT32 boot sector.
+    BadSignature,
+    /// The volume's logical sectors, of the given size, aren't a multiple
+    /// of the size of the device's sectors.
+    UnsupportedSectorSize(u16),
+    /// The boot sector describes a volume that can't be read.
+    Invalid(&'static str),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<mbr::Error> for Error {
+    fn from(error: mbr::Error) -> Error {
+        Error::Mbr(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::Mbr(error) => error.fmt(f),
+            Error::NoPartition => f.write_str("no FAT32 partition found"),
+            Error::BadSignature => f.write_str("partition has no FAT32 boot sector"),
+            Error::UnsupportedSectorSize(size) => write!(f, "unsupported logical sector size {}", size),
+            Error::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+/// A FAT32 volume on a `BlockDevice`.
+///
+/// Changes are cached in memory until `flush` is called or the volume is
+/// dropped.
+pub struct VFat<D: BlockDevice> {
+    device: RefCell<CachedPartition<D>>,
+    ebpb: BiosParameterBlock,
+    /// The FSInfo hints, if the volume has a valid FSInfo sector.
+    fsinfo: Cell<Option<FsInfo>>,
+}
+
+impl<D: BlockDevice> VFat<D> {
+    /// Opens the volume in the first FAT32 partition listed in the master
+    /// boot record of `device`.
+    pub fn from(mut device: D) -> Result<VFat<D>, Error> {
+        let mbr = MasterBootRecord::from(&mut device)?;
+        let start = mbr.fat32_partition().ok_or(Error::NoPartition)?.relative_sector;
+        VFat::from_partition(device, u64::from(start))
+    }
+
+    /// Opens the volume whose boot sector is sector `start` of `device`.
+    /// Use this for devices formatted without a partition table.
+    pub fn from_partition(mut device: D, start: u64) -> Result<VFat<D>, Error> {
+        let mut sector = vec![0; device.sector_size() as usize];
+        if device.read_sector(start, &mut sector)? < sector.len() {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "boot sector is too short")));
+        }
+
+        let ebpb = BiosParameterBlock::parse(&sector)?;
+        let sector_size = u64::from(ebpb.bytes_per_sector);
+        if sector_size < device.sector_size() || sector_size % device.sector_size() != 0 {
+            return Err(Error::UnsupportedSectorSize(ebpb.bytes_per_sector));
+        }
+
+        let partition = Partition { start, num_sectors: u64::from(ebpb.total_sectors), sector_size };
+        let device = RefCell::new(CachedPartition::new(device, partition));
+        let vfat = VFat { device, ebpb, fsinfo: Cell::new(None) };
+        if vfat.check(Cluster::from(ebpb.root_cluster)).is_err() {
+            return Err(Error::Invalid("root directory cluster is out of range"));
+        }
+
+        let fsinfo_sector = u64::from(ebpb.fsinfo_sector);
+        if fsinfo_sector != 0 && fsinfo_sector < u64::from(ebpb.reserved_sectors) {
+            let mut sector = vec![0; sector_size as usize];
+            vfat.read_sector(fsinfo_sector, &mut sector)?;
+            vfat.fsinfo.set(FsInfo::parse(&sector));
+        }
+        Ok(vfat)
+    }
+
+    /// Returns the volume's BIOS parameter block.
+    pub fn ebpb(&self) -> &BiosParameterBlock {
+        &self.ebpb
+    }
+
+    /// Returns the root directory.
+    pub fn root(&self) -> Dir<'_, D> {
+        Dir::root(self)
+    }
+
+    /// Returns the number of bytes in a cluster.
+    pub fn cluster_size(&self) -> u64 {
+        self.ebpb.bytes_per_cluster()
+    }
+
+    /// Writes every change made to the volume since the last flush to the
+    /// device.
+    pub fn flush(&self) -> io::Result<()> {
+        self.device.borrow_mut().flush()
+    }
+
+    /// Returns the hints in the FSInfo sector, or `None` if the volume has
+    /// no valid FSInfo sector.
+    pub fn fsinfo(&self) -> Option<FsInfo> {
+        self.fsinfo.get()
+    }
+
+    /// Counts the free clusters by scanning the FAT. Unlike the FSInfo hint,
+    /// this is always right, but it reads the whole FAT.
+    pub fn free_clusters(&self) -> io::Result<u32> {
+        let mut free = 0;
+        for number in FIRST_DATA_CLUSTER..FIRST_DATA_CLUSTER + self.ebpb.cluster_count() {
+            if self.fat_entry(Cluster::from(number))?.status() == Status::Free {
+                free += 1;
+            }
+        }
+        Ok(free)
+    }
+
+    /// Returns the first cluster of the root directory.
+    pub(crate) fn root_cluster(&self) -> Cluster {
+        Cluster::from(self.ebpb.root_cluster)
+    }
+
+    /// Reads logical sector `n` of the volume into `buf`, which must be a
+    /// sector long.
+    fn read_sector(&self, n: u64, buf: &mut [u8]) -> io::Result<()> {
+        let read = self.device.borrow_mut().read_sector(n, buf)?;
+        if read < buf.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short sector read"));
+        }
+        Ok(())
+    }
+
+    /// Writes `buf`, which must be a sector long, to logical sector `n` of
+    /// the volume.
+    fn write_sector(&self, n: u64, buf: &[u8]) -> io::Result<()> {
+        let written = self.device.borrow_mut().write_sector(n, buf)?;
+        if written < buf.len() {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "short sector write"));
+        }
+        Ok(())
+    }
+
+    /// Returns `cluster` if it names a data cluster of this volume.
+    fn check(&self, cluster: Cluster) -> io::Result<Cluster> {
+        let number = cluster.number();
+        if number < FIRST_DATA_CLUSTER || number - FIRST_DATA_CLUSTER >= self.ebpb.cluster_count() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cluster number out of range"));
+        }
+        Ok(cluster)
+    }
+
+    /// Returns the first sector of `cluster`.
+    fn cluster_start(&self, cluster: Cluster) -> io::Result<u64> {
+        let cluster = self.check(cluster)?;
+        Ok(self.ebpb.data_start() + u64::from(cluster.index()) * u64::from(self.ebpb.sectors_per_cluster))
+    }
+
+    /// Reads from `cluster`, starting `offset` bytes into it, into `buf`.
+    /// Returns the number of bytes read, which is less than `buf.len()` if
+    /// the end of the cluster comes first.
+    pub(crate) fn read_cluster(&self, cluster: Cluster, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let first_sector = self.cluster_start(cluster)?;
+        let sector_size = u64::from(self.ebpb.bytes_per_sector);
+
+        let len = (self.cluster_size().saturating_sub(offset) as usize).min(buf.len());
+        let mut sector = vec![0; sector_size as usize];
+        let mut read = 0;
+        while read < len {
+            let position = offset + read as u64;
+            self.read_sector(first_sector + position / sector_size, &mut sector)?;
+
+            let start = (position % sector_size) as usize;
+            let n = (sector.len() - start).min(len - read);
+            buf[read..read + n].copy_from_slice(&sector[start..start + n]);
+            read += n;
+        }
+        Ok(read)
+    }
+
+    /// Writes `buf` into `cluster`, starting `offset` bytes into it.
+    /// Returns the number of bytes written, which is less than `buf.len()`
+    /// if the end of the cluster comes first.
+    pub(crate) fn write_cluster(&self, cluster: Cluster, offset: u64, buf: &[u8]) -> io::Result<usize> {
+        let first_sector = self.cluster_start(cluster)?;
+        let sector_size = u64::from(self.ebpb.bytes_per_sector);
+
+        let len = (self.cluster_size().saturating_sub(offset) as usize).min(buf.len());
+        let mut sector = vec![0; sector_size as usize];
+        let mut written = 0;
+        while written < len {
+            let position = offset + written as u64;
+            let start = (position % sector_size) as usize;
+            let n = (sector.len() - start).min(len - written);
+
+            // Only a partially overwritten sector needs to be read first.
+            let number = first_sector + position / sector_size;
+            if n < sector.len() {
+                self.read_sector(number, &mut sector)?;
+            }
+            sector[start..start + n].copy_from_slice(&buf[written..written + n]);
+            self.write_sector(number, &sector)?;
+            written += n;
+        }
+        Ok(written)
+    }
+
+    /// Fills `cluster` with zeroes.
+    fn zero_cluster(&self, cluster: Cluster) -> io::Result<()> {
+        let first_sector = self.cluster_start(cluster)?;
+        let sector = vec![0; usize::from(self.ebpb.bytes_per_sector)];
+        for i in 0..u64::from(self.ebpb.sectors_per_cluster) {
+            self.write_sector(first_sector + i, &sector)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sector of the FAT and the offset in it of the entry for
+    /// `cluster`, relative to the start of a FAT.
+    fn fat_position(&self, cluster: Cluster) -> io::Result<(u64, usize)> {
+        let cluster = self.check(cluster)?;
+        let sector_size = u64::from(self.ebpb.bytes_per_sector);
+        let offset = u64::from(cluster.number()) * FAT_ENTRY_SIZE;
+        Ok((offset / sector_size, (offset % sector_size) as usize))
+    }
+
+    /// Reads the FAT entry for `cluster` from the active FAT.
+    pub(crate) fn fat_entry(&self, cluster: Cluster) -> io::Result<FatEntry> {
+        let (sector_index, offset) = self.fat_position(cluster)?;
+        let mut sector = vec![0; usize::from(self.ebpb.bytes_per_sector)];
+        self.read_sector(self.ebpb.fat_start(self.ebpb.active_fat()) + sector_index, &mut sector)?;
+        Ok(FatEntry(crate::le32(&sector, offset)))
+    }
+
+    /// Sets the FAT entry for `cluster` to `value` in every FAT that is
+    /// kept up to date: all of them, unless mirroring is disabled. The
+    /// reserved top four bits of the entry are preserved.
+    pub(crate) fn set_fat_entry(&self, cluster: Cluster, value: u32) -> io::Result<()> {
+        let (sector_index, offset) = self.fat_position(cluster)?;
+        let fats = if self.ebpb.is_mirrored() {
+            0..self.ebpb.num_fats
+        } else {
+            self.ebpb.active_fat()..self.ebpb.active_fat() + 1
+        };
+
+        let mut sector = vec![0; usize::from(self.ebpb.bytes_per_sector)];
+        for fat in fats {
+            let number = self.ebpb.fat_start(fat) + sector_index;
+            self.read_sector(number, &mut sector)?;
+            let entry = FatEntry(crate::le32(&sector, offset)).with_value(value);
+            sector[offset..offset + 4].copy_from_slice(&entry.0.to_le_bytes());
+            self.write_sector(number, &sector)?;
+        }
+        Ok(())
+    }
+
+    /// Updates the FSInfo hints with `update` and writes them back.
+    fn update_fsinfo<F: FnOnce(&mut FsInfo)>(&self, update: F) -> io::Result<()> {
+        let mut fsinfo = match self.fsinfo.get() {
+            Some(fsinfo) => fsinfo,
+            None => return Ok(()),
+        };
+        update(&mut fsinfo);
+        self.fsinfo.set(Some(fsinfo));
+
+        let number = u64::from(self.ebpb.fsinfo_sector);
+        let mut sector = vec![0; usize::from(self.ebpb.bytes_per_sector)];
+        self.read_sector(number, &mut sector)?;
+        fsinfo.write(&mut sector);
+        self.write_sector(number, &sector)
+    }
+
+    /// Allocates a zeroed cluster and appends it to the chain ending at
+    /// `prev`, or starts a new chain if `prev` is `None`.
+    ///
+    /// The search for a free cluster starts at the FSInfo hint and wraps
+    /// around the volume. Returns an error of kind `Other` if the volume is
+    /// full.
+    pub(crate) fn alloc_cluster(&self, prev: Option<Cluster>) -> io::Result<Cluster> {
+        let count = self.ebpb.cluster_count();
+        let hint = match self.fsinfo.get() {
+            Some(fsinfo) if self.check(Cluster::from(fsinfo.next_free)).is_ok() => fsinfo.next_free,
+            _ => FIRST_DATA_CLUSTER,
+        };
+
+        let mut found = None;
+        for i in 0..count {
+            let cluster = Cluster::from(FIRST_DATA_CLUSTER + (hint - FIRST_DATA_CLUSTER + i) % count);
+            if self.fat_entry(cluster)?.status() == Status::Free {
+                found = Some(cluster);
+                break;
+            }
+        }
+        let cluster = found.ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no space left on volume"))?;
+
+        self.zero_cluster(cluster)?;
+        self.set_fat_entry(cluster, EOC)?;
+        if let Some(prev) = prev {
+            self.set_fat_entry(prev, cluster.number())?;
+        }
+
+        let next = if cluster.index() + 1 < count { cluster.number() + 1 } else { FIRST_DATA_CLUSTER };
+        self.update_fsinfo(|fsinfo| {
+            if fsinfo.free_count <= count {
+                fsinfo.free_count = fsinfo.free_count.saturating_sub(1);
+            }
+            fsinfo.next_free = next;
+        })?;
+        Ok(cluster)
+    }
+
+    /// Frees every cluster in the chain starting at `start`.
+    pub(crate) fn free_chain(&self, start: Cluster) -> io::Result<()> {
+        let chain = self.chain(start)?;
+        for &cluster in chain.iter() {
+            self.set_fat_entry(cluster, FREE)?;
+        }
+
+        let count = self.ebpb.cluster_count();
+        self.update_fsinfo(|fsinfo| {
+            if fsinfo.free_count <= count {
+                fsinfo.free_count = (fsinfo.free_count + chain.len() as u32).min(count);
+            }
+        })
+    }
+
+    /// Returns the cluster following `cluster` in its chain, or `None` if
+    /// `cluster` is the last one. A chain leading to a free, reserved or
+    /// bad cluster is an error.
+    pub(crate) fn next_cluster(&self, cluster: Cluster) -> io::Result<Option<Cluster>> {
+        match self.fat_entry(cluster)?.status() {
+            Status::Data(next) => self.check(next).map(Some),
+            Status::Eoc(_) => Ok(None),
+            Status::Free | Status::Reserved | Status::Bad => {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "cluster chain is broken"))
+            }
+        }
+    }
+
+    /// Returns the clusters in the chain starting at `start`, in order.
+    ///
+    /// A chain longer than the volume has clusters must loop back on
+    /// itself, and is reported as an error rather than followed forever.
+    pub(crate) fn chain(&self, start: Cluster) -> io::Result<Vec<Cluster>> {
+        let mut chain = Vec::new();
+        let mut cluster = Some(self.check(start)?);
+
+        while let Some(current) = cluster {
+            if chain.len() as u32 == self.ebpb.cluster_count() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "cluster chain loops"));
+            }
+            chain.push(current);
+            cluster = self.next_cluster(current)?;
+        }
+        Ok(chain)
+    }
+
+    /// Appends the contents of every cluster in the chain starting at
+    /// `start` to `buf`. Returns the clusters read, in order.
+    pub(crate) fn read_chain(&self, start: Cluster, buf: &mut Vec<u8>) -> io::Result<Vec<Cluster>> {
+        let cluster_size = self.cluster_size() as usize;
+        let chain = self.chain(start)?;
+        for &cluster in chain.iter() {
+            let len = buf.len();
+            buf.resize(len + cluster_size, 0);
+            self.read_cluster(cluster, 0, &mut buf[len..])?;
+        }
+        Ok(chain)
+    }
+
+    /// Returns the cluster holding directory entry `index` of the directory
+    /// starting at `dir`, and the entry's offset in it.
+    fn slot(&self, dir: Cluster, index: u32) -> io::Result<(Cluster, u64)> {
+        let position = u64::from(index) * ENTRY_SIZE as u64;
+        let mut cluster = dir;
+        for _ in 0..position / self.cluster_size() {
+            cluster = self
+                .next_cluster(cluster)?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "directory entry is past the end"))?;
+        }
+        Ok((cluster, position % self.cluster_size()))
+    }
+
+    /// Reads directory entry `index` of the directory starting at `dir`.
+    pub(crate) fn read_entry(&self, dir: Cluster, index: u32) -> io::Result<[u8; ENTRY_SIZE]> {
+        let (cluster, offset) = self.slot(dir, index)?;
+        let mut raw = [0; ENTRY_SIZE];
+        self.read_cluster(cluster, offset, &mut raw)?;
+        Ok(raw)
+    }
+
+    /// Writes directory entry `index` of the directory starting at `dir`.
+    pub(crate) fn write_entry(&self, dir: Cluster, index: u32, raw: &[u8]) -> io::Result<()> {
+        let (cluster, offset) = self.slot(dir, index)?;
+        self.write_cluster(cluster, offset, raw)?;
+        Ok(())
+    }
+
+    /// Marks the 8.3 entry at `location` and its LFN entries deleted.
+    pub(crate) fn delete_entry(&self, location: Location) -> io::Result<()> {
+        for index in location.index - location.lfn_count..=location.index {
+            self.write_entry(location.dir, index, &[name::DELETED])?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> fmt::Debug for VFat<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VFat").field("device", &self.device).field("ebpb", &self.ebpb).finish()
+    }
+}
+
+impl<'a, D: BlockDevice> traits::FileSystem for &'a VFat<D> {
+    type File = File<'a, D>;
+    type Dir = Dir<'a, D>;
+    type Entry = Entry<'a, D>;
+
+    fn open(self, path: &str) -> io::Result<Entry<'a, D>> {
+        let mut entry = Entry::Dir(self.root());
+        for component in path.split('/').filter(|c| !c.is_empty() && *c != ".") {
+            let dir = match entry {
+                Entry::Dir(dir) => dir,
+                Entry::File(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a directory")),
+            };
+
+            // The root has no `..` entry of its own.
+            entry = if component == ".." && dir.is_root() { Entry::Dir(dir) } else { dir.find(component)? };
+        }
+        Ok(entry)
+    }
+
+    fn create_file(self, path: &str) -> io::Result<File<'a, D>> {
+        let (parent, name) = split_path(path);
+        self.open_dir(parent)?.create_file(name)
+    }
+
+    fn create_dir(self, path: &str) -> io::Result<Dir<'a, D>> {
+        let (parent, name) = split_path(path);
+        self.open_dir(parent)?.create_dir(name)
+    }
+
+    fn rename(self, from: &str, to: &str) -> io::Result<()> {
+        let (from_parent, from_name) = split_path(from);
+        let (to_parent, to_name) = split_path(to);
+        self.open_dir(from_parent)?.rename(from_name, &self.open_dir(to_parent)?, to_name)
+    }
+
+    fn remove(self, path: &str) -> io::Result<()> {
+        let (parent, name) = split_path(path);
+        self.open_dir(parent)?.remove(name)
+    }
+}
+
+/// Splits `path` into the path of its parent directory and its last
+/// component, ignoring trailing slashes.
+fn split_path(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(slash) => (&path[..slash], &path[slash + 1..]),
+        None => ("", path),
+    }
+}
@@ -0,0 +1,236 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use shim::io;
+
+use crate::traits::BlockDevice;
+
+/// Where a partition is on a device, and the size of its logical sectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partition {
+    /// The physical sector of the device the partition starts at.
+    pub start: u64,
+    /// The number of logical sectors in the partition.
+    pub num_sectors: u64,
+    /// The size of a logical sector in bytes. This must be a multiple of
+    /// the device's sector size.
+    pub sector_size: u64,
+}
+
+/// The number of logical sectors `CachedPartition::new` keeps in memory.
+pub const DEFAULT_CACHE_SECTORS: usize = 256;
+
+/// A logical sector held in memory.
+struct CacheEntry {
+    data: Vec<u8>,
+    /// Whether `data` has changed since it was read or last written back.
+    dirty: bool,
+    /// When the sector was last used, as a value of `CachedPartition::clock`.
+    last_used: u64,
+}
+
+/// A partition of a `BlockDevice`, read and written in logical sectors that
+/// may span several physical ones.
+///
+/// Sectors read or written are kept in memory, up to a fixed number of them.
+/// When the cache is full, the least recently used sector that hasn't changed
+/// is dropped to make room; if every cached sector has changed, they are all
+/// written back first. Otherwise, writes stay in memory until `flush` writes
+/// them back, or until the cache is dropped. Errors writing back on drop are
+/// ignored; call `flush` to see them.
+pub struct CachedPartition<D: BlockDevice> {
+    device: D,
+    cache: BTreeMap<u64, CacheEntry>,
+    /// The most sectors `cache` may hold.
+    capacity: usize,
+    /// Counts sector uses, to find the least recently used one.
+    clock: u64,
+    partition: Partition,
+}
+
+impl<D: BlockDevice> CachedPartition<D> {
+    /// Returns a cache of `partition` on `device` that holds up to
+    /// `DEFAULT_CACHE_SECTORS` sectors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the partition's sector size isn't a nonzero multiple of
+    /// the device's.
+    pub fn new(device: D, partition: Partition) -> CachedPartition<D> {
+        CachedPartition::with_capacity(device, partition, DEFAULT_CACHE_SECTORS)
+    }
+
+    /// Returns a cache of `partition` on `device` that holds up to `capacity`
+    /// sectors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero or if the partition's sector size isn't
+    /// a nonzero multiple of the device's.
+    pub fn with_capacity(device: D, partition: Partition, capacity: usize) -> CachedPartition<D> {
+        assert!(capacity != 0, "cache capacity must be nonzero");
+        assert!(
+            partition.sector_size != 0 && partition.sector_size % device.sector_size() == 0,
+            "logical sector size must be a multiple of the physical sector size"
+        );
+        CachedPartition { device, cache: BTreeMap::new(), capacity, clock: 0, partition }
+    }
+
+    /// Returns the partition this cache covers.
+    pub fn partition(&self) -> &Partition {
+        &self.partition
+    }
+
+    /// Returns the number of physical sectors in a logical sector.
+    fn factor(&self) -> u64 {
+        self.partition.sector_size / self.device.sector_size()
+    }
+
+    /// Returns the first physical sector of logical sector `virt`, or `None`
+    /// if `virt` is outside the partition.
+    fn virtual_to_physical(&self, virt: u64) -> Option<u64> {
+        if virt >= self.partition.num_sectors {
+            return None;
+        }
+        Some(self.partition.start + virt * self.factor())
+    }
+
+    /// Returns the cache entry for logical sector `sector`, reading it from
+    /// the device if it isn't cached.
+    fn entry(&mut self, sector: u64) -> io::Result<&mut CacheEntry> {
+        if !self.cache.contains_key(&sector) {
+            let physical = self.sector_start(sector)?;
+            self.make_room()?;
+            let physical_size = self.device.sector_size() as usize;
+
+            let mut data = vec![0; self.partition.sector_size as usize];
+            for (i, chunk) in data.chunks_mut(physical_size).enumerate() {
+                if self.device.read_sector(physical + i as u64, chunk)? < physical_size {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short sector read"));
+                }
+            }
+            self.cache.insert(sector, CacheEntry { data, dirty: false, last_used: 0 });
+        }
+
+        self.clock += 1;
+        let entry = self.cache.get_mut(&sector).expect("sector was just cached");
+        entry.last_used = self.clock;
+        Ok(entry)
+    }
+
+    /// Drops a sector from the cache if it is full: the least recently used
+    /// clean one, after writing every sector back if none are clean.
+    fn make_room(&mut self) -> io::Result<()> {
+        if self.cache.len() < self.capacity {
+            return Ok(());
+        }
+        if self.cache.values().all(|entry| entry.dirty) {
+            self.flush()?;
+        }
+
+        let oldest = self
+            .cache
+            .iter()
+            .filter(|(_, entry)| !entry.dirty)
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(&sector, _)| sector)
+            .expect("a flushed cache has clean sectors");
+        self.cache.remove(&oldest);
+        Ok(())
+    }
+
+    /// Returns the first physical sector of logical sector `sector`, or an
+    /// error of kind `InvalidInput` if it is outside the partition.
+    fn sector_start(&self, sector: u64) -> io::Result<u64> {
+        self.virtual_to_physical(sector)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "sector is outside the partition"))
+    }
+
+    /// Returns the contents of logical sector `sector`.
+    ///
+    /// Returns an error of kind `InvalidInput` if the sector is outside the
+    /// partition, or any error reading it from the device.
+    pub fn get(&mut self, sector: u64) -> io::Result<&[u8]> {
+        Ok(&self.entry(sector)?.data)
+    }
+
+    /// Returns the contents of logical sector `sector` for writing. The
+    /// sector is written back to the device by the next `flush`.
+    ///
+    /// Returns an error of kind `InvalidInput` if the sector is outside the
+    /// partition, or any error reading it from the device.
+    pub fn get_mut(&mut self, sector: u64) -> io::Result<&mut [u8]> {
+        let entry = self.entry(sector)?;
+        entry.dirty = true;
+        Ok(&mut entry.data)
+    }
+
+    /// Writes every sector changed since the last flush back to the device.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let physical_size = self.device.sector_size() as usize;
+        let factor = self.factor();
+        let start = self.partition.start;
+
+        for (&sector, entry) in self.cache.iter_mut().filter(|(_, entry)| entry.dirty) {
+            for (i, chunk) in entry.data.chunks(physical_size).enumerate() {
+                let physical = start + sector * factor + i as u64;
+                if self.device.write_sector(physical, chunk)? < physical_size {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "short sector write"));
+                }
+            }
+            entry.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> fmt::Debug for CachedPartition<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dirty = self.cache.values().filter(|entry| entry.dirty).count();
+        f.debug_struct("CachedPartition")
+            .field("partition", &self.partition)
+            .field("cached", &self.cache.len())
+            .field("capacity", &self.capacity)
+            .field("dirty", &dirty)
+            .finish()
+    }
+}
+
+impl<D: BlockDevice> Drop for CachedPartition<D> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for CachedPartition<D> {
+    /// Returns the size of a logical sector.
+    fn sector_size(&self) -> u64 {
+        self.partition.sector_size
+    }
+
+    fn read_sector(&mut self, n: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.get(n)?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn write_sector(&mut self, n: u64, buf: &[u8]) -> io::Result<usize> {
+        let sector_size = self.partition.sector_size as usize;
+        if buf.len() >= sector_size && !self.cache.contains_key(&n) {
+            // The whole sector is replaced, so there is no need to read it.
+            self.sector_start(n)?;
+            self.make_room()?;
+            self.clock += 1;
+            self.cache.insert(n, CacheEntry { data: buf[..sector_size].to_vec(), dirty: true, last_used: self.clock });
+            return Ok(sector_size);
+        }
+
+        let data = self.get_mut(n)?;
+        let len = data.len().min(buf.len());
+        data[..len].copy_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
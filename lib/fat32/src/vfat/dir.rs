@@ -0,0 +1,518 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use shim::io;
+
+use crate::traits::{self, BlockDevice};
+use crate::vfat::name::{self, DELETED, ENTRY_SIZE, LFN_CHARS, LFN_CHECKSUM, LFN_LAST, LFN_MAX_ENTRIES, LFN_SEQUENCE};
+use crate::vfat::{Attributes, Cluster, Entry, File, Metadata, VFat};
+use crate::{le16, le32};
+
+/// The first name byte of the entry that ends a directory.
+const END_OF_DIR: u8 = 0x00;
+
+/// The most entries a directory may have.
+const MAX_ENTRIES: u32 = 65536;
+
+/// The names of the `.` and `..` entries, as 8.3 names.
+const DOT: [u8; 11] = *b".          ";
+const DOT_DOT: [u8; 11] = *b"..         ";
+
+/// The date stamped on new entries: 1980-01-01, the earliest FAT can
+/// record, since there is no clock to read.
+const DEFAULT_DATE: u16 = 0x0021;
+
+/// The offsets of fields of an 8.3 entry.
+const ATTRIBUTES_OFFSET: usize = 11;
+const CASE_OFFSET: usize = 12;
+const CREATED_DATE_OFFSET: usize = 16;
+const ACCESSED_DATE_OFFSET: usize = 18;
+const CLUSTER_HIGH_OFFSET: usize = 20;
+const MODIFIED_DATE_OFFSET: usize = 24;
+const CLUSTER_LOW_OFFSET: usize = 26;
+const SIZE_OFFSET: usize = 28;
+
+/// Where an 8.3 entry is stored: its index in the directory starting at
+/// `dir`, and how many LFN entries precede it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Location {
+    pub dir: Cluster,
+    pub index: u32,
+    pub lfn_count: u32,
+}
+
+/// A directory.
+pub struct Dir<'a, D: BlockDevice> {
+    fs: &'a VFat<D>,
+    name: String,
+    metadata: Metadata,
+    start: Cluster,
+    /// Where the directory's entry is, or `None` for the root.
+    location: Option<Location>,
+}
+
+impl<'a, D: BlockDevice> Dir<'a, D> {
+    /// Returns the root directory of `fs`.
+    pub(crate) fn root(fs: &'a VFat<D>) -> Dir<'a, D> {
+        Dir { fs, name: String::from("/"), metadata: Metadata::default(), start: fs.root_cluster(), location: None }
+    }
+
+    /// Returns the directory's name. The root directory is named `/`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the directory's metadata. The root directory has none, and
+    /// returns all zeroes.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the first cluster of the directory.
+    pub fn cluster(&self) -> Cluster {
+        self.start
+    }
+
+    /// Returns `true` if this is the root directory.
+    pub fn is_root(&self) -> bool {
+        self.start == self.fs.root_cluster()
+    }
+
+    /// Returns the entry named `name`, ignoring ASCII case as FAT does.
+    /// Returns an error of kind `NotFound` if there is no such entry.
+    pub fn find(&self, name: &str) -> io::Result<Entry<'a, D>> {
+        traits::Dir::entries(self)?
+            .find(|entry| traits::Entry::name(entry).eq_ignore_ascii_case(name))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file or directory"))
+    }
+
+    /// Returns an error of kind `AlreadyExists` if there is an entry named
+    /// `name`.
+    fn check_absent(&self, name: &str) -> io::Result<()> {
+        match self.find(name) {
+            Ok(_) => Err(io::Error::new(io::ErrorKind::AlreadyExists, "entry already exists")),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates an empty file named `name` in this directory and opens it.
+    ///
+    /// Returns an error of kind `InvalidInput` if `name` isn't a valid name,
+    /// or of kind `AlreadyExists` if there is already an entry named `name`.
+    pub fn create_file(&self, name: &str) -> io::Result<File<'a, D>> {
+        name::validate(name)?;
+        self.check_absent(name)?;
+
+        let raw = short_entry(Attributes::ARCHIVE, 0, 0);
+        let location = self.insert(name, raw, None)?;
+        Ok(File::new(self.fs, String::from(name), Metadata::parse(&raw), None, 0, location))
+    }
+
+    /// Creates an empty directory named `name` in this directory and opens
+    /// it.
+    ///
+    /// Returns an error of kind `InvalidInput` if `name` isn't a valid name,
+    /// or of kind `AlreadyExists` if there is already an entry named `name`.
+    pub fn create_dir(&self, name: &str) -> io::Result<Dir<'a, D>> {
+        name::validate(name)?;
+        self.check_absent(name)?;
+
+        let start = self.fs.alloc_cluster(None)?;
+        let raw = short_entry(Attributes::DIRECTORY, start.number(), 0);
+        let result = self.init_dir(start).and_then(|_| self.insert(name, raw, None));
+        let location = match result {
+            Ok(location) => location,
+            Err(e) => {
+                self.fs.free_chain(start)?;
+                return Err(e);
+            }
+        };
+
+        Ok(Dir {
+            fs: self.fs,
+            name: String::from(name),
+            metadata: Metadata::parse(&raw),
+            start,
+            location: Some(location),
+        })
+    }
+
+    /// Writes the `.` and `..` entries of a new directory, a child of this
+    /// one, into its first cluster `start`.
+    fn init_dir(&self, start: Cluster) -> io::Result<()> {
+        let mut dot = short_entry(Attributes::DIRECTORY, start.number(), 0);
+        dot[..11].copy_from_slice(&DOT);
+        self.fs.write_entry(start, 0, &dot)?;
+
+        let mut dot_dot = short_entry(Attributes::DIRECTORY, self.parent_link(), 0);
+        dot_dot[..11].copy_from_slice(&DOT_DOT);
+        self.fs.write_entry(start, 1, &dot_dot)
+    }
+
+    /// Returns the cluster number the `..` entry of a child of this
+    /// directory records: 0 if this is the root.
+    fn parent_link(&self) -> u32 {
+        if self.is_root() {
+            0
+        } else {
+            self.start.number()
+        }
+    }
+
+    /// Removes the file or empty directory named `name`.
+    ///
+    /// Returns an error of kind `NotFound` if there is no such entry, or of
+    /// kind `Other` if it is a directory that isn't empty.
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        name::validate(name)?;
+        let (location, start) = match self.find(name)? {
+            Entry::File(file) => (file.location(), file.cluster()),
+            Entry::Dir(dir) => {
+                let mut entries = traits::Dir::entries(&dir)?;
+                if entries.any(|entry| !is_dot(traits::Entry::name(&entry))) {
+                    return Err(io::Error::new(io::ErrorKind::Other, "directory is not empty"));
+                }
+                (dir.location.expect("only the root has no entry"), Some(dir.start))
+            }
+        };
+
+        // Free the clusters last, so a failure part way leaves lost
+        // clusters rather than an entry pointing at free ones.
+        self.fs.delete_entry(location)?;
+        match start {
+            Some(start) => self.fs.free_chain(start),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves the entry named `from` in this directory to `to` in directory
+    /// `to_dir`, which may be this one.
+    ///
+    /// Returns an error of kind `AlreadyExists` if `to_dir` has a different
+    /// entry named `to`, or of kind `InvalidInput` if `to` isn't a valid name
+    /// or the move would put a directory inside itself.
+    pub fn rename(&self, from: &str, to_dir: &Dir<'a, D>, to: &str) -> io::Result<()> {
+        name::validate(from)?;
+        name::validate(to)?;
+
+        let entry = self.find(from)?;
+        let (location, moved_dir) = match entry {
+            Entry::File(ref file) => (file.location(), None),
+            Entry::Dir(ref dir) => (dir.location.expect("only the root has no entry"), Some(dir.start)),
+        };
+
+        match to_dir.find(to) {
+            Ok(ref existing) if entry_location(existing) == Some(location) => {}
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "entry already exists")),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let moves_dir = moved_dir.filter(|_| to_dir.start != self.start);
+        if let Some(start) = moves_dir {
+            to_dir.check_not_inside(start)?;
+        }
+
+        // Add the new entry before deleting the old one, so a failure part
+        // way leaves the entry in both places rather than in neither.
+        let raw = self.fs.read_entry(location.dir, location.index)?;
+        to_dir.insert(to, raw, Some(location))?;
+        self.fs.delete_entry(location)?;
+
+        if let Some(start) = moves_dir {
+            let mut dot_dot = self.fs.read_entry(start, 1)?;
+            set_cluster(&mut dot_dot, to_dir.parent_link());
+            self.fs.write_entry(start, 1, &dot_dot)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error of kind `InvalidInput` if this directory is the
+    /// directory starting at `start`, or inside it.
+    fn check_not_inside(&self, start: Cluster) -> io::Result<()> {
+        let mut current = self.start;
+        for _ in 0..self.fs.ebpb().cluster_count() {
+            if current == start {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot move a directory inside itself"));
+            }
+            if current == self.fs.root_cluster() {
+                return Ok(());
+            }
+
+            let dot_dot = self.fs.read_entry(current, 1)?;
+            current = match cluster_of(&dot_dot) {
+                0 => self.fs.root_cluster(),
+                cluster => Cluster::from(cluster),
+            };
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "directory tree loops"))
+    }
+
+    /// Stores the 8.3 entry `raw` under the name `name`, adding LFN entries
+    /// if `name` isn't a valid 8.3 name, and returns where it was stored.
+    ///
+    /// The 8.3 name of the entry at `ignore` doesn't count as taken, so an
+    /// entry can be renamed to a name that only differs in case.
+    fn insert(&self, name: &str, mut raw: [u8; ENTRY_SIZE], ignore: Option<Location>) -> io::Result<Location> {
+        let mut data = Vec::new();
+        let chain = self.fs.read_chain(self.start, &mut data)?;
+        let slots = (data.len() / ENTRY_SIZE) as u32;
+        let slot = |index: u32| &data[index as usize * ENTRY_SIZE..(index as usize + 1) * ENTRY_SIZE];
+
+        let end = (0..slots).find(|&index| slot(index)[0] == END_OF_DIR).unwrap_or(slots);
+        let taken: Vec<[u8; 11]> = (0..end)
+            .filter(|&index| {
+                let raw = slot(index);
+                let ignored = ignore.filter(|l| l.dir == self.start).map(|l| l.index) == Some(index);
+                raw[0] != DELETED && !Attributes(raw[ATTRIBUTES_OFFSET]).is_lfn() && !ignored
+            })
+            .map(|index| {
+                let mut short = [0; 11];
+                short.copy_from_slice(&slot(index)[..11]);
+                short
+            })
+            .collect();
+
+        let mut entries = match name::exact_short_name(name) {
+            Some((short, case)) if !taken.contains(&short) => {
+                raw[..11].copy_from_slice(&short);
+                raw[CASE_OFFSET] = case;
+                Vec::new()
+            }
+            _ => {
+                let short = name::generate_short_name(name, &taken)?;
+                raw[..11].copy_from_slice(&short);
+                raw[CASE_OFFSET] = 0;
+                name::lfn_entries(name, &short)
+            }
+        };
+        entries.push(raw);
+
+        // Use the first run of unused entries long enough, counting every
+        // entry from the end of the directory on as unused.
+        let count = entries.len() as u32;
+        let mut first = 0;
+        for index in 0..end {
+            if slot(index)[0] != DELETED {
+                first = index + 1;
+            } else if index + 1 - first == count {
+                break;
+            }
+        }
+        if first + count > MAX_ENTRIES {
+            return Err(io::Error::new(io::ErrorKind::Other, "directory is full"));
+        }
+
+        let per_cluster = (self.fs.cluster_size() / ENTRY_SIZE as u64) as u32;
+        let mut last = *chain.last().expect("chains have a cluster");
+        let mut clusters = chain.len() as u32;
+        while (first + count) > clusters * per_cluster {
+            last = self.fs.alloc_cluster(Some(last))?;
+            clusters += 1;
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            self.fs.write_entry(self.start, first + i as u32, entry)?;
+        }
+
+        // If the run went past the old end of the directory, make sure the
+        // entry after it ends the directory. New clusters are already zero.
+        let after = first + count;
+        if after >= end && after < slots && slot(after)[0] != END_OF_DIR {
+            self.fs.write_entry(self.start, after, &[END_OF_DIR])?;
+        }
+
+        Ok(Location { dir: self.start, index: first + count - 1, lfn_count: count - 1 })
+    }
+}
+
+impl<'a, D: BlockDevice> fmt::Debug for Dir<'a, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dir")
+            .field("name", &self.name)
+            .field("metadata", &self.metadata)
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl<'a, D: BlockDevice> traits::Dir for Dir<'a, D> {
+    type Entry = Entry<'a, D>;
+    type Iter = EntryIter<'a, D>;
+
+    /// Reads the whole directory and returns an iterator over its entries,
+    /// including `.` and `..` but not deleted entries or the volume label.
+    fn entries(&self) -> io::Result<EntryIter<'a, D>> {
+        let mut data = Vec::new();
+        self.fs.read_chain(self.start, &mut data)?;
+        Ok(EntryIter { fs: self.fs, dir: self.start, data, offset: 0, long_name: None })
+    }
+}
+
+/// An iterator over the entries of a directory.
+pub struct EntryIter<'a, D: BlockDevice> {
+    fs: &'a VFat<D>,
+    /// The first cluster of the directory.
+    dir: Cluster,
+    data: Vec<u8>,
+    offset: usize,
+    /// The long name being collected for the next 8.3 entry.
+    long_name: Option<LongName>,
+}
+
+impl<'a, D: BlockDevice> Iterator for EntryIter<'a, D> {
+    type Item = Entry<'a, D>;
+
+    fn next(&mut self) -> Option<Entry<'a, D>> {
+        while self.offset + ENTRY_SIZE <= self.data.len() {
+            let mut raw = [0; ENTRY_SIZE];
+            raw.copy_from_slice(&self.data[self.offset..self.offset + ENTRY_SIZE]);
+            let index = (self.offset / ENTRY_SIZE) as u32;
+            self.offset += ENTRY_SIZE;
+
+            match raw[0] {
+                END_OF_DIR => {
+                    self.offset = self.data.len();
+                    return None;
+                }
+                DELETED => {
+                    self.long_name = None;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let attributes = Attributes(raw[ATTRIBUTES_OFFSET]);
+            if attributes.is_lfn() {
+                self.long_name = LongName::push(self.long_name.take(), &raw);
+                continue;
+            }
+
+            let long_name = self.long_name.take();
+            if attributes.volume_id() {
+                continue;
+            }
+
+            let (name, lfn_count) = long_name
+                .and_then(|name| name.finish(name::checksum(&raw[..11])))
+                .unwrap_or_else(|| (name::short_name(&raw), 0));
+            let location = Location { dir: self.dir, index, lfn_count };
+            return Some(entry(self.fs, name, &raw, location));
+        }
+        None
+    }
+}
+
+/// A long file name being reassembled from its LFN entries. These precede
+/// the 8.3 entry they name, last part first, each numbered with its
+/// position in the name and carrying a checksum of the 8.3 name.
+struct LongName {
+    checksum: u8,
+    /// The sequence number of the next entry expected; 0 once complete.
+    next: u8,
+    units: Vec<u16>,
+}
+
+impl LongName {
+    /// Adds the LFN entry `raw` to `name`, the name collected so far.
+    /// Returns `None` if `raw` doesn't continue `name`, dropping both.
+    fn push(name: Option<LongName>, raw: &[u8]) -> Option<LongName> {
+        let sequence = raw[0] & LFN_SEQUENCE;
+        let checksum = raw[LFN_CHECKSUM];
+
+        let mut name = if raw[0] & LFN_LAST != 0 {
+            if sequence == 0 || sequence > LFN_MAX_ENTRIES {
+                return None;
+            }
+            LongName { checksum, next: sequence, units: vec![0; usize::from(sequence) * LFN_CHARS] }
+        } else {
+            name?
+        };
+
+        if sequence == 0 || sequence != name.next || checksum != name.checksum {
+            return None;
+        }
+
+        let base = usize::from(sequence - 1) * LFN_CHARS;
+        name::read_lfn_units(raw, &mut name.units[base..base + LFN_CHARS]);
+        name.next -= 1;
+        Some(name)
+    }
+
+    /// Returns the name and the number of LFN entries it took, if every
+    /// part of it was collected and it belongs to the 8.3 name with checksum
+    /// `checksum`.
+    fn finish(self, checksum: u8) -> Option<(String, u32)> {
+        if self.next != 0 || self.checksum != checksum {
+            return None;
+        }
+        Some((name::long_name(&self.units), (self.units.len() / LFN_CHARS) as u32))
+    }
+}
+
+/// Returns `true` if `name` is `.` or `..`.
+fn is_dot(name: &str) -> bool {
+    name == "." || name == ".."
+}
+
+/// Returns where the entry `entry` is stored, or `None` for the root.
+fn entry_location<D: BlockDevice>(entry: &Entry<D>) -> Option<Location> {
+    match entry {
+        Entry::File(file) => Some(file.location()),
+        Entry::Dir(dir) => dir.location,
+    }
+}
+
+/// Returns a new 8.3 entry with no name, the attributes `attributes`, first
+/// cluster `cluster` and size `size`.
+fn short_entry(attributes: u8, cluster: u32, size: u32) -> [u8; ENTRY_SIZE] {
+    let mut raw = [0; ENTRY_SIZE];
+    raw[ATTRIBUTES_OFFSET] = attributes;
+    for &offset in [CREATED_DATE_OFFSET, ACCESSED_DATE_OFFSET, MODIFIED_DATE_OFFSET].iter() {
+        raw[offset..offset + 2].copy_from_slice(&DEFAULT_DATE.to_le_bytes());
+    }
+    set_cluster(&mut raw, cluster);
+    set_size(&mut raw, size);
+    raw
+}
+
+/// Returns the first cluster recorded in the 8.3 entry `raw`.
+fn cluster_of(raw: &[u8]) -> u32 {
+    u32::from(le16(raw, CLUSTER_HIGH_OFFSET)) << 16 | u32::from(le16(raw, CLUSTER_LOW_OFFSET))
+}
+
+/// Sets the first cluster recorded in the 8.3 entry `raw`.
+pub(crate) fn set_cluster(raw: &mut [u8], cluster: u32) {
+    raw[CLUSTER_HIGH_OFFSET..CLUSTER_HIGH_OFFSET + 2].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    raw[CLUSTER_LOW_OFFSET..CLUSTER_LOW_OFFSET + 2].copy_from_slice(&(cluster as u16).to_le_bytes());
+}
+
+/// Sets the file size recorded in the 8.3 entry `raw`.
+pub(crate) fn set_size(raw: &mut [u8], size: u32) {
+    raw[SIZE_OFFSET..SIZE_OFFSET + 4].copy_from_slice(&size.to_le_bytes());
+}
+
+/// Sets the attributes recorded in the 8.3 entry `raw`.
+pub(crate) fn set_attributes(raw: &mut [u8], attributes: Attributes) {
+    raw[ATTRIBUTES_OFFSET] = attributes.0;
+}
+
+/// Returns the entry the 8.3 entry `raw`, stored at `location`, describes,
+/// named `name`.
+fn entry<'a, D: BlockDevice>(fs: &'a VFat<D>, name: String, raw: &[u8], location: Location) -> Entry<'a, D> {
+    let metadata = Metadata::parse(raw);
+    let cluster = cluster_of(raw);
+
+    if metadata.attributes.directory() {
+        // `..` in a directory just below the root names cluster 0.
+        let start = if cluster == 0 { fs.root_cluster() } else { Cluster::from(cluster) };
+        let location = if start == fs.root_cluster() { None } else { Some(location) };
+        Entry::Dir(Dir { fs, name, metadata, start, location })
+    } else {
+        let start = if cluster == 0 { None } else { Some(Cluster::from(cluster)) };
+        Entry::File(File::new(fs, name, metadata, start, le32(raw, SIZE_OFFSET), location))
+    }
+}
@@ -0,0 +1,172 @@
+use core::str;
+
+use crate::vfat::Error;
+use crate::{le16, le32};
+
+/// The signature the boot sector ends with.
+const SIGNATURE: u16 = 0xAA55;
+
+/// The values of the extended boot signature that say the volume ID, label
+/// and system ID fields are present.
+const EXTENDED_SIGNATURES: [u8; 2] = [0x28, 0x29];
+
+/// The size of the fields parsed from the boot sector.
+const BOOT_SECTOR_SIZE: usize = 512;
+
+/// `flags` bit saying only one FAT is kept up to date rather than all of
+/// them being mirrors.
+const FLAG_NO_MIRRORING: u16 = 1 << 7;
+
+/// `flags` bits naming the active FAT when mirroring is disabled.
+const FLAG_ACTIVE_FAT: u16 = 0xF;
+
+/// The BIOS parameter block and FAT32's extension to it, from the first
+/// sector of the partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BiosParameterBlock {
+    /// The number of bytes in a logical sector: 512, 1024, 2048 or 4096.
+    pub bytes_per_sector: u16,
+    /// The number of sectors in a cluster, a power of two.
+    pub sectors_per_cluster: u8,
+    /// The number of sectors before the first FAT, including this one.
+    pub reserved_sectors: u16,
+    /// The number of copies of the FAT.
+    pub num_fats: u8,
+    /// The media descriptor, 0xF8 for fixed disks.
+    pub media: u8,
+    /// The number of sectors before the partition on the disk.
+    pub hidden_sectors: u32,
+    /// The number of sectors in the volume.
+    pub total_sectors: u32,
+    /// The number of sectors in each FAT.
+    pub sectors_per_fat: u32,
+    /// FAT mirroring flags.
+    pub flags: u16,
+    /// The FAT32 version; only 0.0 exists.
+    pub version: u16,
+    /// The first cluster of the root directory.
+    pub root_cluster: u32,
+    /// The sector of the FSInfo structure.
+    pub fsinfo_sector: u16,
+    /// The sector of the boot sector's backup copy.
+    pub backup_boot_sector: u16,
+    /// The BIOS drive number.
+    pub drive_number: u8,
+    /// The extended boot signature, 0x28 or 0x29.
+    pub signature: u8,
+    /// The volume's serial number.
+    pub volume_id: u32,
+    /// The volume label, padded with spaces.
+    pub volume_label: [u8; 11],
+    /// The filesystem type label, usually `FAT32   `.
+    pub system_id: [u8; 8],
+}
+
+impl BiosParameterBlock {
+    /// Parses the boot sector `sector`, which must be at least 512 bytes
+    /// long.
+    ///
+    /// Returns `Error::BadSignature` if the sector isn't a FAT boot sector
+    /// and `Error::Invalid` if it describes a volume this filesystem can't
+    /// read, including FAT12 and FAT16 volumes.
+    pub fn parse(sector: &[u8]) -> Result<BiosParameterBlock, Error> {
+        if sector.len() < BOOT_SECTOR_SIZE || le16(sector, BOOT_SECTOR_SIZE - 2) != SIGNATURE {
+            return Err(Error::BadSignature);
+        }
+
+        let mut volume_label = [0; 11];
+        volume_label.copy_from_slice(&sector[0x47..0x52]);
+        let mut system_id = [0; 8];
+        system_id.copy_from_slice(&sector[0x52..0x5A]);
+
+        let total_sectors_16 = le16(sector, 0x13);
+        let ebpb = BiosParameterBlock {
+            bytes_per_sector: le16(sector, 0x0B),
+            sectors_per_cluster: sector[0x0D],
+            reserved_sectors: le16(sector, 0x0E),
+            num_fats: sector[0x10],
+            media: sector[0x15],
+            hidden_sectors: le32(sector, 0x1C),
+            total_sectors: if total_sectors_16 != 0 { u32::from(total_sectors_16) } else { le32(sector, 0x20) },
+            sectors_per_fat: le32(sector, 0x24),
+            flags: le16(sector, 0x28),
+            version: le16(sector, 0x2A),
+            root_cluster: le32(sector, 0x2C),
+            fsinfo_sector: le16(sector, 0x30),
+            backup_boot_sector: le16(sector, 0x32),
+            drive_number: sector[0x40],
+            signature: sector[0x42],
+            volume_id: le32(sector, 0x43),
+            volume_label,
+            system_id,
+        };
+
+        // FAT12 and FAT16 keep the FAT size in the 16-bit field and have a
+        // fixed-size root directory instead of a root cluster.
+        if le16(sector, 0x16) != 0 || le16(sector, 0x11) != 0 || ebpb.sectors_per_fat == 0 {
+            return Err(Error::Invalid("not a FAT32 volume"));
+        }
+        if !EXTENDED_SIGNATURES.contains(&ebpb.signature) {
+            return Err(Error::BadSignature);
+        }
+        if !ebpb.bytes_per_sector.is_power_of_two() || ebpb.bytes_per_sector < 512 || ebpb.bytes_per_sector > 4096 {
+            return Err(Error::Invalid("invalid sector size"));
+        }
+        if !ebpb.sectors_per_cluster.is_power_of_two() {
+            return Err(Error::Invalid("invalid cluster size"));
+        }
+        if ebpb.num_fats == 0 || ebpb.reserved_sectors == 0 {
+            return Err(Error::Invalid("invalid FAT layout"));
+        }
+        if ebpb.version != 0 {
+            return Err(Error::Invalid("unsupported FAT32 version"));
+        }
+        if ebpb.data_start() >= u64::from(ebpb.total_sectors) {
+            return Err(Error::Invalid("volume has no data region"));
+        }
+
+        Ok(ebpb)
+    }
+
+    /// Returns the volume label with its padding removed.
+    pub fn label(&self) -> &str {
+        str::from_utf8(&self.volume_label).unwrap_or("").trim_end_matches(' ')
+    }
+
+    /// Returns the number of bytes in a cluster.
+    pub fn bytes_per_cluster(&self) -> u64 {
+        u64::from(self.bytes_per_sector) * u64::from(self.sectors_per_cluster)
+    }
+
+    /// Returns `true` if every FAT is kept up to date, rather than only the
+    /// active one.
+    pub fn is_mirrored(&self) -> bool {
+        self.flags & FLAG_NO_MIRRORING == 0
+    }
+
+    /// Returns the index of the FAT that reads should use: the first one,
+    /// unless mirroring is disabled and another one is marked active.
+    pub fn active_fat(&self) -> u8 {
+        if self.is_mirrored() {
+            0
+        } else {
+            (self.flags & FLAG_ACTIVE_FAT) as u8
+        }
+    }
+
+    /// Returns the first sector of the FAT with index `fat`.
+    pub fn fat_start(&self, fat: u8) -> u64 {
+        u64::from(self.reserved_sectors) + u64::from(fat) * u64::from(self.sectors_per_fat)
+    }
+
+    /// Returns the first sector of the data region, which holds cluster 2.
+    pub fn data_start(&self) -> u64 {
+        self.fat_start(self.num_fats)
+    }
+
+    /// Returns the number of data clusters in the volume. Valid cluster
+    /// numbers run from 2 to `cluster_count() + 1`.
+    pub fn cluster_count(&self) -> u32 {
+        ((u64::from(self.total_sectors) - self.data_start()) / u64::from(self.sectors_per_cluster)) as u32
+    }
+}
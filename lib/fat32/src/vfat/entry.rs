@@ -0,0 +1,67 @@
+use core::fmt;
+
+use crate::traits::{self, BlockDevice};
+use crate::vfat::{Dir, File, Metadata};
+
+/// An entry in a directory.
+pub enum Entry<'a, D: BlockDevice> {
+    File(File<'a, D>),
+    Dir(Dir<'a, D>),
+}
+
+impl<'a, D: BlockDevice> fmt::Debug for Entry<'a, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entry::File(file) => f.debug_tuple("File").field(file).finish(),
+            Entry::Dir(dir) => f.debug_tuple("Dir").field(dir).finish(),
+        }
+    }
+}
+
+impl<'a, D: BlockDevice> traits::Entry for Entry<'a, D> {
+    type File = File<'a, D>;
+    type Dir = Dir<'a, D>;
+    type Metadata = Metadata;
+
+    fn name(&self) -> &str {
+        match self {
+            Entry::File(file) => file.name(),
+            Entry::Dir(dir) => dir.name(),
+        }
+    }
+
+    fn metadata(&self) -> &Metadata {
+        match self {
+            Entry::File(file) => file.metadata(),
+            Entry::Dir(dir) => dir.metadata(),
+        }
+    }
+
+    fn as_file(&self) -> Option<&File<'a, D>> {
+        match self {
+            Entry::File(file) => Some(file),
+            Entry::Dir(_) => None,
+        }
+    }
+
+    fn as_dir(&self) -> Option<&Dir<'a, D>> {
+        match self {
+            Entry::File(_) => None,
+            Entry::Dir(dir) => Some(dir),
+        }
+    }
+
+    fn into_file(self) -> Option<File<'a, D>> {
+        match self {
+            Entry::File(file) => Some(file),
+            Entry::Dir(_) => None,
+        }
+    }
+
+    fn into_dir(self) -> Option<Dir<'a, D>> {
+        match self {
+            Entry::File(_) => None,
+            Entry::Dir(dir) => Some(dir),
+        }
+    }
+}
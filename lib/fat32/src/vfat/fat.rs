@@ -0,0 +1,90 @@
+use core::fmt;
+
+/// The bits of a FAT entry that are used; the top four are reserved.
+const ENTRY_MASK: u32 = 0x0FFF_FFFF;
+
+/// The first entry value marking the end of a chain.
+const EOC_MIN: u32 = 0x0FFF_FFF8;
+
+/// The first of the entry values reserved for purposes other than naming a
+/// cluster.
+const RESERVED_MIN: u32 = 0x0FFF_FFF0;
+
+/// The entry value marking a bad cluster.
+const BAD: u32 = 0x0FFF_FFF7;
+
+/// The entry value of a free cluster.
+pub const FREE: u32 = 0;
+
+/// The entry value written to end a chain.
+pub const EOC: u32 = 0x0FFF_FFFF;
+
+/// The first cluster number that refers to the data region.
+pub const FIRST_DATA_CLUSTER: u32 = 2;
+
+/// A cluster number.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cluster(u32);
+
+impl Cluster {
+    /// Returns the cluster number.
+    pub fn number(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the index of the cluster in the data region.
+    pub fn index(self) -> u32 {
+        self.0 - FIRST_DATA_CLUSTER
+    }
+}
+
+impl From<u32> for Cluster {
+    fn from(number: u32) -> Cluster {
+        Cluster(number & ENTRY_MASK)
+    }
+}
+
+impl fmt::Debug for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cluster({:#x})", self.0)
+    }
+}
+
+/// What a FAT entry says about its cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The cluster is unused.
+    Free,
+    /// The cluster is reserved and must not be used.
+    Reserved,
+    /// The cluster is in use and the chain continues at the given cluster.
+    Data(Cluster),
+    /// The cluster is bad.
+    Bad,
+    /// The cluster is the last in its chain. The value is the full entry,
+    /// anything from 0x0FFFFFF8 to 0x0FFFFFFF.
+    Eoc(u32),
+}
+
+/// An entry in the file allocation table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FatEntry(pub u32);
+
+impl FatEntry {
+    /// Returns the status of the cluster this entry describes.
+    pub fn status(&self) -> Status {
+        match self.0 & ENTRY_MASK {
+            0 => Status::Free,
+            1 => Status::Reserved,
+            BAD => Status::Bad,
+            value if value >= EOC_MIN => Status::Eoc(value),
+            value if value >= RESERVED_MIN => Status::Reserved,
+            value => Status::Data(Cluster(value)),
+        }
+    }
+
+    /// Returns this entry set to `value`, keeping its reserved top bits.
+    pub fn with_value(self, value: u32) -> FatEntry {
+        FatEntry(self.0 & !ENTRY_MASK | value & ENTRY_MASK)
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::string::String;
+use alloc::vec;
+use core::fmt;
+
+use shim::io::{self, SeekFrom};
+
+use crate::traits::{self, BlockDevice};
+use crate::vfat::dir::{self, Location};
+use crate::vfat::{fat, Attributes, Cluster, Metadata, VFat};
+
+/// The largest size a file can have.
+const MAX_SIZE: u64 = 0xFFFF_FFFF;
+
+/// A regular file.
+pub struct File<'a, D: BlockDevice> {
+    fs: &'a VFat<D>,
+    name: String,
+    metadata: Metadata,
+    /// The first cluster, or `None` for an empty file.
+    start: Option<Cluster>,
+    size: u32,
+    /// Where the file's entry is, so it can be updated when the file grows
+    /// or shrinks.
+    location: Location,
+    /// The offset the next read or write starts at.
+    position: u64,
+    /// The index in the chain of the cluster last used, and that cluster,
+    /// so sequential reads and writes don't walk the chain from the start.
+    cursor: Option<(u64, Cluster)>,
+}
+
+impl<'a, D: BlockDevice> File<'a, D> {
+    pub(crate) fn new(
+        fs: &'a VFat<D>,
+        name: String,
+        metadata: Metadata,
+        start: Option<Cluster>,
+        size: u32,
+        location: Location,
+    ) -> Self {
+        File { fs, name, metadata, start, size, location, position: 0, cursor: None }
+    }
+
+    /// Returns the file's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file's metadata.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the file's first cluster, or `None` if the file is empty.
+    pub fn cluster(&self) -> Option<Cluster> {
+        self.start
+    }
+
+    /// Returns where the file's entry is.
+    pub(crate) fn location(&self) -> Location {
+        self.location
+    }
+
+    /// Returns the cluster at `index` in the file's chain.
+    fn cluster_at(&mut self, index: u64) -> io::Result<Cluster> {
+        let too_short = || io::Error::new(io::ErrorKind::InvalidData, "file is larger than its cluster chain");
+
+        let (mut i, mut cluster) = match self.cursor {
+            Some((i, cluster)) if i <= index => (i, cluster),
+            _ => (0, self.start.ok_or_else(too_short)?),
+        };
+        while i < index {
+            cluster = self.fs.next_cluster(cluster)?.ok_or_else(too_short)?;
+            i += 1;
+        }
+
+        self.cursor = Some((index, cluster));
+        Ok(cluster)
+    }
+
+    /// Returns the number of clusters a file of `size` bytes needs.
+    fn clusters_for(&self, size: u64) -> u64 {
+        let cluster_size = self.fs.cluster_size();
+        (size + cluster_size - 1) / cluster_size
+    }
+
+    /// Returns an error of kind `PermissionDenied` if the file is read-only.
+    fn check_writable(&self) -> io::Result<()> {
+        if self.metadata.attributes.read_only() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "file is read-only"));
+        }
+        Ok(())
+    }
+
+    /// Grows the chain so it can hold `len` bytes. New clusters are zeroed.
+    /// If the volume fills up, the clusters allocated so far are freed.
+    fn reserve(&mut self, len: u64) -> io::Result<()> {
+        let have = self.clusters_for(u64::from(self.size));
+        let old_last = if have == 0 { None } else { Some(self.cluster_at(have - 1)?) };
+        let mut last = old_last;
+        for _ in have..self.clusters_for(len) {
+            match self.fs.alloc_cluster(last) {
+                Ok(cluster) => last = Some(cluster),
+                Err(e) => {
+                    self.release(old_last)?;
+                    return Err(e);
+                }
+            }
+            if self.start.is_none() {
+                self.start = last;
+            }
+        }
+        Ok(())
+    }
+
+    /// Frees the clusters after `last` in the chain, or the whole chain if
+    /// `last` is `None`.
+    fn release(&mut self, last: Option<Cluster>) -> io::Result<()> {
+        self.cursor = None;
+        match last {
+            None => match self.start.take() {
+                Some(start) => self.fs.free_chain(start),
+                None => Ok(()),
+            },
+            Some(last) => match self.fs.next_cluster(last)? {
+                Some(next) => {
+                    self.fs.set_fat_entry(last, fat::EOC)?;
+                    self.fs.free_chain(next)
+                }
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Records the file's size and first cluster in its directory entry,
+    /// and marks it as changed since the last backup.
+    fn update_entry(&mut self) -> io::Result<()> {
+        self.metadata.attributes = Attributes(self.metadata.attributes.0 | Attributes::ARCHIVE);
+
+        let mut raw = self.fs.read_entry(self.location.dir, self.location.index)?;
+        dir::set_cluster(&mut raw, self.start.map_or(0, |cluster| cluster.number()));
+        dir::set_size(&mut raw, self.size);
+        dir::set_attributes(&mut raw, self.metadata.attributes);
+        self.fs.write_entry(self.location.dir, self.location.index, &raw)
+    }
+}
+
+impl<'a, D: BlockDevice> fmt::Debug for File<'a, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("File")
+            .field("name", &self.name)
+            .field("metadata", &self.metadata)
+            .field("start", &self.start)
+            .field("size", &self.size)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl<'a, D: BlockDevice> io::Read for File<'a, D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let cluster_size = self.fs.cluster_size();
+        let remaining = u64::from(self.size).saturating_sub(self.position);
+        let len = remaining.min(buf.len() as u64) as usize;
+
+        let mut read = 0;
+        while read < len {
+            let cluster = self.cluster_at(self.position / cluster_size)?;
+            let n = self.fs.read_cluster(cluster, self.position % cluster_size, &mut buf[read..len])?;
+            read += n;
+            self.position += n as u64;
+        }
+        Ok(read)
+    }
+}
+
+impl<'a, D: BlockDevice> io::Write for File<'a, D> {
+    /// Writes `buf` at the current position, growing the file if needed. If
+    /// the position is past the end of the file, the gap is filled with
+    /// zeroes.
+    ///
+    /// Returns an error of kind `PermissionDenied` if the file is read-only,
+    /// or of kind `Other` if the volume is full.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_writable()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.position >= MAX_SIZE {
+            return Err(io::Error::new(io::ErrorKind::Other, "file is at its maximum size"));
+        }
+
+        if self.position > u64::from(self.size) {
+            traits::File::set_len(self, self.position)?;
+        }
+        let len = (MAX_SIZE - self.position).min(buf.len() as u64) as usize;
+        let end = self.position + len as u64;
+        self.reserve(end)?;
+
+        let cluster_size = self.fs.cluster_size();
+        let mut written = 0;
+        while written < len {
+            let cluster = self.cluster_at(self.position / cluster_size)?;
+            let n = self.fs.write_cluster(cluster, self.position % cluster_size, &buf[written..len])?;
+            written += n;
+            self.position += n as u64;
+        }
+
+        self.size = self.size.max(end as u32);
+        self.update_entry()?;
+        Ok(written)
+    }
+
+    /// Writes every change made to the volume since the last flush, not
+    /// just this file's, to the device.
+    fn flush(&mut self) -> io::Result<()> {
+        self.fs.flush()
+    }
+}
+
+impl<'a, D: BlockDevice> io::Seek for File<'a, D> {
+    /// Moves the position of the next read or write. Seeking past the end
+    /// of the file is allowed; reads there return no data, and writes fill
+    /// the gap with zeroes.
+    ///
+    /// Returns an error of kind `InvalidInput` if the new position would be
+    /// negative.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => (position, 0),
+            SeekFrom::End(offset) => (u64::from(self.size), offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+
+        let position =
+            if offset >= 0 { base.checked_add(offset as u64) } else { base.checked_sub(offset.wrapping_neg() as u64) };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))
+            }
+        }
+    }
+}
+
+impl<'a, D: BlockDevice> traits::File for File<'a, D> {
+    fn size(&self) -> u64 {
+        u64::from(self.size)
+    }
+
+    /// Truncates or extends the file to `len` bytes, freeing or allocating
+    /// clusters as needed.
+    ///
+    /// Returns an error of kind `PermissionDenied` if the file is read-only,
+    /// or of kind `InvalidInput` if `len` is larger than a file can be.
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        self.check_writable()?;
+        if len > MAX_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "file size is too large"));
+        }
+
+        let size = u64::from(self.size);
+        let keep = self.clusters_for(len);
+        if len < size {
+            let last = if keep == 0 { None } else { Some(self.cluster_at(keep - 1)?) };
+            self.release(last)?;
+        } else if len > size {
+            // Bytes past the end of the last cluster may be left over from
+            // an earlier, longer file; clear them before they become part
+            // of this one.
+            let cluster_size = self.fs.cluster_size();
+            let tail = size % cluster_size;
+            if tail != 0 {
+                let cluster = self.cluster_at(size / cluster_size)?;
+                let zeroes = vec![0; (cluster_size - tail).min(len - size) as usize];
+                self.fs.write_cluster(cluster, tail, &zeroes)?;
+            }
+            self.reserve(len)?;
+        }
+
+        self.size = len as u32;
+        self.update_entry()
+    }
+}
@@ -0,0 +1,53 @@
+use crate::le32;
+
+/// The signatures at the start, in the middle and at the end of the sector.
+const LEAD_SIGNATURE: u32 = 0x4161_5252;
+const STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+
+/// The offsets of the signatures and fields in the sector.
+const LEAD_OFFSET: usize = 0;
+const STRUCT_OFFSET: usize = 484;
+const FREE_COUNT_OFFSET: usize = 488;
+const NEXT_FREE_OFFSET: usize = 492;
+const TRAIL_OFFSET: usize = 508;
+
+/// The size of the fields this module reads and writes.
+const FSINFO_SIZE: usize = 512;
+
+/// The FSInfo sector: hints that save scanning the FAT for the number of
+/// free clusters and for a free cluster to allocate. Both are only hints;
+/// either may be `FsInfo::UNKNOWN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsInfo {
+    /// The number of free clusters.
+    pub free_count: u32,
+    /// The cluster to start searching for a free cluster at.
+    pub next_free: u32,
+}
+
+impl FsInfo {
+    /// The value of either hint when it isn't known.
+    pub const UNKNOWN: u32 = 0xFFFF_FFFF;
+
+    /// Parses the FSInfo sector `sector`. Returns `None` if its signatures
+    /// are wrong.
+    pub fn parse(sector: &[u8]) -> Option<FsInfo> {
+        if sector.len() < FSINFO_SIZE
+            || le32(sector, LEAD_OFFSET) != LEAD_SIGNATURE
+            || le32(sector, STRUCT_OFFSET) != STRUCT_SIGNATURE
+            || le32(sector, TRAIL_OFFSET) != TRAIL_SIGNATURE
+        {
+            return None;
+        }
+
+        Some(FsInfo { free_count: le32(sector, FREE_COUNT_OFFSET), next_free: le32(sector, NEXT_FREE_OFFSET) })
+    }
+
+    /// Writes the hints into `sector`, an FSInfo sector that `parse`
+    /// accepts.
+    pub fn write(&self, sector: &mut [u8]) {
+        sector[FREE_COUNT_OFFSET..FREE_COUNT_OFFSET + 4].copy_from_slice(&self.free_count.to_le_bytes());
+        sector[NEXT_FREE_OFFSET..NEXT_FREE_OFFSET + 4].copy_from_slice(&self.next_free.to_le_bytes());
+    }
+}
@@ -0,0 +1,180 @@
+use core::fmt;
+
+use crate::le16;
+use crate::traits;
+
+/// A date as stored in a directory entry: the year since 1980 in bits
+/// 15-9, the month in bits 8-5 and the day in bits 4-0.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date(pub u16);
+
+/// A time as stored in a directory entry: the hour in bits 15-11, the
+/// minute in bits 10-5 and the second divided by two in bits 4-0.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Time(pub u16);
+
+/// The attribute byte of a directory entry.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attributes(pub u8);
+
+impl Attributes {
+    pub const READ_ONLY: u8 = 0x01;
+    pub const HIDDEN: u8 = 0x02;
+    pub const SYSTEM: u8 = 0x04;
+    pub const VOLUME_ID: u8 = 0x08;
+    pub const DIRECTORY: u8 = 0x10;
+    pub const ARCHIVE: u8 = 0x20;
+
+    /// The combination of attributes that marks a long file name entry.
+    pub const LFN: u8 = Self::READ_ONLY | Self::HIDDEN | Self::SYSTEM | Self::VOLUME_ID;
+
+    fn has(self, bits: u8) -> bool {
+        self.0 & bits == bits
+    }
+
+    pub fn read_only(self) -> bool {
+        self.has(Self::READ_ONLY)
+    }
+
+    pub fn hidden(self) -> bool {
+        self.has(Self::HIDDEN)
+    }
+
+    pub fn system(self) -> bool {
+        self.has(Self::SYSTEM)
+    }
+
+    pub fn volume_id(self) -> bool {
+        self.has(Self::VOLUME_ID)
+    }
+
+    pub fn directory(self) -> bool {
+        self.has(Self::DIRECTORY)
+    }
+
+    pub fn archive(self) -> bool {
+        self.has(Self::ARCHIVE)
+    }
+
+    /// Returns `true` if these are the attributes of a long file name
+    /// entry rather than of a file.
+    pub fn is_lfn(self) -> bool {
+        self.0 & 0x3F == Self::LFN
+    }
+}
+
+impl fmt::Debug for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [
+            (self.read_only(), 'r'),
+            (self.hidden(), 'h'),
+            (self.system(), 's'),
+            (self.volume_id(), 'v'),
+            (self.directory(), 'd'),
+            (self.archive(), 'a'),
+        ];
+        for &(set, c) in flags.iter() {
+            write!(f, "{}", if set { c } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+/// A date and time as stored in a directory entry.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    pub date: Date,
+    pub time: Time,
+    /// Hundredths of a second to add to `time`, from 0 to 199. Only the
+    /// creation time records these.
+    pub centis: u8,
+}
+
+impl traits::Timestamp for Timestamp {
+    fn year(&self) -> usize {
+        1980 + usize::from(self.date.0 >> 9)
+    }
+
+    fn month(&self) -> u8 {
+        ((self.date.0 >> 5) & 0xF) as u8
+    }
+
+    fn day(&self) -> u8 {
+        (self.date.0 & 0x1F) as u8
+    }
+
+    fn hour(&self) -> u8 {
+        (self.time.0 >> 11) as u8
+    }
+
+    fn minute(&self) -> u8 {
+        ((self.time.0 >> 5) & 0x3F) as u8
+    }
+
+    fn second(&self) -> u8 {
+        (self.time.0 & 0x1F) as u8 * 2 + self.centis / 100
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crate::traits::Timestamp;
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
+}
+
+/// The metadata recorded in a directory entry.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub attributes: Attributes,
+    pub created: Timestamp,
+    /// Only the date of the last access is recorded.
+    pub accessed: Timestamp,
+    pub modified: Timestamp,
+}
+
+impl Metadata {
+    /// Reads the metadata from the 32-byte directory entry `entry`.
+    pub(crate) fn parse(entry: &[u8]) -> Metadata {
+        Metadata {
+            attributes: Attributes(entry[11]),
+            created: Timestamp { date: Date(le16(entry, 16)), time: Time(le16(entry, 14)), centis: entry[13] },
+            accessed: Timestamp { date: Date(le16(entry, 18)), ..Timestamp::default() },
+            modified: Timestamp { date: Date(le16(entry, 24)), time: Time(le16(entry, 22)), centis: 0 },
+        }
+    }
+}
+
+impl traits::Metadata for Metadata {
+    type Timestamp = Timestamp;
+
+    fn read_only(&self) -> bool {
+        self.attributes.read_only()
+    }
+
+    fn hidden(&self) -> bool {
+        self.attributes.hidden()
+    }
+
+    fn created(&self) -> Timestamp {
+        self.created
+    }
+
+    fn accessed(&self) -> Timestamp {
+        self.accessed
+    }
+
+    fn modified(&self) -> Timestamp {
+        self.modified
+    }
+}
@@ -0,0 +1,255 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::char;
+
+use shim::io;
+
+use crate::le16;
+
+/// The size of a directory entry.
+pub const ENTRY_SIZE: usize = 32;
+
+/// The first name byte of a deleted entry, and the stand-in stored for a
+/// name that really starts with that byte.
+pub const DELETED: u8 = 0xE5;
+const ESCAPED_E5: u8 = 0x05;
+
+/// Bits of an 8.3 entry's case byte asking for the base name or the
+/// extension to be shown in lower case.
+pub const LOWER_BASE: u8 = 0x08;
+pub const LOWER_EXT: u8 = 0x10;
+
+/// The flag on the sequence number of the last entry of a long name, which
+/// is stored first.
+pub const LFN_LAST: u8 = 0x40;
+
+/// The bits of an LFN entry's first byte holding its sequence number.
+pub const LFN_SEQUENCE: u8 = 0x1F;
+
+/// The most entries a long name of 255 characters needs.
+pub const LFN_MAX_ENTRIES: u8 = 20;
+
+/// The number of UCS-2 characters in an LFN entry, and their offsets.
+pub const LFN_CHARS: usize = 13;
+pub const LFN_OFFSETS: [usize; LFN_CHARS] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// The offset of the short name checksum in an LFN entry.
+pub const LFN_CHECKSUM: usize = 13;
+
+/// The attribute byte of an LFN entry.
+const LFN_ATTRIBUTES: u8 = 0x0F;
+
+/// The longest name, in UTF-16 code units.
+const MAX_NAME_UNITS: usize = 255;
+
+/// Characters a long name may not contain, besides control characters.
+const INVALID_CHARS: &str = "\"*/:<>?\\|";
+
+/// Characters an 8.3 name may contain besides letters and digits.
+const SHORT_SPECIAL: &[u8] = b"!#$%&'()-@^_`{}~";
+
+/// The longest base name and extension of an 8.3 name.
+const BASE_LEN: usize = 8;
+const EXT_LEN: usize = 3;
+
+/// How much of a long name is kept in front of the `~N` tail of a
+/// generated 8.3 name.
+const TAIL_BASE_LEN: usize = 6;
+
+/// Returns the checksum of the 11-byte 8.3 name `short` that LFN entries
+/// belonging to it record.
+pub fn checksum(short: &[u8]) -> u8 {
+    short.iter().fold(0u8, |sum, &byte| ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(byte))
+}
+
+/// Returns `bytes` without trailing spaces.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+    &bytes[..len]
+}
+
+/// Appends the name part `bytes` to `name`, in lower case if `lower`.
+fn push_part(name: &mut String, bytes: &[u8], lower: bool) {
+    for &byte in bytes {
+        // Bytes above 0x7F are in an OEM code page we don't know; Latin-1
+        // is as good a guess as any.
+        let c = char::from(byte);
+        name.push(if lower { c.to_ascii_lowercase() } else { c });
+    }
+}
+
+/// Returns the name of the 8.3 entry `raw` as `BASE.EXT`, or `BASE` if the
+/// extension is empty.
+pub fn short_name(raw: &[u8]) -> String {
+    let mut base = [0; BASE_LEN];
+    base.copy_from_slice(&raw[..BASE_LEN]);
+    if base[0] == ESCAPED_E5 {
+        base[0] = DELETED;
+    }
+
+    let mut name = String::new();
+    push_part(&mut name, trim(&base), raw[12] & LOWER_BASE != 0);
+    let ext = trim(&raw[BASE_LEN..BASE_LEN + EXT_LEN]);
+    if !ext.is_empty() {
+        name.push('.');
+        push_part(&mut name, ext, raw[12] & LOWER_EXT != 0);
+    }
+    name
+}
+
+/// Returns the long name stored in the UCS-2 code units `units`, which end
+/// at the first NUL if they don't fill their last entry.
+pub fn long_name(units: &[u16]) -> String {
+    let len = units.iter().position(|&unit| unit == 0).unwrap_or(units.len());
+    char::decode_utf16(units[..len].iter().cloned()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+/// Reads the code units of the LFN entry `raw` into `units`.
+pub fn read_lfn_units(raw: &[u8], units: &mut [u16]) {
+    for (unit, &offset) in units.iter_mut().zip(LFN_OFFSETS.iter()) {
+        *unit = le16(raw, offset);
+    }
+}
+
+/// Returns an error of kind `InvalidInput` unless `name` can name a file.
+pub fn validate(name: &str) -> io::Result<()> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.ends_with(' ')
+        || name.ends_with('.')
+        || name.encode_utf16().count() > MAX_NAME_UNITS
+        || name.chars().any(|c| c < ' ' || INVALID_CHARS.contains(c));
+
+    if invalid {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"));
+    }
+    Ok(())
+}
+
+/// Returns `true` if `byte` may appear in an 8.3 name.
+fn is_short_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || SHORT_SPECIAL.contains(&byte)
+}
+
+/// Copies the name part `part` into `dst` in upper case. Returns the case
+/// flag `lower` if `part` is all lower case, 0 if it is all upper case, and
+/// `None` if it mixes cases or has characters an 8.3 name can't.
+fn exact_part(part: &str, dst: &mut [u8], lower: u8) -> Option<u8> {
+    let bytes = part.as_bytes();
+    if bytes.len() > dst.len() || !bytes.iter().all(|&b| is_short_char(b)) {
+        return None;
+    }
+
+    let has_lower = bytes.iter().any(|b| b.is_ascii_lowercase());
+    let has_upper = bytes.iter().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return None;
+    }
+
+    for (d, b) in dst.iter_mut().zip(bytes) {
+        *d = b.to_ascii_uppercase();
+    }
+    Some(if has_lower { lower } else { 0 })
+}
+
+/// Returns the 8.3 name and case flags that spell `name` exactly, if there
+/// are any. Such names need no long name entries.
+pub fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.find('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => (name, ""),
+    };
+    if base.is_empty() || ext.contains('.') || (name.contains('.') && ext.is_empty()) {
+        return None;
+    }
+
+    let mut short = [b' '; 11];
+    let base_case = exact_part(base, &mut short[..BASE_LEN], LOWER_BASE)?;
+    let ext_case = exact_part(ext, &mut short[BASE_LEN..], LOWER_EXT)?;
+    Some((short, base_case | ext_case))
+}
+
+/// Returns the characters of `part` an 8.3 name can hold, in upper case,
+/// with spaces and dots dropped and anything else replaced by `_`.
+fn short_chars(part: &str) -> Vec<u8> {
+    part.chars()
+        .filter(|&c| c != ' ' && c != '.')
+        .map(|c| if c.is_ascii() && is_short_char(c as u8) { c.to_ascii_uppercase() as u8 } else { b'_' })
+        .collect()
+}
+
+/// Returns an 8.3 name for the long name `name` made of a prefix of it and
+/// a `~N` tail, choosing the smallest `N` that makes it differ from every
+/// name in `taken`.
+pub fn generate_short_name(name: &str, taken: &[[u8; 11]]) -> io::Result<[u8; 11]> {
+    let name = name.trim_start_matches('.');
+    let (base, ext) = match name.rfind('.') {
+        Some(dot) => (short_chars(&name[..dot]), short_chars(&name[dot + 1..])),
+        None => (short_chars(name), Vec::new()),
+    };
+    let base = if base.is_empty() { vec![b'_'] } else { base };
+
+    let mut short = [b' '; 11];
+    for (d, &b) in short[BASE_LEN..].iter_mut().zip(ext.iter().take(EXT_LEN)) {
+        *d = b;
+    }
+
+    let mut tail = [0u8; BASE_LEN];
+    for n in 1u32..10_000_000 {
+        // Write `~N` right-aligned into `tail`.
+        let mut len = 0;
+        let mut rest = n;
+        while rest > 0 {
+            len += 1;
+            tail[BASE_LEN - len] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+        }
+        len += 1;
+        tail[BASE_LEN - len] = b'~';
+
+        let keep = base.len().min(TAIL_BASE_LEN).min(BASE_LEN - len);
+        for d in short[..BASE_LEN].iter_mut() {
+            *d = b' ';
+        }
+        short[..keep].copy_from_slice(&base[..keep]);
+        short[keep..keep + len].copy_from_slice(&tail[BASE_LEN - len..]);
+
+        if !taken.contains(&short) {
+            return Ok(short);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::Other, "no unused short name"))
+}
+
+/// Returns the LFN entries storing `name` for the 8.3 name `short`, in the
+/// order they are stored: last part first.
+pub fn lfn_entries(name: &str, short: &[u8; 11]) -> Vec<[u8; ENTRY_SIZE]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    // A name that doesn't fill its last entry ends with a NUL followed by
+    // 0xFFFF padding.
+    let partial = units.len() % LFN_CHARS;
+    if partial != 0 {
+        units.push(0);
+        units.resize(units.len() + LFN_CHARS - partial - 1, 0xFFFF);
+    }
+
+    let count = units.len() / LFN_CHARS;
+    let checksum = checksum(short);
+    (1..=count)
+        .rev()
+        .map(|sequence| {
+            let mut raw = [0; ENTRY_SIZE];
+            raw[0] = sequence as u8 | if sequence == count { LFN_LAST } else { 0 };
+            raw[11] = LFN_ATTRIBUTES;
+            raw[LFN_CHECKSUM] = checksum;
+
+            let chars = &units[(sequence - 1) * LFN_CHARS..sequence * LFN_CHARS];
+            for (&unit, &offset) in chars.iter().zip(LFN_OFFSETS.iter()) {
+                raw[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            raw
+        })
+        .collect()
+}
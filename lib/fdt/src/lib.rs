@@ -0,0 +1,232 @@
+#![cfg_attr(not(test), no_std)]
+
+//! A reader for flattened device trees (FDT), the blob newer firmware and
+//! QEMU pass the kernel in `x0` to describe the board.
+//!
+//! A blob is a header, a "structure block" and a "strings block". The
+//! structure block is a flat stream of big-endian tokens that spells out the
+//! tree depth-first: each node is a `BEGIN_NODE` token with the node's name,
+//! its properties as `PROP` tokens, its children, and an `END_NODE` token.
+//! Property names are offsets into the strings block (ref: Devicetree
+//! Specification v0.3, chapter 5).
+//!
+//! The reader never allocates and checks every offset and length against
+//! the blob, so a corrupt blob yields errors or missing nodes rather than
+//! reads outside of it.
+
+#[cfg(test)]
+mod tests;
+
+mod node;
+
+pub use self::node::{Node, Nodes, Properties, Property, Reg};
+
+use core::{fmt, slice, str};
+
+/// The magic number every blob starts with.
+const MAGIC: u32 = 0xd00d_feed;
+
+/// The oldest blob version whose layout this reader understands.
+const MIN_VERSION: u32 = 16;
+
+/// The size of the header fields this reader uses.
+const HEADER_SIZE: usize = 40;
+
+/// The most levels of nesting `Fdt::peripheral_base` follows.
+const MAX_DEPTH: usize = 16;
+
+/// An error reading a blob's header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The blob doesn't start with the FDT magic number.
+    BadMagic,
+    /// The blob's version is older than this reader supports.
+    UnsupportedVersion(u32),
+    /// A block or the blob itself extends past the data given.
+    Truncated,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BadMagic => f.write_str("not a device tree blob"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported device tree version {}", version),
+            Error::Truncated => f.write_str("device tree blob is truncated"),
+        }
+    }
+}
+
+/// Reads the big-endian `u32` at `offset` in `data`, if it is in bounds.
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a number made of `cells` big-endian 32-bit cells from the front of
+/// `data`, returning it and the rest of `data`. Numbers wider than 64 bits
+/// are truncated to their low 64 bits.
+fn read_cells(data: &[u8], cells: u32) -> Option<(u64, &[u8])> {
+    let len = (cells as usize).checked_mul(4)?;
+    if data.len() < len {
+        return None;
+    }
+
+    let value = (0..cells as usize).fold(0u64, |value, i| (value << 32) | u64::from(be32(data, i * 4).unwrap_or(0)));
+    Some((value, &data[len..]))
+}
+
+/// Returns the NUL-terminated UTF-8 string at the start of `data`.
+fn c_str(data: &[u8]) -> Option<&str> {
+    let len = data.iter().position(|&b| b == 0)?;
+    str::from_utf8(&data[..len]).ok()
+}
+
+/// A flattened device tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Fdt<'a> {
+    size: usize,
+    structs: &'a [u8],
+    strings: &'a [u8],
+}
+
+impl<'a> Fdt<'a> {
+    /// Reads the blob at the start of `data`.
+    pub fn new(data: &'a [u8]) -> Result<Fdt<'a>, Error> {
+        let field = |i: usize| be32(data, i * 4).ok_or(Error::Truncated);
+
+        if field(0)? != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let version = field(5)?;
+        if version < MIN_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let total = field(1)? as usize;
+        let data = data.get(..total).ok_or(Error::Truncated)?;
+        let block = |offset: u32, size: u32| {
+            let (start, size) = (offset as usize, size as usize);
+            data.get(start..start.saturating_add(size)).ok_or(Error::Truncated)
+        };
+
+        Ok(Fdt {
+            size: total,
+            structs: block(field(2)?, field(9)?)?,
+            strings: block(field(3)?, field(8)?)?,
+        })
+    }
+
+    /// Reads the blob at `addr`.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must be readable for the size of an FDT header and, if it
+    /// holds one, for the total size it gives.
+    pub unsafe fn from_addr(addr: usize) -> Result<Fdt<'static>, Error> {
+        let header = slice::from_raw_parts(addr as *const u8, HEADER_SIZE);
+        if be32(header, 0) != Some(MAGIC) {
+            return Err(Error::BadMagic);
+        }
+
+        let total = be32(header, 4).ok_or(Error::Truncated)? as usize;
+        Fdt::new(slice::from_raw_parts(addr as *const u8, total.max(HEADER_SIZE)))
+    }
+
+    /// Returns the size of the blob in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the root node.
+    pub fn root(&self) -> Option<Node<'a>> {
+        self.nodes().next()
+    }
+
+    /// Returns an iterator over every node, depth-first.
+    pub fn nodes(&self) -> Nodes<'a> {
+        Nodes::new(*self)
+    }
+
+    /// Returns the node at `path`, like `/chosen` or `/soc/gpio@7e200000`.
+    /// A path component without a unit address, like `memory`, also matches
+    /// a node with one, like `memory@0`.
+    pub fn find_node(&self, path: &str) -> Option<Node<'a>> {
+        let mut components = path.split('/').filter(|c| !c.is_empty());
+        let mut want = match components.next() {
+            Some(component) => component,
+            None => return self.root(),
+        };
+        // The depth of the deepest node matched so far; the root is 0.
+        let mut matched = 0;
+
+        for node in self.nodes().skip(1) {
+            if node.depth() <= matched {
+                // Left the subtree of the last match without finding the
+                // next component.
+                return None;
+            }
+            if node.depth() != matched + 1 || !node.matches(want) {
+                continue;
+            }
+
+            matched += 1;
+            match components.next() {
+                Some(component) => want = component,
+                None => return Some(node),
+            }
+        }
+        None
+    }
+
+    /// Returns the first node whose `compatible` property lists
+    /// `compatible`.
+    pub fn find_compatible(&self, compatible: &str) -> Option<Node<'a>> {
+        self.nodes().find(|node| node.is_compatible(compatible))
+    }
+
+    /// Returns the (base address, size) of the first region of RAM in the
+    /// `/memory` node.
+    pub fn memory(&self) -> Option<(u64, u64)> {
+        let root = self.root()?;
+        self.find_node("/memory")?.reg(root.address_cells(), root.size_cells()).next()
+    }
+
+    /// Returns the kernel command line, `/chosen/bootargs`.
+    pub fn bootargs(&self) -> Option<&'a str> {
+        self.find_node("/chosen")?.property("bootargs")?.as_str()
+    }
+
+    /// Returns the physical base address of the first device compatible with
+    /// `compatible`: the first address in its `reg` property, translated
+    /// through the `ranges` of every bus above it into the CPU's address
+    /// space.
+    pub fn peripheral_base(&self, compatible: &str) -> Option<u64> {
+        // The ancestors of the node being visited, root first.
+        let mut path: [Option<Node<'a>>; MAX_DEPTH] = [None; MAX_DEPTH];
+
+        for node in self.nodes() {
+            let depth = node.depth();
+            if depth >= MAX_DEPTH {
+                continue;
+            }
+            path[depth] = Some(node);
+            if depth == 0 || !node.is_compatible(compatible) {
+                continue;
+            }
+
+            let parent = path[depth - 1]?;
+            let (mut addr, _) = node.reg(parent.address_cells(), parent.size_cells()).next()?;
+            for level in (1..depth).rev() {
+                let (bus, above) = (path[level]?, path[level - 1]?);
+                addr = bus.translate(addr, above.address_cells())?;
+            }
+            return Some(addr);
+        }
+        None
+    }
+
+    /// Returns the name in the strings block at `offset`.
+    fn string(&self, offset: u32) -> Option<&'a str> {
+        c_str(self.strings.get(offset as usize..)?)
+    }
+}
@@ -0,0 +1,266 @@
+use core::str;
+
+use crate::{be32, c_str, read_cells, Fdt};
+
+/// Structure block tokens.
+const BEGIN_NODE: u32 = 1;
+const END_NODE: u32 = 2;
+const PROP: u32 = 3;
+const NOP: u32 = 4;
+
+/// The `#address-cells` and `#size-cells` a node's children use if it
+/// doesn't say.
+const DEFAULT_ADDRESS_CELLS: u32 = 2;
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// A node in the tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Node<'a> {
+    fdt: Fdt<'a>,
+    name: &'a str,
+    depth: usize,
+    /// The offset of the node's first property in the structure block.
+    props: usize,
+}
+
+impl<'a> Node<'a> {
+    /// Returns the node's name, including any unit address, like
+    /// `gpio@7e200000`. The root's name is empty.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns how deeply the node is nested; the root is at depth 0.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns an iterator over the node's properties.
+    pub fn properties(&self) -> Properties<'a> {
+        Properties { fdt: self.fdt, offset: self.props }
+    }
+
+    /// Returns the property named `name`.
+    pub fn property(&self, name: &str) -> Option<Property<'a>> {
+        self.properties().find(|prop| prop.name == name)
+    }
+
+    /// Returns `true` if `component` names this node: either its full name
+    /// or, if `component` has no unit address, its name without one.
+    pub fn matches(&self, component: &str) -> bool {
+        self.name == component || (!component.contains('@') && self.name.split('@').next() == Some(component))
+    }
+
+    /// Returns `true` if the node's `compatible` property lists
+    /// `compatible`.
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        match self.property("compatible") {
+            Some(prop) => prop.strings().any(|s| s == compatible),
+            None => false,
+        }
+    }
+
+    /// Returns the number of cells in the addresses of this node's children.
+    pub fn address_cells(&self) -> u32 {
+        self.property("#address-cells").and_then(|p| p.as_u32()).unwrap_or(DEFAULT_ADDRESS_CELLS)
+    }
+
+    /// Returns the number of cells in the sizes of this node's children.
+    pub fn size_cells(&self) -> u32 {
+        self.property("#size-cells").and_then(|p| p.as_u32()).unwrap_or(DEFAULT_SIZE_CELLS)
+    }
+
+    /// Returns an iterator over the (address, size) pairs of the node's
+    /// `reg` property, given its parent's `#address-cells` and
+    /// `#size-cells`.
+    pub fn reg(&self, address_cells: u32, size_cells: u32) -> Reg<'a> {
+        let data = self.property("reg").map_or(&[][..], |prop| prop.value);
+        Reg { data, address_cells, size_cells }
+    }
+
+    /// Translates `addr`, an address on the bus this node is, to its
+    /// parent's address space through this node's `ranges` property, given
+    /// the parent's `#address-cells`. Returns `None` if the node has no
+    /// `ranges` or none of them contains `addr`.
+    pub fn translate(&self, addr: u64, parent_address_cells: u32) -> Option<u64> {
+        let mut ranges = self.property("ranges")?.value;
+        if ranges.is_empty() {
+            // An empty `ranges` means the bus maps addresses one to one.
+            return Some(addr);
+        }
+
+        let (child_cells, size_cells) = (self.address_cells(), self.size_cells());
+        if child_cells == 0 && parent_address_cells == 0 && size_cells == 0 {
+            return None;
+        }
+
+        while !ranges.is_empty() {
+            let (child, rest) = read_cells(ranges, child_cells)?;
+            let (parent, rest) = read_cells(rest, parent_address_cells)?;
+            let (size, rest) = read_cells(rest, size_cells)?;
+            ranges = rest;
+
+            if addr >= child && addr - child < size {
+                return Some(parent + (addr - child));
+            }
+        }
+        None
+    }
+}
+
+/// A property of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Property<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+}
+
+impl<'a> Property<'a> {
+    /// Returns the value as a NUL-terminated string.
+    pub fn as_str(&self) -> Option<&'a str> {
+        c_str(self.value)
+    }
+
+    /// Returns the value as a single big-endian cell.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.value.len() {
+            4 => be32(self.value, 0),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the value as a list of NUL-terminated
+    /// strings, like `compatible`. Strings that aren't UTF-8 are skipped.
+    pub fn strings(&self) -> impl Iterator<Item = &'a str> {
+        let value = match self.value.split_last() {
+            Some((0, init)) => init,
+            _ => self.value,
+        };
+        value.split(|&b| b == 0).filter_map(|s| str::from_utf8(s).ok())
+    }
+}
+
+/// An iterator over every node of a tree, depth-first.
+#[derive(Debug, Clone)]
+pub struct Nodes<'a> {
+    fdt: Fdt<'a>,
+    offset: usize,
+    depth: usize,
+}
+
+impl<'a> Nodes<'a> {
+    pub(crate) fn new(fdt: Fdt<'a>) -> Nodes<'a> {
+        Nodes { fdt, offset: 0, depth: 0 }
+    }
+
+    /// Ends iteration, on the end of the tree or on a malformed token.
+    fn finish(&mut self) -> Option<Node<'a>> {
+        self.offset = self.fdt.structs.len();
+        None
+    }
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        let structs = self.fdt.structs;
+        loop {
+            let token = match be32(structs, self.offset) {
+                Some(token) => token,
+                None => return self.finish(),
+            };
+
+            match token {
+                BEGIN_NODE => {
+                    let name = match structs.get(self.offset + 4..).and_then(c_str) {
+                        Some(name) => name,
+                        None => return self.finish(),
+                    };
+
+                    let node = Node {
+                        fdt: self.fdt,
+                        name,
+                        depth: self.depth,
+                        props: align4(self.offset + 4 + name.len() + 1),
+                    };
+                    self.offset = node.props;
+                    self.depth += 1;
+                    return Some(node);
+                }
+                END_NODE => {
+                    self.depth = match self.depth.checked_sub(1) {
+                        Some(depth) => depth,
+                        None => return self.finish(),
+                    };
+                    self.offset += 4;
+                }
+                PROP => match be32(structs, self.offset + 4) {
+                    Some(len) => self.offset = align4(self.offset + 12 + len as usize),
+                    None => return self.finish(),
+                },
+                NOP => self.offset += 4,
+                // `END`, or garbage.
+                _ => return self.finish(),
+            }
+        }
+    }
+}
+
+/// An iterator over the properties of a node.
+#[derive(Debug, Clone)]
+pub struct Properties<'a> {
+    fdt: Fdt<'a>,
+    offset: usize,
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = Property<'a>;
+
+    fn next(&mut self) -> Option<Property<'a>> {
+        let structs = self.fdt.structs;
+        loop {
+            match be32(structs, self.offset)? {
+                PROP => {
+                    let len = be32(structs, self.offset + 4)? as usize;
+                    let name = self.fdt.string(be32(structs, self.offset + 8)?)?;
+                    let start = self.offset + 12;
+                    let value = structs.get(start..start.checked_add(len)?)?;
+
+                    self.offset = align4(start + len);
+                    return Some(Property { name, value });
+                }
+                NOP => self.offset += 4,
+                // The node's children or its end.
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// An iterator over the (address, size) pairs of a `reg` property.
+#[derive(Debug, Clone)]
+pub struct Reg<'a> {
+    data: &'a [u8],
+    address_cells: u32,
+    size_cells: u32,
+}
+
+impl<'a> Iterator for Reg<'a> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        if self.address_cells == 0 && self.size_cells == 0 {
+            return None;
+        }
+
+        let (address, rest) = read_cells(self.data, self.address_cells)?;
+        let (size, rest) = read_cells(rest, self.size_cells)?;
+        self.data = rest;
+        Some((address, size))
+    }
+}
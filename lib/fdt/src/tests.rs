@@ -0,0 +1,318 @@
+use crate::{Error, Fdt};
+
+/// Builds device tree blobs for the tests.
+#[derive(Default)]
+struct Builder {
+    structs: Vec<u8>,
+    strings: Vec<u8>,
+}
+
+impl Builder {
+    fn token(&mut self, token: u32) -> &mut Builder {
+        self.structs.extend_from_slice(&token.to_be_bytes());
+        self
+    }
+
+    fn pad(&mut self) {
+        let len = (self.structs.len() + 3) / 4 * 4;
+        self.structs.resize(len, 0);
+    }
+
+    fn begin(&mut self, name: &str) -> &mut Builder {
+        self.token(1);
+        self.structs.extend_from_slice(name.as_bytes());
+        self.structs.push(0);
+        self.pad();
+        self
+    }
+
+    fn end(&mut self) -> &mut Builder {
+        self.token(2)
+    }
+
+    fn nop(&mut self) -> &mut Builder {
+        self.token(4)
+    }
+
+    fn prop(&mut self, name: &str, value: &[u8]) -> &mut Builder {
+        let offset = self.strings.len() as u32;
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+
+        self.token(3).token(value.len() as u32).token(offset);
+        self.structs.extend_from_slice(value);
+        self.pad();
+        self
+    }
+
+    fn cells(&mut self, name: &str, cells: &[u32]) -> &mut Builder {
+        let value: Vec<u8> = cells.iter().flat_map(|c| c.to_be_bytes()).collect();
+        self.prop(name, &value)
+    }
+
+    fn string(&mut self, name: &str, value: &str) -> &mut Builder {
+        self.prop(name, format!("{}\0", value).as_bytes())
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        self.token(9);
+
+        let header_len = 40 + 16;
+        let structs_off = header_len;
+        let strings_off = structs_off + self.structs.len();
+        let total = strings_off + self.strings.len();
+
+        let mut blob = Vec::new();
+        for field in &[
+            0xd00d_feed,
+            total as u32,
+            structs_off as u32,
+            strings_off as u32,
+            40,
+            17,
+            16,
+            0,
+            self.strings.len() as u32,
+            self.structs.len() as u32,
+        ] {
+            blob.extend_from_slice(&u32::to_be_bytes(*field));
+        }
+        // An empty memory reservation map.
+        blob.extend_from_slice(&[0; 16]);
+        blob.extend_from_slice(&self.structs);
+        blob.extend_from_slice(&self.strings);
+        blob
+    }
+}
+
+/// A cut-down version of the Raspberry Pi 3's tree.
+fn pi3() -> Vec<u8> {
+    Builder::default()
+        .begin("")
+        .string("compatible", "raspberrypi,3-model-b\0brcm,bcm2837")
+        .cells("#address-cells", &[1])
+        .cells("#size-cells", &[1])
+        .begin("chosen")
+        .string("bootargs", "console=ttyS0,115200 root=/dev/mmcblk0p2")
+        .end()
+        .begin("soc")
+        .string("compatible", "simple-bus")
+        .cells("#address-cells", &[1])
+        .cells("#size-cells", &[1])
+        .cells("ranges", &[0x7e00_0000, 0x3f00_0000, 0x0100_0000, 0x4000_0000, 0x4000_0000, 0x0004_0000])
+        .begin("gpio@7e200000")
+        .string("compatible", "brcm,bcm2835-gpio")
+        .cells("reg", &[0x7e20_0000, 0xb4])
+        .end()
+        .nop()
+        .begin("aux@7e215000")
+        .string("compatible", "brcm,bcm2835-aux")
+        .cells("reg", &[0x7e21_5000, 0x8])
+        .end()
+        .begin("local_intc@40000000")
+        .string("compatible", "brcm,bcm2836-l1-intc")
+        .cells("reg", &[0x4000_0000, 0x100])
+        .end()
+        .end()
+        .begin("memory@0")
+        .string("device_type", "memory")
+        .cells("reg", &[0, 0x3b40_0000])
+        .end()
+        .end()
+        .finish()
+}
+
+#[test]
+fn header_errors() {
+    let mut blob = pi3();
+    assert_eq!(Fdt::new(&blob).unwrap().size(), blob.len());
+    assert_eq!(Fdt::new(&blob[..20]).unwrap_err(), Error::Truncated);
+    assert_eq!(Fdt::new(&blob[..blob.len() - 1]).unwrap_err(), Error::Truncated);
+
+    blob[0] = 0;
+    assert_eq!(Fdt::new(&blob).unwrap_err(), Error::BadMagic);
+    assert_eq!(Fdt::new(&[]).unwrap_err(), Error::Truncated);
+
+    let mut blob = pi3();
+    blob[23] = 3;
+    assert_eq!(Fdt::new(&blob).unwrap_err(), Error::UnsupportedVersion(3));
+
+    // A block past the end of the blob.
+    let mut blob = pi3();
+    blob[8..12].copy_from_slice(&0x1000u32.to_be_bytes());
+    assert_eq!(Fdt::new(&blob).unwrap_err(), Error::Truncated);
+}
+
+#[test]
+fn from_addr() {
+    let blob = pi3();
+    let fdt = unsafe { Fdt::from_addr(blob.as_ptr() as usize) }.unwrap();
+    assert_eq!(fdt.nodes().count(), 7);
+
+    let garbage = [0u8; 64];
+    assert_eq!(unsafe { Fdt::from_addr(garbage.as_ptr() as usize) }.unwrap_err(), Error::BadMagic);
+}
+
+#[test]
+fn walks_nodes_depth_first() {
+    let blob = pi3();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    let nodes: Vec<_> = fdt.nodes().map(|n| (n.name(), n.depth())).collect();
+    assert_eq!(
+        nodes,
+        vec![
+            ("", 0),
+            ("chosen", 1),
+            ("soc", 1),
+            ("gpio@7e200000", 2),
+            ("aux@7e215000", 2),
+            ("local_intc@40000000", 2),
+            ("memory@0", 1),
+        ]
+    );
+}
+
+#[test]
+fn properties() {
+    let blob = pi3();
+    let fdt = Fdt::new(&blob).unwrap();
+    let root = fdt.root().unwrap();
+
+    let names: Vec<_> = root.properties().map(|p| p.name).collect();
+    assert_eq!(names, vec!["compatible", "#address-cells", "#size-cells"]);
+
+    let compatible: Vec<_> = root.property("compatible").unwrap().strings().collect();
+    assert_eq!(compatible, vec!["raspberrypi,3-model-b", "brcm,bcm2837"]);
+    assert!(root.is_compatible("brcm,bcm2837"));
+    assert!(!root.is_compatible("brcm"));
+
+    assert_eq!(root.address_cells(), 1);
+    assert_eq!(fdt.find_node("/chosen").unwrap().address_cells(), 2);
+    assert_eq!(root.property("#size-cells").unwrap().as_u32(), Some(1));
+    assert_eq!(root.property("compatible").unwrap().as_u32(), None);
+    assert!(root.property("missing").is_none());
+}
+
+#[test]
+fn find_node() {
+    let blob = pi3();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    assert_eq!(fdt.find_node("/").unwrap().name(), "");
+    assert_eq!(fdt.find_node("/chosen").unwrap().name(), "chosen");
+    assert_eq!(fdt.find_node("/memory").unwrap().name(), "memory@0");
+    assert_eq!(fdt.find_node("/memory@0").unwrap().name(), "memory@0");
+    assert_eq!(fdt.find_node("/soc/aux").unwrap().name(), "aux@7e215000");
+    assert_eq!(fdt.find_node("/soc/gpio@7e200000").unwrap().depth(), 2);
+
+    assert!(fdt.find_node("/memory@1").is_none());
+    assert!(fdt.find_node("/aux").is_none());
+    assert!(fdt.find_node("/chosen/aux").is_none());
+    assert!(fdt.find_node("/soc/gpio/pins").is_none());
+}
+
+#[test]
+fn memory_and_bootargs() {
+    let blob = pi3();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    assert_eq!(fdt.memory(), Some((0, 0x3b40_0000)));
+    assert_eq!(fdt.bootargs(), Some("console=ttyS0,115200 root=/dev/mmcblk0p2"));
+}
+
+#[test]
+fn memory_with_64_bit_cells() {
+    let blob = Builder::default()
+        .begin("")
+        .cells("#address-cells", &[2])
+        .cells("#size-cells", &[2])
+        .begin("memory@40000000")
+        .cells("reg", &[0, 0x4000_0000, 0x1, 0])
+        .end()
+        .end()
+        .finish();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    assert_eq!(fdt.memory(), Some((0x4000_0000, 0x1_0000_0000)));
+    assert_eq!(fdt.bootargs(), None);
+}
+
+#[test]
+fn peripheral_base_translates_ranges() {
+    let blob = pi3();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    assert_eq!(fdt.peripheral_base("brcm,bcm2835-gpio"), Some(0x3f20_0000));
+    assert_eq!(fdt.peripheral_base("brcm,bcm2835-aux"), Some(0x3f21_5000));
+    assert_eq!(fdt.peripheral_base("brcm,bcm2836-l1-intc"), Some(0x4000_0000));
+    assert_eq!(fdt.peripheral_base("brcm,bcm2835-mmc"), None);
+    assert_eq!(fdt.find_compatible("simple-bus").unwrap().name(), "soc");
+}
+
+#[test]
+fn peripheral_base_without_ranges() {
+    let blob = Builder::default()
+        .begin("")
+        .cells("#address-cells", &[1])
+        .cells("#size-cells", &[1])
+        .begin("bus")
+        .cells("#address-cells", &[1])
+        .cells("#size-cells", &[1])
+        .begin("uart@1000")
+        .string("compatible", "uart")
+        .cells("reg", &[0x1000, 0x100])
+        .end()
+        .end()
+        .begin("flat")
+        .cells("#address-cells", &[1])
+        .cells("#size-cells", &[1])
+        .prop("ranges", &[])
+        .begin("uart@2000")
+        .string("compatible", "other-uart")
+        .cells("reg", &[0x2000, 0x100])
+        .end()
+        .end()
+        .end()
+        .finish();
+    let fdt = Fdt::new(&blob).unwrap();
+
+    // A bus with no `ranges` can't be translated through; an empty one maps
+    // one to one.
+    assert_eq!(fdt.peripheral_base("uart"), None);
+    assert_eq!(fdt.peripheral_base("other-uart"), Some(0x2000));
+}
+
+#[test]
+fn malformed_structure_block() {
+    // A property whose length runs off the end of the block.
+    let mut builder = Builder::default();
+    builder.begin("").cells("#address-cells", &[1]);
+    let mut blob = builder.end().finish();
+    let len_at = 56 + 8 + 4;
+    blob[len_at..len_at + 4].copy_from_slice(&0x1000u32.to_be_bytes());
+
+    let fdt = Fdt::new(&blob).unwrap();
+    let root = fdt.root().unwrap();
+    assert_eq!(root.properties().count(), 0);
+    assert_eq!(fdt.nodes().count(), 1);
+
+    // More `END_NODE`s than nodes, and an unknown token.
+    let blob = Builder::default().begin("").end().end().begin("x").end().finish();
+    assert_eq!(Fdt::new(&blob).unwrap().nodes().count(), 1);
+    let blob = Builder::default().begin("").token(7).begin("x").end().end().finish();
+    assert_eq!(Fdt::new(&blob).unwrap().nodes().count(), 1);
+
+    // A node name that isn't terminated.
+    let mut builder = Builder::default();
+    builder.token(1);
+    builder.structs.extend_from_slice(b"abcd");
+    let mut blob = builder.finish();
+    let end = blob.len() - 4;
+    blob.truncate(end);
+    let total = blob.len() as u32;
+    blob[4..8].copy_from_slice(&total.to_be_bytes());
+    blob[12..16].copy_from_slice(&total.to_be_bytes());
+    blob[36..40].copy_from_slice(&8u32.to_be_bytes());
+    assert_eq!(Fdt::new(&blob).unwrap().nodes().count(), 0);
+}
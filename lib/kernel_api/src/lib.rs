@@ -0,0 +1,60 @@
+#![feature(asm)]
+#![no_std]
+
+//! The system call ABI shared by the kernel and user programs.
+//!
+//! A system call is made with `svc #n`, where `n` is one of the `NR_*`
+//! numbers below. Arguments are passed in `x0` through `x5`. The kernel
+//! returns the result in `x0` and an error code in `x7`: `0` on success,
+//! otherwise an `OsError`.
+
+use core::fmt;
+
+pub mod syscall;
+
+pub const NR_SLEEP: usize = 1;
+pub const NR_TIME: usize = 2;
+pub const NR_EXIT: usize = 3;
+pub const NR_WRITE: usize = 4;
+pub const NR_GETPID: usize = 5;
+
+/// An error returned by a system call.
+#[repr(u64)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsError {
+    Unknown = 1,
+    /// No system call has the requested number.
+    InvalidSyscall = 2,
+    /// An argument was out of range.
+    InvalidArgument = 3,
+    /// The system call can only be made by a process.
+    NoProcess = 4,
+}
+
+impl OsError {
+    /// Returns the `OsError` for the error code `code`, or `None` for `0`,
+    /// which signals success.
+    pub fn from_code(code: u64) -> Option<OsError> {
+        match code {
+            0 => None,
+            2 => Some(OsError::InvalidSyscall),
+            3 => Some(OsError::InvalidArgument),
+            4 => Some(OsError::NoProcess),
+            _ => Some(OsError::Unknown),
+        }
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            OsError::Unknown => "unknown error",
+            OsError::InvalidSyscall => "invalid system call",
+            OsError::InvalidArgument => "invalid argument",
+            OsError::NoProcess => "not called from a process",
+        };
+        f.write_str(msg)
+    }
+}
+
+pub type OsResult<T> = Result<T, OsError>;
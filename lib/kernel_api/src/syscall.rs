@@ -0,0 +1,88 @@
+//! Wrappers that make each system call from user space.
+
+use core::time::Duration;
+
+use crate::*;
+
+/// Converts the error code a system call left in `x7` into a result.
+fn result<T>(code: u64, value: T) -> OsResult<T> {
+    match OsError::from_code(code) {
+        None => Ok(value),
+        Some(err) => Err(err),
+    }
+}
+
+/// Sleeps for at least `span`, returning how long the process actually
+/// slept. `span` is rounded down to whole milliseconds.
+pub fn sleep(span: Duration) -> OsResult<Duration> {
+    if span.as_millis() > u64::max_value() as u128 {
+        return Err(OsError::InvalidArgument);
+    }
+
+    let ms = span.as_millis() as u64;
+    let elapsed_ms: u64;
+    let code: u64;
+    unsafe {
+        asm!("mov x0, $2
+              svc $3
+              mov $0, x0
+              mov $1, x7"
+             : "=r"(elapsed_ms), "=r"(code)
+             : "r"(ms), "i"(NR_SLEEP)
+             : "x0", "x7"
+             : "volatile");
+    }
+
+    result(code, Duration::from_millis(elapsed_ms))
+}
+
+/// Returns the time since boot.
+pub fn time() -> Duration {
+    let micros: u64;
+    unsafe {
+        asm!("svc $1
+              mov $0, x0"
+             : "=r"(micros)
+             : "i"(NR_TIME)
+             : "x0", "x7"
+             : "volatile");
+    }
+
+    Duration::from_micros(micros)
+}
+
+/// Ends the calling process.
+pub fn exit() -> ! {
+    unsafe {
+        asm!("svc $0" :: "i"(NR_EXIT) :: "volatile");
+    }
+
+    // The kernel never switches back to a process that has exited.
+    loop {}
+}
+
+/// Writes `byte` to the console.
+pub fn write(byte: u8) {
+    unsafe {
+        asm!("mov x0, $0
+              svc $1"
+             :: "r"(byte as u64), "i"(NR_WRITE)
+             : "x0", "x7"
+             : "volatile");
+    }
+}
+
+/// Returns the ID of the calling process.
+pub fn getpid() -> u64 {
+    let pid: u64;
+    unsafe {
+        asm!("svc $1
+              mov $0, x0"
+             : "=r"(pid)
+             : "i"(NR_GETPID)
+             : "x0", "x7"
+             : "volatile");
+    }
+
+    pid
+}
@@ -0,0 +1,95 @@
+//! The ATAGS boot information list.
+//!
+//! The firmware leaves a list of tagged records at `ATAG_BASE` describing
+//! the board: the RAM the ARM cores get, the kernel command line from
+//! `cmdline.txt` and so on. Each record ("ATAG") starts with a two-word
+//! header, its length in 32-bit words including the header and its tag,
+//! followed by a tag-specific body. The list ends with a `NONE` tag.
+//!
+//! Nothing about the list is trusted: every header is checked against the
+//! words remaining before it is followed, so a corrupt or missing list ends
+//! iteration rather than reading past it.
+
+mod atag;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::atag::{Atag, Core, Mem};
+
+use core::slice;
+
+/// The address the firmware writes the list to.
+const ATAG_BASE: usize = 0x100;
+
+/// The most words the list is read from: it has to fit below the kernel's
+/// boot stack, and 16KiB is far more than the firmware ever writes.
+const MAX_WORDS: usize = 16 * 1024 / 4;
+
+/// The number of words in an ATAG header.
+const HEADER_WORDS: usize = 2;
+
+mod tag {
+    pub const NONE: u32 = 0x0000_0000;
+    pub const CORE: u32 = 0x5441_0001;
+    pub const MEM: u32 = 0x5441_0002;
+    pub const CMDLINE: u32 = 0x5441_0009;
+}
+
+/// An iterator over the ATAGS in a list.
+#[derive(Debug, Clone)]
+pub struct Atags<'a> {
+    words: &'a [u32],
+}
+
+impl Atags<'static> {
+    /// Returns an iterator over the ATAGS the firmware left at `ATAG_BASE`.
+    pub fn get() -> Atags<'static> {
+        let words = unsafe { slice::from_raw_parts(ATAG_BASE as *const u32, MAX_WORDS) };
+        Atags::from_words(words)
+    }
+}
+
+impl<'a> Atags<'a> {
+    /// Returns an iterator over the ATAGS list at the start of `words`.
+    pub fn from_words(words: &'a [u32]) -> Atags<'a> {
+        Atags { words }
+    }
+
+    /// Returns the (start address, end address) of the first memory region
+    /// in the list.
+    pub fn memory_map(&self) -> Option<(usize, usize)> {
+        self.clone().find_map(|atag| match atag {
+            Atag::Mem(mem) => Some((mem.start as usize, mem.start as usize + mem.size as usize)),
+            _ => None,
+        })
+    }
+
+    /// Returns the kernel command line.
+    pub fn cmdline(&self) -> Option<&'a str> {
+        self.clone().find_map(|atag| match atag {
+            Atag::Cmd(cmd) => Some(cmd),
+            _ => None,
+        })
+    }
+}
+
+impl<'a> Iterator for Atags<'a> {
+    type Item = Atag<'a>;
+
+    fn next(&mut self) -> Option<Atag<'a>> {
+        if self.words.len() < HEADER_WORDS {
+            return None;
+        }
+
+        let (len, tag) = (self.words[0] as usize, self.words[1]);
+        if tag == tag::NONE || len < HEADER_WORDS || len > self.words.len() {
+            self.words = &[];
+            return None;
+        }
+
+        let body = &self.words[HEADER_WORDS..len];
+        self.words = &self.words[len..];
+        Some(Atag::parse(tag, body))
+    }
+}
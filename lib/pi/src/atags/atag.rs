@@ -0,0 +1,59 @@
+use core::{slice, str};
+
+use super::tag;
+
+/// The `CORE` ATAG: the first in every list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Core {
+    pub flags: u32,
+    pub page_size: u32,
+    pub root_dev: u32,
+}
+
+/// The `MEM` ATAG: one region of RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mem {
+    pub size: u32,
+    pub start: u32,
+}
+
+/// A parsed ATAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Atag<'a> {
+    Core(Core),
+    Mem(Mem),
+    /// The kernel command line.
+    Cmd(&'a str),
+    /// A tag this parser doesn't know, or a known one with a body too short
+    /// for it.
+    Unknown(u32),
+}
+
+impl<'a> Atag<'a> {
+    /// Parses the ATAG with tag `tag` and body `body`.
+    pub(super) fn parse(tag: u32, body: &'a [u32]) -> Atag<'a> {
+        match tag {
+            // A `CORE` tag may have an empty body; it has no fields then.
+            tag::CORE if body.is_empty() => Atag::Core(Core { flags: 0, page_size: 0, root_dev: 0 }),
+            tag::CORE if body.len() >= 3 => Atag::Core(Core {
+                flags: body[0],
+                page_size: body[1],
+                root_dev: body[2],
+            }),
+            tag::MEM if body.len() >= 2 => Atag::Mem(Mem { size: body[0], start: body[1] }),
+            tag::CMDLINE => match cmdline(body) {
+                Some(cmd) => Atag::Cmd(cmd),
+                None => Atag::Unknown(tag),
+            },
+            _ => Atag::Unknown(tag),
+        }
+    }
+}
+
+/// Returns the NUL-terminated string in `body`, or `None` if it isn't
+/// terminated within `body` or isn't UTF-8.
+fn cmdline(body: &[u32]) -> Option<&str> {
+    let bytes = unsafe { slice::from_raw_parts(body.as_ptr() as *const u8, body.len() * 4) };
+    let len = bytes.iter().position(|&b| b == 0)?;
+    str::from_utf8(&bytes[..len]).ok()
+}
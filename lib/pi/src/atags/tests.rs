@@ -0,0 +1,126 @@
+use super::tag::{CMDLINE, CORE, MEM, NONE};
+use super::{Atag, Atags, Core, Mem};
+
+/// Appends an ATAG with tag `tag` and body `body` to `list`.
+fn push(list: &mut Vec<u32>, tag: u32, body: &[u32]) {
+    list.push(body.len() as u32 + 2);
+    list.push(tag);
+    list.extend_from_slice(body);
+}
+
+/// Returns the words of a NUL-terminated string, zero-padded to a whole
+/// word.
+fn string(s: &str) -> Vec<u32> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize((bytes.len() + 3) / 4 * 4 + 4, 0);
+    bytes.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+}
+
+/// Returns a list like the one the firmware writes.
+fn firmware_list() -> Vec<u32> {
+    let mut list = Vec::new();
+    push(&mut list, CORE, &[0, 4096, 0]);
+    push(&mut list, MEM, &[0x3b40_0000, 0]);
+    push(&mut list, CMDLINE, &string("bcm2708_fb.fbwidth=656 console=ttyS0,115200"));
+    push(&mut list, NONE, &[]);
+    list
+}
+
+#[test]
+fn parses_firmware_list() {
+    let list = firmware_list();
+    let atags: Vec<_> = Atags::from_words(&list).collect();
+
+    assert_eq!(
+        atags,
+        vec![
+            Atag::Core(Core { flags: 0, page_size: 4096, root_dev: 0 }),
+            Atag::Mem(Mem { size: 0x3b40_0000, start: 0 }),
+            Atag::Cmd("bcm2708_fb.fbwidth=656 console=ttyS0,115200"),
+        ]
+    );
+}
+
+#[test]
+fn helpers() {
+    let list = firmware_list();
+    let atags = Atags::from_words(&list);
+
+    assert_eq!(atags.memory_map(), Some((0, 0x3b40_0000)));
+    assert_eq!(atags.cmdline(), Some("bcm2708_fb.fbwidth=656 console=ttyS0,115200"));
+}
+
+#[test]
+fn empty_core_and_unknown_tags() {
+    let mut list = Vec::new();
+    push(&mut list, CORE, &[]);
+    push(&mut list, 0x5441_0004, &[1, 2, 3]);
+    push(&mut list, MEM, &[0x1000]);
+    push(&mut list, NONE, &[]);
+
+    let atags: Vec<_> = Atags::from_words(&list).collect();
+    assert_eq!(
+        atags,
+        vec![
+            Atag::Core(Core { flags: 0, page_size: 0, root_dev: 0 }),
+            Atag::Unknown(0x5441_0004),
+            // Too short to be a memory region.
+            Atag::Unknown(MEM),
+        ]
+    );
+    assert_eq!(Atags::from_words(&list).memory_map(), None);
+    assert_eq!(Atags::from_words(&list).cmdline(), None);
+}
+
+#[test]
+fn stops_at_none() {
+    let mut list = Vec::new();
+    push(&mut list, CORE, &[]);
+    push(&mut list, NONE, &[]);
+    push(&mut list, MEM, &[0x1000, 0]);
+
+    assert_eq!(Atags::from_words(&list).count(), 1);
+}
+
+#[test]
+fn bounds_checks() {
+    // Nothing at all, or a truncated header.
+    assert_eq!(Atags::from_words(&[]).count(), 0);
+    assert_eq!(Atags::from_words(&[5]).count(), 0);
+
+    // A length running past the end of the list.
+    let mut list = Vec::new();
+    push(&mut list, CORE, &[]);
+    list.extend_from_slice(&[100, MEM, 0x1000, 0]);
+    assert_eq!(Atags::from_words(&list).collect::<Vec<_>>().len(), 1);
+
+    // Lengths shorter than a header would loop forever or underflow.
+    for &len in &[0, 1] {
+        let list = [len, MEM, 0x1000, 0];
+        assert_eq!(Atags::from_words(&list).count(), 0);
+    }
+
+    // No `NONE` at the end: iteration ends with the words.
+    let mut list = Vec::new();
+    push(&mut list, MEM, &[0x1000, 0]);
+    assert_eq!(Atags::from_words(&list).count(), 1);
+}
+
+#[test]
+fn bad_cmdlines() {
+    // Not NUL-terminated within the tag.
+    let mut list = Vec::new();
+    push(&mut list, CMDLINE, &[u32::from_le_bytes(*b"abcd")]);
+    push(&mut list, MEM, &[0, 0]);
+    assert_eq!(Atags::from_words(&list).next(), Some(Atag::Unknown(CMDLINE)));
+
+    // Not UTF-8.
+    let mut list = Vec::new();
+    push(&mut list, CMDLINE, &[u32::from_le_bytes([0xff, 0xfe, 0, 0])]);
+    assert_eq!(Atags::from_words(&list).next(), Some(Atag::Unknown(CMDLINE)));
+
+    // Empty.
+    let mut list = Vec::new();
+    push(&mut list, CMDLINE, &[0]);
+    assert_eq!(Atags::from_words(&list).cmdline(), Some(""));
+}
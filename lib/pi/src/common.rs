@@ -0,0 +1,14 @@
+/// The address where I/O peripherals are mapped to.
+pub const IO_BASE: usize = 0x3F000000;
+
+/// Generates `pub enum`s with no variants for each `ident` passed in. These
+/// are used as type-level states and can never be constructed.
+macro_rules! states {
+    ($($name:ident),*) => {
+        $(
+            /// A possible state.
+            #[doc(hidden)]
+            pub enum $name {  }
+        )*
+    };
+}
@@ -0,0 +1,190 @@
+use core::marker::PhantomData;
+
+use crate::common::IO_BASE;
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile, WriteVolatile};
+
+/// The base address of the `GPIO` registers.
+const GPIO_BASE: usize = IO_BASE + 0x200000;
+
+/// The number of GPIO pins on the BCM2837.
+pub const NUM_PINS: u8 = 54;
+
+/// An alternative GPIO function.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Input = 0b000,
+    Output = 0b001,
+    Alt0 = 0b100,
+    Alt1 = 0b101,
+    Alt2 = 0b110,
+    Alt3 = 0b111,
+    Alt4 = 0b011,
+    Alt5 = 0b010,
+}
+
+/// The internal pull-up/pull-down resistor setting of a pin.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    Off = 0b00,
+    Down = 0b01,
+    Up = 0b10,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    FSEL: [Volatile<u32>; 6],
+    __r0: Reserved<u32>,
+    SET: [WriteVolatile<u32>; 2],
+    __r1: Reserved<u32>,
+    CLR: [WriteVolatile<u32>; 2],
+    __r2: Reserved<u32>,
+    LEV: [ReadVolatile<u32>; 2],
+    __r3: Reserved<u32>,
+    EDS: [Volatile<u32>; 2],
+    __r4: Reserved<u32>,
+    REN: [Volatile<u32>; 2],
+    __r5: Reserved<u32>,
+    FEN: [Volatile<u32>; 2],
+    __r6: Reserved<u32>,
+    HEN: [Volatile<u32>; 2],
+    __r7: Reserved<u32>,
+    LEN: [Volatile<u32>; 2],
+    __r8: Reserved<u32>,
+    AREN: [Volatile<u32>; 2],
+    __r9: Reserved<u32>,
+    AFEN: [Volatile<u32>; 2],
+    __r10: Reserved<u32>,
+    PUD: Volatile<u32>,
+    PUDCLK: [Volatile<u32>; 2],
+}
+
+// Possible states for a GPIO pin.
+states! {
+    Uninitialized, Input, Output, Alt
+}
+
+/// A GPIO pin in state `State`.
+///
+/// The `State` generic always corresponds to an uninstantiatable type that is
+/// used solely to mark and track the state of a given GPIO pin. A `Gpio`
+/// structure starts in the `Uninitialized` state and must be transitioned into
+/// one of `Input`, `Output`, or `Alt` via the `into_input`, `into_output`, and
+/// `into_alt` methods before it can be used.
+pub struct Gpio<State> {
+    pin: u8,
+    registers: &'static mut Registers,
+    _state: PhantomData<State>,
+}
+
+impl<T> Gpio<T> {
+    /// Transitions `self` to state `S`, consuming `self` and returning a new
+    /// `Gpio` instance in state `S`. This method should _never_ be exposed to
+    /// the public!
+    #[inline(always)]
+    fn transition<S>(self) -> Gpio<S> {
+        Gpio {
+            pin: self.pin,
+            registers: self.registers,
+            _state: PhantomData,
+        }
+    }
+
+    /// Returns the register bank and the bit within that bank for this pin.
+    #[inline(always)]
+    fn bank_bit(&self) -> (usize, u32) {
+        ((self.pin / 32) as usize, 1 << (self.pin % 32))
+    }
+
+    /// Enables the internal pull-up or pull-down resistor of this pin, or
+    /// disables both, using the GPPUD/GPPUDCLK sequence from the datasheet.
+    pub fn set_pull(&mut self, pull: Pull) {
+        let (bank, bit) = self.bank_bit();
+        self.registers.PUD.write(pull as u32);
+        delay_cycles(150);
+        self.registers.PUDCLK[bank].write(bit);
+        delay_cycles(150);
+        self.registers.PUD.write(0);
+        self.registers.PUDCLK[bank].write(0);
+    }
+}
+
+impl Gpio<Uninitialized> {
+    /// Returns a new `GPIO` structure for pin number `pin`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` > `53`.
+    pub fn new(pin: u8) -> Gpio<Uninitialized> {
+        if pin >= NUM_PINS {
+            panic!("Gpio::new(): pin {} exceeds maximum of {}", pin, NUM_PINS - 1);
+        }
+
+        Gpio {
+            registers: unsafe { &mut *(GPIO_BASE as *mut Registers) },
+            pin,
+            _state: PhantomData,
+        }
+    }
+
+    /// Enables the alternative function `function` for `self`. Consumes self
+    /// and returns a `Gpio` structure in the `Alt` state.
+    pub fn into_alt(self, function: Function) -> Gpio<Alt> {
+        let index = (self.pin / 10) as usize;
+        let shift = (self.pin % 10) * 3;
+        let fsel = &mut self.registers.FSEL[index];
+        let value = fsel.read() & !(0b111 << shift);
+        fsel.write(value | ((function as u32) << shift));
+        self.transition()
+    }
+
+    /// Sets this pin to be an _output_ pin. Consumes self and returns a `Gpio`
+    /// structure in the `Output` state.
+    pub fn into_output(self) -> Gpio<Output> {
+        self.into_alt(Function::Output).transition()
+    }
+
+    /// Sets this pin to be an _input_ pin. Consumes self and returns a `Gpio`
+    /// structure in the `Input` state.
+    pub fn into_input(self) -> Gpio<Input> {
+        self.into_alt(Function::Input).transition()
+    }
+}
+
+impl Gpio<Output> {
+    /// Sets (turns on) the pin.
+    pub fn set(&mut self) {
+        let (bank, bit) = self.bank_bit();
+        self.registers.SET[bank].write(bit);
+    }
+
+    /// Clears (turns off) the pin.
+    pub fn clear(&mut self) {
+        let (bank, bit) = self.bank_bit();
+        self.registers.CLR[bank].write(bit);
+    }
+}
+
+impl Gpio<Input> {
+    /// Reads the pin's value. Returns `true` if the level is high and `false`
+    /// if the level is low.
+    pub fn level(&mut self) -> bool {
+        let (bank, bit) = self.bank_bit();
+        self.registers.LEV[bank].has_mask(bit)
+    }
+}
+
+/// Busy-waits for at least `cycles` CPU cycles. Used for the set-up and hold
+/// times the GPPUD/GPPUDCLK sequence requires.
+#[inline(never)]
+fn delay_cycles(cycles: usize) {
+    for _ in 0..cycles {
+        // `core::hint::spin_loop` replaces this on newer toolchains, but
+        // not on the one `bin/setup.sh` pins.
+        #[allow(deprecated)]
+        core::sync::atomic::spin_loop_hint();
+    }
+}
@@ -0,0 +1,202 @@
+use core::cell::UnsafeCell;
+use core::ptr;
+
+use crate::common::IO_BASE;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile};
+
+/// The base address of the legacy interrupt controller's registers.
+const INT_BASE: usize = IO_BASE + 0xB000 + 0x200;
+
+/// The number of GPU interrupt lines, split across two 32-bit banks.
+const GPU_INTERRUPTS: usize = 64;
+
+/// The number of "basic" ARM interrupt lines.
+const ARM_INTERRUPTS: usize = 8;
+
+/// An interrupt source known to the controller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    /// GPU peripheral interrupt `0..64`.
+    Gpu(u8),
+    /// Basic ARM interrupt `0..8`: the ARM timer, mailbox, doorbells and
+    /// GPU halted/illegal access notifications.
+    Arm(u8),
+}
+
+impl Interrupt {
+    pub const TIMER1: Interrupt = Interrupt::Gpu(1);
+    pub const TIMER3: Interrupt = Interrupt::Gpu(3);
+    pub const USB: Interrupt = Interrupt::Gpu(9);
+    pub const AUX: Interrupt = Interrupt::Gpu(29);
+    pub const GPIO0: Interrupt = Interrupt::Gpu(49);
+    pub const GPIO1: Interrupt = Interrupt::Gpu(50);
+    pub const GPIO2: Interrupt = Interrupt::Gpu(51);
+    pub const GPIO3: Interrupt = Interrupt::Gpu(52);
+    pub const UART: Interrupt = Interrupt::Gpu(57);
+
+    pub const ARM_TIMER: Interrupt = Interrupt::Arm(0);
+    pub const ARM_MAILBOX: Interrupt = Interrupt::Arm(1);
+
+    /// The number of interrupt sources.
+    pub const MAX: usize = GPU_INTERRUPTS + ARM_INTERRUPTS;
+
+    /// Returns an iterator over every interrupt source.
+    pub fn iter() -> impl Iterator<Item = Interrupt> {
+        (0..Interrupt::MAX).map(Interrupt::from_index)
+    }
+
+    /// Returns this interrupt's index in `0..Interrupt::MAX`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interrupt number is out of range.
+    pub fn to_index(self) -> usize {
+        match self {
+            Interrupt::Gpu(n) if (n as usize) < GPU_INTERRUPTS => n as usize,
+            Interrupt::Arm(n) if (n as usize) < ARM_INTERRUPTS => GPU_INTERRUPTS + n as usize,
+            _ => panic!("no such interrupt: {:?}", self),
+        }
+    }
+
+    /// Returns the interrupt with index `index`, the inverse of `to_index`.
+    pub fn from_index(index: usize) -> Interrupt {
+        if index < GPU_INTERRUPTS {
+            Interrupt::Gpu(index as u8)
+        } else {
+            Interrupt::Arm((index - GPU_INTERRUPTS) as u8)
+        }
+    }
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    BASIC_PENDING: ReadVolatile<u32>,
+    PENDING: [ReadVolatile<u32>; 2],
+    FIQ_CONTROL: Volatile<u32>,
+    ENABLE: [Volatile<u32>; 2],
+    ENABLE_BASIC: Volatile<u32>,
+    DISABLE: [Volatile<u32>; 2],
+    DISABLE_BASIC: Volatile<u32>,
+}
+
+/// The signature of an interrupt handler. It is passed the interrupt that
+/// fired and must clear its source before returning.
+pub type Handler = fn(Interrupt);
+
+/// A value for each interrupt, shared by every `Controller`.
+///
+/// Entries are only ever read and written whole, with single volatile
+/// accesses, and never read-modify-written, so this works before the MMU is
+/// on. An array of atomics would do the same, but the pinned toolchain can't
+/// initialize one in a `static`.
+struct Table<T>(UnsafeCell<[T; Interrupt::MAX]>);
+
+// Entries are at most word-sized, so each access is single-copy atomic.
+unsafe impl<T: Copy> Sync for Table<T> {}
+
+impl<T: Copy> Table<T> {
+    fn get(&self, int: Interrupt) -> T {
+        unsafe { ptr::read_volatile(&(*self.0.get())[int.to_index()]) }
+    }
+
+    fn set(&self, int: Interrupt, value: T) {
+        unsafe { ptr::write_volatile(&mut (*self.0.get())[int.to_index()], value) }
+    }
+}
+
+/// The registered handler of each interrupt, as a function pointer, or `0`.
+static HANDLERS: Table<usize> = Table(UnsafeCell::new([0; Interrupt::MAX]));
+
+/// Whether each interrupt was enabled through a `Controller`. The GPU
+/// firmware enables interrupts of its own; `dispatch` leaves those alone.
+static ENABLED: Table<bool> = Table(UnsafeCell::new([false; Interrupt::MAX]));
+
+/// An interrupt controller. Used to enable and disable interrupts as well as
+/// to check if an interrupt is pending and dispatch it to its handler.
+pub struct Controller {
+    registers: &'static mut Registers,
+}
+
+impl Controller {
+    /// Returns a new handle to the interrupt controller.
+    pub fn new() -> Controller {
+        Controller {
+            registers: unsafe { &mut *(INT_BASE as *mut Registers) },
+        }
+    }
+
+    /// Enables the interrupt `int`.
+    pub fn enable(&mut self, int: Interrupt) {
+        ENABLED.set(int, true);
+
+        // The enable and disable registers are write-1-to-set: zero bits
+        // leave the other interrupts alone.
+        match int {
+            Interrupt::Gpu(n) => self.registers.ENABLE[bank(int)].write(1 << (n % 32)),
+            Interrupt::Arm(n) => self.registers.ENABLE_BASIC.write(1 << n),
+        }
+    }
+
+    /// Disables the interrupt `int`.
+    pub fn disable(&mut self, int: Interrupt) {
+        ENABLED.set(int, false);
+        match int {
+            Interrupt::Gpu(n) => self.registers.DISABLE[bank(int)].write(1 << (n % 32)),
+            Interrupt::Arm(n) => self.registers.DISABLE_BASIC.write(1 << n),
+        }
+    }
+
+    /// Returns `true` if `int` is pending. An interrupt can be pending
+    /// without being enabled.
+    pub fn is_pending(&self, int: Interrupt) -> bool {
+        match int {
+            Interrupt::Gpu(n) => self.registers.PENDING[bank(int)].has_mask(1 << (n % 32)),
+            Interrupt::Arm(n) => self.registers.BASIC_PENDING.has_mask(1 << n),
+        }
+    }
+
+    /// Registers `handler` to be called by `dispatch` when `int` is pending,
+    /// replacing any previous handler, and enables `int`.
+    pub fn register(&mut self, int: Interrupt, handler: Handler) {
+        HANDLERS.set(int, handler as usize);
+        self.enable(int);
+    }
+
+    /// Calls the registered handler of every pending interrupt that was
+    /// enabled through a `Controller`. Such an interrupt with no handler is
+    /// disabled instead so that it can't fire again. Interrupts enabled by
+    /// anything else, such as the GPU firmware, are ignored. Returns the
+    /// number of handlers called.
+    pub fn dispatch(&mut self) -> usize {
+        let mut handled = 0;
+        for int in Interrupt::iter() {
+            if !ENABLED.get(int) || !self.is_pending(int) {
+                continue;
+            }
+
+            match HANDLERS.get(int) {
+                0 => self.disable(int),
+                handler => {
+                    let handler: Handler = unsafe { core::mem::transmute(handler) };
+                    handler(int);
+                    handled += 1;
+                }
+            }
+        }
+        handled
+    }
+}
+
+impl Default for Controller {
+    fn default() -> Controller {
+        Controller::new()
+    }
+}
+
+/// Returns which of the two GPU register banks covers `int`.
+fn bank(int: Interrupt) -> usize {
+    int.to_index() / 32
+}
@@ -0,0 +1,11 @@
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+pub mod common;
+
+pub mod atags;
+pub mod gpio;
+pub mod interrupt;
+pub mod pm;
+pub mod timer;
+pub mod uart;
@@ -0,0 +1,37 @@
+use crate::common::IO_BASE;
+use volatile::prelude::*;
+use volatile::{Reserved, Volatile};
+
+/// The base address of the power management (`PM`) registers.
+const PM_BASE: usize = IO_BASE + 0x100000;
+
+/// Every write to a `PM` register must carry this password in its top byte.
+const PM_PASSWORD: u32 = 0x5a000000;
+
+/// `RSTC` bits selecting the reset performed when the watchdog expires.
+const PM_RSTC_WRCFG_CLR: u32 = 0xffffffcf;
+const PM_RSTC_WRCFG_FULL_RESET: u32 = 0x00000020;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    __r0: [Reserved<u32>; 7],
+    RSTC: Volatile<u32>,
+    RSTS: Volatile<u32>,
+    WDOG: Volatile<u32>,
+}
+
+/// Resets the board by arming the power management watchdog with the
+/// shortest possible timeout.
+pub fn reset() -> ! {
+    let registers = unsafe { &mut *(PM_BASE as *mut Registers) };
+
+    let rstc = registers.RSTC.read() & PM_RSTC_WRCFG_CLR;
+    registers.WDOG.write(PM_PASSWORD | 10);
+    registers.RSTC.write(PM_PASSWORD | rstc | PM_RSTC_WRCFG_FULL_RESET);
+
+    loop {
+        #[allow(deprecated)]
+        core::sync::atomic::spin_loop_hint();
+    }
+}
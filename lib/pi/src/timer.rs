@@ -0,0 +1,106 @@
+use core::time::Duration;
+
+use crate::common::IO_BASE;
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile};
+
+/// The base address for the ARM system timer registers.
+const TIMER_REG_BASE: usize = IO_BASE + 0x3000;
+
+/// The system timer runs at a fixed 1MHz, so one tick is one microsecond.
+const TICKS_PER_MICRO: u64 = 1;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    CS: Volatile<u32>,
+    CLO: ReadVolatile<u32>,
+    CHI: ReadVolatile<u32>,
+    COMPARE: [Volatile<u32>; 4],
+}
+
+/// One of the four compare channels of the system timer.
+///
+/// Channels 0 and 2 are used by the GPU firmware; the ARM core should only
+/// program channels 1 and 3.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    C0 = 0,
+    C1 = 1,
+    C2 = 2,
+    C3 = 3,
+}
+
+/// The Raspberry Pi ARM system timer.
+pub struct Timer {
+    registers: &'static mut Registers,
+}
+
+impl Timer {
+    /// Returns a new instance of `Timer`.
+    pub fn new() -> Timer {
+        Timer {
+            registers: unsafe { &mut *(TIMER_REG_BASE as *mut Registers) },
+        }
+    }
+
+    /// Reads the system timer's counter and returns the 64-bit counter value.
+    /// The returned value is the number of elapsed microseconds.
+    pub fn read(&self) -> u64 {
+        // CLO may wrap between reading CHI and CLO; if CHI changed while we
+        // were reading, read CLO again so both halves belong together.
+        let hi = self.registers.CHI.read();
+        let mut lo = self.registers.CLO.read();
+        let hi_again = self.registers.CHI.read();
+        if hi != hi_again {
+            lo = self.registers.CLO.read();
+        }
+
+        (((hi_again as u64) << 32) | lo as u64) / TICKS_PER_MICRO
+    }
+
+    /// Sets up a match in timer `channel` to occur `t` from now. The match is
+    /// signalled through the channel's `CS` bit and its interrupt line.
+    pub fn tick_in(&mut self, channel: Channel, t: Duration) {
+        let ticks = t.as_micros() as u64 * TICKS_PER_MICRO;
+        let target = self.registers.CLO.read().wrapping_add(ticks as u32);
+        self.clear_match(channel);
+        self.registers.COMPARE[channel as usize].write(target);
+    }
+
+    /// Returns `true` if `channel` has matched since its match bit was last
+    /// cleared.
+    pub fn is_matched(&self, channel: Channel) -> bool {
+        self.registers.CS.has_mask(1 << channel as u32)
+    }
+
+    /// Clears the match bit of `channel`, which also acknowledges its
+    /// interrupt.
+    pub fn clear_match(&mut self, channel: Channel) {
+        // CS is write-1-to-clear: writing zeroes leaves other channels alone.
+        self.registers.CS.write(1 << channel as u32);
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+/// Returns current time.
+pub fn current_time() -> Duration {
+    Duration::from_micros(Timer::new().read())
+}
+
+/// Spins until `t` duration have passed.
+pub fn spin_sleep(t: Duration) {
+    let end = current_time() + t;
+    while current_time() < end {}
+}
+
+/// Sets up a match in timer `channel` to occur `t` from now.
+pub fn tick_in(channel: Channel, t: Duration) {
+    Timer::new().tick_in(channel, t);
+}
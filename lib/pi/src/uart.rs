@@ -0,0 +1,240 @@
+use core::fmt;
+use core::time::Duration;
+
+use shim::io;
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile};
+
+use crate::common::IO_BASE;
+use crate::gpio::{Function, Gpio, Pull};
+use crate::timer;
+
+/// The base address for the `MU` registers.
+const MU_REG_BASE: usize = IO_BASE + 0x215040;
+
+/// The `AUXENB` register from page 9 of the BCM2837 documentation.
+const AUX_ENABLES: *mut Volatile<u8> = (IO_BASE + 0x215004) as *mut Volatile<u8>;
+
+/// The core clock the mini UART's baud rate generator is derived from.
+const SYSTEM_CLOCK_HZ: u32 = 250_000_000;
+
+/// The baud rate `MiniUart::new()` configures.
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+
+/// The slowest and fastest baud rates the mini UART can generate: those with
+/// the largest and smallest dividers its 16-bit `BAUD` register can hold.
+pub const MIN_BAUD_RATE: u32 = SYSTEM_CLOCK_HZ / (8 * (u16::max_value() as u32 + 1)) + 1;
+pub const MAX_BAUD_RATE: u32 = SYSTEM_CLOCK_HZ / 8;
+
+/// The GPIO pins the mini UART is routed to through `Function::Alt5`.
+const TX_PIN: u8 = 14;
+const RX_PIN: u8 = 15;
+
+/// Enum representing bit fields of the `AUX_MU_LSR_REG` register.
+#[repr(u8)]
+enum LsrStatus {
+    DataReady = 1,
+    TxAvailable = 1 << 5,
+    TxIdle = 1 << 6,
+}
+
+/// `AUX_MU_IER_REG` value enabling only the receive interrupt. Bits 3:2 are
+/// documented as unused but must be set for the interrupt to be raised
+/// (BCM2835 datasheet errata).
+const IER_RX_INTERRUPT: u8 = 0b0101;
+
+/// The number of data bits per character.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataBits {
+    Seven = 0b00,
+    Eight = 0b11,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    IO: Volatile<u8>,
+    __r0: [Reserved<u8>; 3],
+    IER: Volatile<u8>,
+    __r1: [Reserved<u8>; 3],
+    IIR: Volatile<u8>,
+    __r2: [Reserved<u8>; 3],
+    LCR: Volatile<u8>,
+    __r3: [Reserved<u8>; 3],
+    MCR: Volatile<u8>,
+    __r4: [Reserved<u8>; 3],
+    LSR: ReadVolatile<u8>,
+    __r5: [Reserved<u8>; 3],
+    MSR: ReadVolatile<u8>,
+    __r6: [Reserved<u8>; 3],
+    SCRATCH: Volatile<u8>,
+    __r7: [Reserved<u8>; 3],
+    CNTL: Volatile<u8>,
+    __r8: [Reserved<u8>; 3],
+    STAT: ReadVolatile<u32>,
+    BAUD: Volatile<u16>,
+    __r9: [Reserved<u8>; 2],
+}
+
+/// The Raspberry Pi's "mini UART".
+pub struct MiniUart {
+    registers: &'static mut Registers,
+    timeout: Option<Duration>,
+}
+
+impl MiniUart {
+    /// Initializes the mini UART by enabling it as an auxiliary peripheral,
+    /// setting the data size to 8 bits, setting the BAUD rate to ~115200 (baud
+    /// divider of 270), setting GPIO pins 14 and 15 to alternative function 5
+    /// (TXD1/RDXD1), and finally enabling the UART transmitter and receiver.
+    ///
+    /// By default, reads will never time out. To set a read timeout, use
+    /// `set_read_timeout()`.
+    pub fn new() -> MiniUart {
+        let registers = unsafe {
+            // Enable the mini UART as an auxiliary device.
+            (*AUX_ENABLES).or_mask(1);
+            &mut *(MU_REG_BASE as *mut Registers)
+        };
+
+        // Keep the transmitter and receiver off while reconfiguring.
+        registers.CNTL.write(0);
+        registers.IER.write(0);
+        registers.MCR.write(0);
+        // Clear both FIFOs.
+        registers.IIR.write(0b110);
+
+        Gpio::new(TX_PIN).into_alt(Function::Alt5).set_pull(Pull::Off);
+        Gpio::new(RX_PIN).into_alt(Function::Alt5).set_pull(Pull::Off);
+
+        let mut uart = MiniUart {
+            registers,
+            timeout: None,
+        };
+        uart.set_data_bits(DataBits::Eight);
+        uart.set_baud_rate(DEFAULT_BAUD_RATE);
+
+        // Enable the transmitter and receiver.
+        uart.registers.CNTL.write(0b11);
+        uart
+    }
+
+    /// Sets the baud rate to the closest rate the mini UART can generate from
+    /// the 250MHz core clock. Rates outside `MIN_BAUD_RATE..=MAX_BAUD_RATE`
+    /// (477 to 31,250,000) are clamped to that range.
+    pub fn set_baud_rate(&mut self, baud: u32) {
+        let baud = baud.max(MIN_BAUD_RATE).min(MAX_BAUD_RATE);
+        let divider = SYSTEM_CLOCK_HZ / (8 * baud) - 1;
+        self.registers.BAUD.write(divider as u16);
+    }
+
+    /// Selects 7-bit or 8-bit characters.
+    pub fn set_data_bits(&mut self, bits: DataBits) {
+        self.registers.LCR.write(bits as u8);
+    }
+
+    /// Set the read timeout to `t` duration.
+    pub fn set_read_timeout(&mut self, t: Duration) {
+        self.timeout = Some(t);
+    }
+
+    /// Enables or disables the receive interrupt, which is raised on the AUX
+    /// interrupt line for as long as there is a byte ready to be read.
+    pub fn set_rx_interrupt(&mut self, enabled: bool) {
+        self.registers.IER.write(if enabled { IER_RX_INTERRUPT } else { 0 });
+    }
+
+    /// Write the byte `byte`. This method blocks until there is space
+    /// available in the output FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        while !self.registers.LSR.has_mask(LsrStatus::TxAvailable as u8) {}
+        self.registers.IO.write(byte);
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. If this
+    /// method returns `true`, a subsequent call to `read_byte` is guaranteed
+    /// to return immediately. This method does not block.
+    pub fn has_byte(&self) -> bool {
+        self.registers.LSR.has_mask(LsrStatus::DataReady as u8)
+    }
+
+    /// Blocks until there is a byte ready to read. If a read timeout is set,
+    /// this method blocks for at most that amount of time. Otherwise, this
+    /// method blocks indefinitely until there is a byte to read.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns an error of kind
+    /// `TimedOut` if the timeout expired while waiting for a byte to be
+    /// ready. If this method returns `Ok(())`, a subsequent call to
+    /// `read_byte` is guaranteed to return immediately.
+    pub fn wait_for_byte(&self) -> io::Result<()> {
+        let deadline = self.timeout.map(|t| timer::current_time() + t);
+        while !self.has_byte() {
+            if let Some(deadline) = deadline {
+                if timer::current_time() >= deadline {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "UART read timed out"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    pub fn read_byte(&mut self) -> u8 {
+        while !self.has_byte() {}
+        self.registers.IO.read()
+    }
+
+    /// Blocks until every byte written so far has left the transmitter.
+    fn wait_for_tx_idle(&self) {
+        while !self.registers.LSR.has_mask(LsrStatus::TxIdle as u8) {}
+    }
+}
+
+impl Default for MiniUart {
+    fn default() -> MiniUart {
+        MiniUart::new()
+    }
+}
+
+impl fmt::Write for MiniUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+impl io::Read for MiniUart {
+    /// Waits for the first byte, honouring the read timeout, then reads as
+    /// many bytes as are immediately available without blocking again.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.wait_for_byte()?;
+
+        let mut read = 0;
+        while read < buf.len() && self.has_byte() {
+            buf[read] = self.read_byte();
+            read += 1;
+        }
+        Ok(read)
+    }
+}
+
+impl io::Write for MiniUart {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.write_byte(byte);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wait_for_tx_idle();
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+#![cfg_attr(feature = "no_std", no_std)]
+
+//! A thin compatibility layer over the parts of `std` that our libraries
+//! need. On the host this simply re-exports `std`; with the `no_std` feature
+//! enabled it provides minimal, API-compatible replacements so the same
+//! library code can run in the kernel.
+
+#[cfg(feature = "no_std")]
+pub mod io;
+
+#[cfg(not(feature = "no_std"))]
+pub use std::io;
@@ -0,0 +1,172 @@
+use super::StackVec;
+
+#[test]
+fn assignment_text_example() {
+    let mut storage = [0u8; 1024];
+    let mut vec = StackVec::new(&mut storage);
+
+    for i in 0..10 {
+        vec.push(i * i).expect("can push 1024 times");
+    }
+
+    for (i, v) in vec.iter().enumerate() {
+        assert_eq!(*v, (i * i) as u8);
+    }
+
+    let last_element = vec.pop().expect("has elements");
+    assert_eq!(last_element, 9 * 9);
+}
+
+#[test]
+fn len_and_capacity_ok() {
+    let mut storage = [0u8; 1024];
+    let stack_vec = StackVec::new(&mut storage);
+
+    assert_eq!(stack_vec.len(), 0);
+    assert_eq!(stack_vec.capacity(), 1024);
+    assert!(stack_vec.is_empty());
+    assert!(!stack_vec.is_full());
+}
+
+#[test]
+fn push_until_full_returns_err() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+
+    for i in 0..4 {
+        assert!(stack_vec.push(i).is_ok());
+        assert_eq!(stack_vec.len(), i + 1);
+    }
+
+    assert!(stack_vec.is_full());
+    assert_eq!(stack_vec.push(4), Err(4));
+    assert_eq!(stack_vec.len(), 4);
+    assert_eq!(stack_vec.as_slice(), &[0, 1, 2, 3]);
+}
+
+#[test]
+fn pop() {
+    let mut storage = [0usize; 2];
+    let mut stack_vec = StackVec::new(&mut storage);
+    assert!(stack_vec.pop().is_none());
+
+    stack_vec.push(123).unwrap();
+    stack_vec.push(321).unwrap();
+    assert_eq!(stack_vec.len(), 2);
+
+    assert_eq!(stack_vec.pop(), Some(321));
+    assert_eq!(stack_vec.pop(), Some(123));
+    assert_eq!(stack_vec.pop(), None);
+    assert!(stack_vec.is_empty());
+
+    stack_vec.push(1).unwrap();
+    assert_eq!(stack_vec.pop(), Some(1));
+}
+
+#[test]
+fn with_len_treats_prefix_as_pushed() {
+    let mut storage = [5u8, 6, 7, 8];
+    let mut stack_vec = StackVec::with_len(&mut storage, 2);
+
+    assert_eq!(stack_vec.len(), 2);
+    assert_eq!(&*stack_vec, &[5, 6]);
+
+    stack_vec.push(9).unwrap();
+    assert_eq!(&*stack_vec, &[5, 6, 9]);
+}
+
+#[test]
+#[should_panic]
+fn with_len_beyond_storage_panics() {
+    let mut storage = [0u8; 2];
+    StackVec::with_len(&mut storage, 3);
+}
+
+#[test]
+fn truncate() {
+    let mut storage = [0u8; 8];
+    let mut stack_vec = StackVec::new(&mut storage);
+    for i in 0..6 {
+        stack_vec.push(i).unwrap();
+    }
+
+    stack_vec.truncate(10);
+    assert_eq!(stack_vec.len(), 6);
+
+    stack_vec.truncate(3);
+    assert_eq!(&*stack_vec, &[0, 1, 2]);
+    assert_eq!(stack_vec.capacity(), 8);
+
+    stack_vec.truncate(0);
+    assert!(stack_vec.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn indexing_past_len_panics() {
+    let mut storage = [0u8; 8];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.push(1).unwrap();
+
+    let _ = stack_vec[1];
+}
+
+#[test]
+fn deref_mut_writes_through() {
+    let mut storage = [0u8; 4];
+    {
+        let mut stack_vec = StackVec::new(&mut storage);
+        stack_vec.push(1).unwrap();
+        stack_vec.push(2).unwrap();
+
+        stack_vec[0] = 10;
+        stack_vec.as_mut_slice()[1] = 20;
+        assert_eq!(stack_vec.iter().sum::<u8>(), 30);
+    }
+
+    assert_eq!(storage, [10, 20, 0, 0]);
+}
+
+#[test]
+fn iterators() {
+    let mut storage = [0usize; 16];
+    let mut stack_vec = StackVec::new(&mut storage);
+    for i in 0..5 {
+        stack_vec.push(i).unwrap();
+    }
+
+    let mut expected = 0;
+    for v in &stack_vec {
+        assert_eq!(*v, expected);
+        expected += 1;
+    }
+    assert_eq!(expected, 5);
+
+    for v in &mut stack_vec {
+        *v *= 2;
+    }
+
+    let collected: Vec<usize> = stack_vec.into_iter().map(|v| *v).collect();
+    assert_eq!(collected, vec![0, 2, 4, 6, 8]);
+}
+
+#[test]
+fn into_slice_is_truncated_to_len() {
+    let mut storage = [0u8; 8];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.push(7).unwrap();
+    stack_vec.push(8).unwrap();
+
+    assert_eq!(stack_vec.into_slice(), &mut [7, 8]);
+}
+
+#[test]
+fn works_with_non_copy_types() {
+    let mut storage: [String; 3] = Default::default();
+    let mut stack_vec = StackVec::new(&mut storage);
+
+    stack_vec.push("hello".to_string()).unwrap();
+    stack_vec.push("world".to_string()).unwrap();
+    assert_eq!(stack_vec.join(" "), "hello world");
+    assert_eq!(stack_vec.pop(), Some("world".to_string()));
+}
@@ -0,0 +1,144 @@
+//! Sends a file (or stdin) to a serial device, by default over XMODEM.
+//!
+//! Any TTY works, including the PTY QEMU allocates for `-serial pty`, which is
+//! how `boot/qemu.sh` exposes the bootloader's UART:
+//!
+//! ```text
+//! $ make -C boot qemu     # "char device redirected to /dev/pts/N"
+//! $ ttywrite -i build/blinky.bin /dev/pts/N
+//! ```
+
+mod parsers;
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use serial::core::{BaudRate, CharSize, FlowControl, SerialDevice, SerialPortSettings, StopBits};
+use structopt::StructOpt;
+use xmodem::{Progress, Xmodem};
+
+use crate::parsers::{parse_baud_rate, parse_flow_control, parse_stop_bits, parse_width};
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Write to TTY using the XMODEM protocol by default.")]
+struct Opt {
+    #[structopt(short = "i", help = "Input file (defaults to stdin if not set)", parse(from_os_str))]
+    input: Option<PathBuf>,
+
+    #[structopt(
+        short = "b",
+        long = "baud",
+        parse(try_from_str = parse_baud_rate),
+        help = "Set baud rate",
+        default_value = "115200"
+    )]
+    baud_rate: BaudRate,
+
+    #[structopt(
+        short = "t",
+        long = "timeout",
+        parse(try_from_str),
+        help = "Set timeout in seconds",
+        default_value = "10"
+    )]
+    timeout: u64,
+
+    #[structopt(
+        short = "w",
+        long = "width",
+        parse(try_from_str = parse_width),
+        help = "Set data character width in bits",
+        default_value = "8"
+    )]
+    char_width: CharSize,
+
+    #[structopt(help = "Path to TTY device", parse(from_os_str))]
+    tty_path: PathBuf,
+
+    #[structopt(
+        short = "f",
+        long = "flow-control",
+        parse(try_from_str = parse_flow_control),
+        help = "Enable flow control ('none', 'software' or 'hardware')",
+        default_value = "none"
+    )]
+    flow_control: FlowControl,
+
+    #[structopt(
+        short = "s",
+        long = "stop-bits",
+        parse(try_from_str = parse_stop_bits),
+        help = "Set number of stop bits",
+        default_value = "1"
+    )]
+    stop_bits: StopBits,
+
+    #[structopt(short = "r", long = "raw", help = "Disable XMODEM")]
+    raw: bool,
+}
+
+fn progress_fn(progress: Progress) {
+    match progress {
+        Progress::Waiting => eprintln!("[ttywrite] waiting for receiver..."),
+        Progress::Started => eprintln!("[ttywrite] transfer started"),
+        Progress::Packet(n) => eprint!("\r[ttywrite] packet {:>3} sent", n),
+    }
+}
+
+/// Copies `input` to `port` verbatim, reporting the running byte count.
+fn send_raw<R: Read, W: Write>(mut input: R, mut port: W) -> io::Result<usize> {
+    let mut buf = [0u8; 4096];
+    let mut sent = 0;
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        port.write_all(&buf[..n])?;
+        sent += n;
+        eprint!("\r[ttywrite] {} bytes sent", sent);
+    }
+
+    port.flush()?;
+    Ok(sent)
+}
+
+fn run(opt: &Opt) -> io::Result<usize> {
+    let mut port = serial::open(&opt.tty_path)?;
+
+    port.set_timeout(Duration::from_secs(opt.timeout))?;
+    let mut settings = port.read_settings()?;
+    settings.set_baud_rate(opt.baud_rate)?;
+    settings.set_char_size(opt.char_width);
+    settings.set_stop_bits(opt.stop_bits);
+    settings.set_flow_control(opt.flow_control);
+    port.write_settings(&settings)?;
+
+    let input: Box<dyn Read> = match opt.input {
+        Some(ref path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin()),
+    };
+
+    if opt.raw {
+        send_raw(input, port)
+    } else {
+        Xmodem::transmit_with_progress(input, port, progress_fn)
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    match run(&opt) {
+        Ok(sent) => eprintln!("\n[ttywrite] wrote {} bytes to {}", sent, opt.tty_path.display()),
+        Err(e) => {
+            eprintln!("\n[ttywrite] error: {}", e);
+            process::exit(1);
+        }
+    }
+}
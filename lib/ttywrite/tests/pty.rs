@@ -0,0 +1,172 @@
+//! Drives the `ttywrite` binary against a pseudo-terminal, the same kind of
+//! device QEMU's `-serial pty` creates, so no hardware is needed.
+
+use std::ffi::CStr;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::io::FromRawFd;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::ptr;
+
+use xmodem::Xmodem;
+
+/// A pseudo-terminal pair. `master` is our end; `slave_path` is the device
+/// handed to `ttywrite`.
+struct Pty {
+    master: File,
+    slave_path: PathBuf,
+    // Held open so the master never sees a hang-up between opens.
+    _slave: File,
+}
+
+fn openpty() -> Pty {
+    let mut master = 0;
+    let mut slave = 0;
+    let mut name = [0 as libc::c_char; 128];
+    unsafe {
+        let ret = libc::openpty(&mut master, &mut slave, name.as_mut_ptr(), ptr::null(), ptr::null());
+        assert_eq!(ret, 0, "openpty failed: {}", io::Error::last_os_error());
+
+        // Tests run in parallel; don't leak one test's pty into another
+        // test's child process.
+        for &fd in &[master, slave] {
+            assert_eq!(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC), 0);
+        }
+
+        // Put the line discipline in raw mode up front so nothing sent from
+        // the master before `ttywrite` configures the port is echoed or held
+        // back waiting for a newline.
+        let mut termios = std::mem::zeroed();
+        assert_eq!(libc::tcgetattr(slave, &mut termios), 0);
+        libc::cfmakeraw(&mut termios);
+        assert_eq!(libc::tcsetattr(slave, libc::TCSANOW, &termios), 0);
+
+        Pty {
+            master: File::from_raw_fd(master),
+            slave_path: PathBuf::from(CStr::from_ptr(name.as_ptr()).to_str().unwrap()),
+            _slave: File::from_raw_fd(slave),
+        }
+    }
+}
+
+/// Writes `data` to a fresh temporary file and returns its path.
+fn input_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ttywrite-{}-{}", std::process::id(), name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn ttywrite(args: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_ttywrite"))
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn ttywrite")
+}
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 13 + 5) as u8).collect()
+}
+
+#[test]
+fn raw_transfer() {
+    let mut pty = openpty();
+    let input = data(3000);
+    let path = input_file("raw", &input);
+
+    let child = ttywrite(&["-r", "-i", path.to_str().unwrap(), pty.slave_path.to_str().unwrap()]);
+
+    let mut output = vec![0u8; input.len()];
+    pty.master.read_exact(&mut output).unwrap();
+    let result = child.wait_with_output().unwrap();
+
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert_eq!(output, input);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn xmodem_transfer() {
+    let pty = openpty();
+    let input = data(1000);
+    let path = input_file("xmodem", &input);
+
+    let mut child = ttywrite(&["-i", path.to_str().unwrap(), pty.slave_path.to_str().unwrap()]);
+
+    // Configuring the port may discard pending input, so only send the
+    // receiver's NAK once the transmitter reports it is waiting for it.
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    while !line.contains("waiting for receiver") {
+        line.clear();
+        assert_ne!(stderr.read_line(&mut line).unwrap(), 0, "ttywrite exited early");
+    }
+
+    let mut output = Vec::new();
+    let received = Xmodem::receive(&pty.master, &mut output).unwrap();
+    let status = child.wait().unwrap();
+
+    assert!(status.success());
+    assert_eq!(received, 1024);
+    assert_eq!(&output[..input.len()], &input[..]);
+    assert!(output[input.len()..].iter().all(|&b| b == 0));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn serial_settings_are_accepted() {
+    let mut pty = openpty();
+    let input = data(64);
+    let path = input_file("settings", &input);
+
+    let child = ttywrite(&[
+        "-r",
+        "-b",
+        "9600",
+        "-w",
+        "7",
+        "-s",
+        "2",
+        "-f",
+        "software",
+        "-i",
+        path.to_str().unwrap(),
+        pty.slave_path.to_str().unwrap(),
+    ]);
+
+    let mut output = vec![0u8; input.len()];
+    pty.master.read_exact(&mut output).unwrap();
+    let result = child.wait_with_output().unwrap();
+
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn missing_device_fails() {
+    let result = ttywrite(&["-r", "/dev/does-not-exist"]).wait_with_output().unwrap();
+    assert!(!result.status.success());
+}
+
+#[test]
+fn xmodem_timeout_fails() {
+    // Nobody answers on the master side, so the transmitter never sees NAK.
+    let pty = openpty();
+    let path = input_file("timeout", &data(128));
+
+    let result = ttywrite(&["-t", "1", "-i", path.to_str().unwrap(), pty.slave_path.to_str().unwrap()])
+        .wait_with_output()
+        .unwrap();
+
+    assert!(!result.status.success());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn invalid_arguments_fail() {
+    let pty = openpty();
+    let result = ttywrite(&["-w", "9", pty.slave_path.to_str().unwrap()]).wait_with_output().unwrap();
+    assert!(!result.status.success());
+}
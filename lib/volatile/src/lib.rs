@@ -0,0 +1,83 @@
+#![no_std]
+
+//! Wrappers for volatile memory accesses.
+//!
+//! The types in this crate are meant to be overlaid on memory-mapped I/O
+//! registers. A driver describes its register block as a `#[repr(C)]` struct
+//! whose fields are one of [`Volatile`], [`ReadVolatile`], [`WriteVolatile`]
+//! or [`Reserved`] and casts the peripheral's base address to a reference to
+//! that struct. Every access then goes through `read_volatile` or
+//! `write_volatile`, and accesses the hardware doesn't allow, such as a write
+//! to a read-only register, are rejected at compile time.
+
+mod traits;
+
+pub mod prelude {
+    pub use crate::traits::{Readable, ReadableWriteable, Writeable};
+}
+
+pub use crate::traits::*;
+
+/// A wrapper type that enforces **read-only** _volatile_ accesses to a raw
+/// pointer.
+#[repr(transparent)]
+pub struct ReadVolatile<T>(T);
+
+/// A wrapper type that enforces **write-only** _volatile_ accesses to a raw
+/// pointer.
+#[repr(transparent)]
+pub struct WriteVolatile<T>(T);
+
+/// A wrapper type that enforces _volatile_ (read **or** write) accesses to a
+/// raw pointer.
+#[repr(transparent)]
+pub struct Volatile<T>(T);
+
+/// A wrapper type that prevents reads or writes to its value.
+///
+/// This type implements no methods. It is meant to make the inner type
+/// inaccessible to prevent accesses to reserved registers.
+#[repr(transparent)]
+pub struct Reserved<T>(T);
+
+impl<T> Volatile<T> {
+    /// Returns a `Volatile` reference to the memory pointed to by `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non-null, properly aligned and valid for reads and
+    /// writes of `T` for the lifetime `'a`.
+    pub unsafe fn from_ptr<'a>(ptr: *mut T) -> &'a mut Volatile<T> {
+        &mut *(ptr as *mut Volatile<T>)
+    }
+}
+
+impl<T> Readable<T> for ReadVolatile<T> {
+    #[inline(always)]
+    fn inner(&self) -> *const T {
+        &self.0
+    }
+}
+
+impl<T> Writeable<T> for WriteVolatile<T> {
+    #[inline(always)]
+    fn inner_mut(&mut self) -> *mut T {
+        &mut self.0
+    }
+}
+
+impl<T> Readable<T> for Volatile<T> {
+    #[inline(always)]
+    fn inner(&self) -> *const T {
+        &self.0
+    }
+}
+
+impl<T> Writeable<T> for Volatile<T> {
+    #[inline(always)]
+    fn inner_mut(&mut self) -> *mut T {
+        &mut self.0
+    }
+}
+
+impl<T> ReadableWriteable<T> for Volatile<T> {}
@@ -0,0 +1,73 @@
+use core::ops::{BitAnd, BitOr, Not};
+use core::ptr::{read_volatile, write_volatile};
+
+/// Trait implemented by **readable** volatile wrappers.
+pub trait Readable<T> {
+    /// Returns a pointer to the memory location being wrapped.
+    fn inner(&self) -> *const T;
+
+    /// Reads and returns the value pointed to by `self`. The read is always
+    /// done using volatile semantics.
+    #[inline(always)]
+    fn read(&self) -> T {
+        unsafe { read_volatile(self.inner()) }
+    }
+
+    /// Returns `true` if the value pointed to by `self` has all of the bits
+    /// in `mask` set.
+    #[inline(always)]
+    fn has_mask(&self, mask: T) -> bool
+    where
+        T: BitAnd<Output = T> + PartialEq + Copy,
+    {
+        (self.read() & mask) == mask
+    }
+}
+
+/// Trait implemented by **writeable** volatile wrappers.
+pub trait Writeable<T> {
+    /// Returns a mutable pointer to the memory location being wrapped.
+    fn inner_mut(&mut self) -> *mut T;
+
+    /// Writes the value `val` to the inner address of `self`. The write is
+    /// always done using volatile semantics.
+    #[inline(always)]
+    fn write(&mut self, val: T) {
+        unsafe { write_volatile(self.inner_mut(), val) }
+    }
+}
+
+/// Trait implemented by **readable _and_ writeable** volatile wrappers.
+pub trait ReadableWriteable<T>: Readable<T> + Writeable<T> {
+    /// Applies the mask `mask` using `|` to the value referred to by `self`.
+    /// This is equivalent to `self.write(self.read() | mask)`.
+    #[inline(always)]
+    fn or_mask(&mut self, mask: T)
+    where
+        T: BitOr<Output = T>,
+    {
+        let init_val = self.read();
+        self.write(init_val | mask);
+    }
+
+    /// Applies the mask `mask` using `&` to the value referred to by `self`.
+    /// This is equivalent to `self.write(self.read() & mask)`.
+    #[inline(always)]
+    fn and_mask(&mut self, mask: T)
+    where
+        T: BitAnd<Output = T>,
+    {
+        let init_val = self.read();
+        self.write(init_val & mask);
+    }
+
+    /// Clears the bits in `mask` from the value referred to by `self`. This
+    /// is equivalent to `self.write(self.read() & !mask)`.
+    #[inline(always)]
+    fn clear_mask(&mut self, mask: T)
+    where
+        T: BitAnd<Output = T> + Not<Output = T>,
+    {
+        self.and_mask(!mask);
+    }
+}
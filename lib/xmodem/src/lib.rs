@@ -0,0 +1,371 @@
+#![cfg_attr(feature = "no_std", no_std)]
+
+//! An implementation of the XMODEM file transfer protocol with 128-byte
+//! packets and an 8-bit checksum.
+//!
+//! The transfer is driven over any type implementing `io::Read` and
+//! `io::Write`, so the same code runs on the host over a serial port and in
+//! the kernel over the UART.
+
+use shim::io;
+
+mod progress;
+mod read_ext;
+#[cfg(test)]
+mod tests;
+
+pub use crate::progress::{noop, Progress, ProgressFn};
+use crate::read_ext::ReadExt;
+
+const SOH: u8 = 0x01;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+
+/// The number of data bytes in every packet.
+pub const PACKET_SIZE: usize = 128;
+
+/// The number of times a packet is retried before the transfer is abandoned.
+pub const RETRIES: usize = 10;
+
+/// Implementation of the XMODEM protocol.
+pub struct Xmodem<R> {
+    packet: u8,
+    started: bool,
+    inner: R,
+    progress: ProgressFn,
+}
+
+impl Xmodem<()> {
+    /// Transmits `data` to the receiver `to` using the XMODEM protocol. If the
+    /// length of the total data yielded by `data` is not a multiple of 128
+    /// bytes, the data is padded with zeroes and sent to the receiver.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    #[inline]
+    pub fn transmit<R, W>(data: R, to: W) -> io::Result<usize>
+    where
+        W: io::Read + io::Write,
+        R: io::Read,
+    {
+        Xmodem::transmit_with_progress(data, to, progress::noop)
+    }
+
+    /// Transmits `data` to the receiver `to` using the XMODEM protocol. If the
+    /// length of the total data yielded by `data` is not a multiple of 128
+    /// bytes, the data is padded with zeroes and sent to the receiver.
+    ///
+    /// The function `f` is used as a callback to indicate progress throughout
+    /// the transmission. See the [`Progress`] enum for more information.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    pub fn transmit_with_progress<R, W>(mut data: R, to: W, f: ProgressFn) -> io::Result<usize>
+    where
+        W: io::Read + io::Write,
+        R: io::Read,
+    {
+        let mut transmitter = Xmodem::new_with_progress(to, f);
+        let mut packet = [0u8; PACKET_SIZE];
+        let mut written = 0;
+        'next_packet: loop {
+            let n = data.read_max(&mut packet)?;
+            packet[n..].iter_mut().for_each(|b| *b = 0);
+
+            if n == 0 {
+                transmitter.write_packet(&[])?;
+                return Ok(written);
+            }
+
+            for _ in 0..RETRIES {
+                match transmitter.write_packet(&packet) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                    Ok(_) => {
+                        written += n;
+                        continue 'next_packet;
+                    }
+                }
+            }
+
+            transmitter.cancel()?;
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "bad transmit"));
+        }
+    }
+
+    /// Receives `data` from `from` using the XMODEM protocol and writes it
+    /// into `into`. Returns the number of bytes read from `from`, a multiple
+    /// of 128.
+    #[inline]
+    pub fn receive<R, W>(from: R, into: W) -> io::Result<usize>
+    where
+        R: io::Read + io::Write,
+        W: io::Write,
+    {
+        Xmodem::receive_with_progress(from, into, progress::noop)
+    }
+
+    /// Receives `data` from `from` using the XMODEM protocol and writes it
+    /// into `into`. Returns the number of bytes read from `from`, a multiple
+    /// of 128.
+    ///
+    /// The function `f` is used as a callback to indicate progress throughout
+    /// the reception. See the [`Progress`] enum for more information.
+    ///
+    /// If writing to `into` fails, the transfer is cancelled and the write
+    /// error is returned.
+    pub fn receive_with_progress<R, W>(from: R, mut into: W, f: ProgressFn) -> io::Result<usize>
+    where
+        R: io::Read + io::Write,
+        W: io::Write,
+    {
+        let mut receiver = Xmodem::new_with_progress(from, f);
+        let mut packet = [0u8; PACKET_SIZE];
+        let mut received = 0;
+        'next_packet: loop {
+            for _ in 0..RETRIES {
+                match receiver.read_packet(&mut packet) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                    Ok(0) => break 'next_packet,
+                    Ok(n) => {
+                        received += n;
+                        if let Err(e) = into.write_all(&packet) {
+                            // The packet has already been acknowledged, so
+                            // stop the sender before it sends another.
+                            receiver.cancel()?;
+                            return Err(e);
+                        }
+                        continue 'next_packet;
+                    }
+                }
+            }
+
+            receiver.cancel()?;
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "bad receive"));
+        }
+
+        Ok(received)
+    }
+}
+
+impl<T: io::Read + io::Write> Xmodem<T> {
+    /// Returns a new `Xmodem` instance with the internal reader/writer set to
+    /// `inner`. The returned instance can be used for both receiving
+    /// (downloading) and sending (uploading).
+    pub fn new(inner: T) -> Self {
+        Xmodem::new_with_progress(inner, progress::noop)
+    }
+
+    /// Returns a new `Xmodem` instance with the internal reader/writer set to
+    /// `inner`. The returned instance can be used for both receiving
+    /// (downloading) and sending (uploading). The function `f` is used as a
+    /// callback to indicate progress throughout the transfer. See the
+    /// [`Progress`] enum for more information.
+    pub fn new_with_progress(inner: T, f: ProgressFn) -> Self {
+        Xmodem {
+            packet: 1,
+            started: false,
+            inner,
+            progress: f,
+        }
+    }
+
+    /// Reads a single byte from the inner I/O stream. If `abort_on_can` is
+    /// `true`, an error of `ConnectionAborted` is returned if the read byte is
+    /// `CAN`.
+    fn read_byte(&mut self, abort_on_can: bool) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.inner.read_exact(&mut buf)?;
+
+        let byte = buf[0];
+        if abort_on_can && byte == CAN {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "received CAN"));
+        }
+
+        Ok(byte)
+    }
+
+    /// Writes a single byte to the inner I/O stream.
+    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        self.inner.write_all(&[byte])
+    }
+
+    /// Reads a single byte from the inner I/O stream and compares it to
+    /// `byte`. If the bytes match, the byte is returned as an `Ok`. If they
+    /// differ, a `CAN` byte is written out to the inner stream and an error is
+    /// returned: `ConnectionAborted` if the read byte was itself `CAN`, and
+    /// `InvalidData` with the message `expected` otherwise.
+    ///
+    /// A `CAN` is only treated as a cancellation on mismatch since `byte` may
+    /// legitimately be `0x18`, as with packet number 24.
+    fn expect_byte_or_cancel(&mut self, byte: u8, expected: &'static str) -> io::Result<u8> {
+        let read = self.read_byte(false)?;
+        if read != byte {
+            self.cancel()?;
+            if read == CAN {
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "received CAN"));
+            }
+            return Err(io::Error::new(io::ErrorKind::InvalidData, expected));
+        }
+
+        Ok(read)
+    }
+
+    /// Reads a single byte from the inner I/O stream and compares it to
+    /// `byte`. If they differ, an error of `InvalidData` with the message
+    /// `expected` is returned. Otherwise the byte is returned. If `byte` is
+    /// not `CAN` and the read byte is `CAN`, a `ConnectionAborted` error is
+    /// returned.
+    fn expect_byte(&mut self, byte: u8, expected: &'static str) -> io::Result<u8> {
+        let read = self.read_byte(byte != CAN)?;
+        if read != byte {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, expected));
+        }
+
+        Ok(read)
+    }
+
+    /// Aborts the transfer by sending `CAN` to the other side.
+    fn cancel(&mut self) -> io::Result<()> {
+        self.write_byte(CAN)?;
+        self.inner.flush()
+    }
+
+    /// Reads (downloads) a single packet from the inner stream using the
+    /// XMODEM protocol. On success, returns the number of bytes read (always
+    /// 128).
+    ///
+    /// The progress callback is called with `Progress::Started` when
+    /// reception begins and with `Progress::Packet` for each packet received.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the inner stream fails or if `buf` is
+    /// not large enough to hold a packet. If the checksum fails for a packet,
+    /// a `NAK` is sent and an error of `Interrupted` is returned: the caller
+    /// should retry. If the sender cancels the transfer, an error of
+    /// `ConnectionAborted` is returned. If an unexpected byte is received, the
+    /// transfer is cancelled and an error of `InvalidData` is returned. When
+    /// the sender signals the end of the transmission, `Ok(0)` is returned.
+    pub fn read_packet(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.len() < PACKET_SIZE {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "buffer smaller than a packet"));
+        }
+
+        if !self.started {
+            self.write_byte(NAK)?;
+            self.inner.flush()?;
+            self.started = true;
+            (self.progress)(Progress::Started);
+        }
+
+        match self.read_byte(true)? {
+            SOH => {}
+            EOT => {
+                self.write_byte(NAK)?;
+                self.inner.flush()?;
+                self.expect_byte_or_cancel(EOT, "expected second EOT")?;
+                self.write_byte(ACK)?;
+                self.inner.flush()?;
+                return Ok(0);
+            }
+            _ => {
+                self.cancel()?;
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "expected SOH or EOT"));
+            }
+        }
+
+        self.expect_byte_or_cancel(self.packet, "unexpected packet number")?;
+        self.expect_byte_or_cancel(!self.packet, "packet number complement mismatch")?;
+
+        let data = &mut buf[..PACKET_SIZE];
+        self.inner.read_exact(data)?;
+        let checksum = get_checksum(data);
+        if self.read_byte(false)? != checksum {
+            self.write_byte(NAK)?;
+            self.inner.flush()?;
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "checksum failed"));
+        }
+
+        self.write_byte(ACK)?;
+        self.inner.flush()?;
+        (self.progress)(Progress::Packet(self.packet));
+        self.packet = self.packet.wrapping_add(1);
+
+        Ok(PACKET_SIZE)
+    }
+
+    /// Sends (uploads) a single packet to the inner stream using the XMODEM
+    /// protocol. If `buf` is empty, end of transmission is sent. Users of this
+    /// interface should ensure that `write_packet(&[])` is called when data
+    /// transmission is complete. On success, returns the number of bytes
+    /// written.
+    ///
+    /// The progress callback is called with `Progress::Waiting` before
+    /// waiting for the receiver's `NAK`, ignoring any bytes other than
+    /// `CAN` that arrive first, `Progress::Started` when the
+    /// transmission begins, and `Progress::Packet` for each packet
+    /// transmitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to the inner stream fails or if `buf` is
+    /// neither empty nor exactly 128 bytes. If the receiver replies with
+    /// `NAK`, an error of `Interrupted` is returned: the caller should retry.
+    /// If the receiver cancels the transfer, an error of `ConnectionAborted`
+    /// is returned. If any other unexpected byte is received, an error of
+    /// `InvalidData` is returned.
+    pub fn write_packet(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() && buf.len() != PACKET_SIZE {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "packet must be 128 bytes"));
+        }
+
+        if !self.started {
+            (self.progress)(Progress::Waiting);
+            // Bytes before the first NAK aren't part of the protocol (e.g. a
+            // bootloader's status output), so skip them instead of failing.
+            while self.read_byte(true)? != NAK {}
+            self.started = true;
+            (self.progress)(Progress::Started);
+        }
+
+        if buf.is_empty() {
+            self.write_byte(EOT)?;
+            self.inner.flush()?;
+            self.expect_byte(NAK, "expected NAK after first EOT")?;
+            self.write_byte(EOT)?;
+            self.inner.flush()?;
+            self.expect_byte(ACK, "expected ACK after second EOT")?;
+            return Ok(0);
+        }
+
+        self.write_byte(SOH)?;
+        self.write_byte(self.packet)?;
+        self.write_byte(!self.packet)?;
+        self.inner.write_all(buf)?;
+        self.write_byte(get_checksum(buf))?;
+        self.inner.flush()?;
+
+        match self.read_byte(true)? {
+            ACK => {
+                (self.progress)(Progress::Packet(self.packet));
+                self.packet = self.packet.wrapping_add(1);
+                Ok(buf.len())
+            }
+            NAK => Err(io::Error::new(io::ErrorKind::Interrupted, "receiver rejected packet")),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "expected ACK or NAK")),
+        }
+    }
+
+    /// Flush this output stream, ensuring that all intermediately buffered
+    /// contents reach their destination.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the XMODEM checksum of `buf`: the sum of its bytes modulo 256.
+fn get_checksum(buf: &[u8]) -> u8 {
+    buf.iter().fold(0, |a: u8, b| a.wrapping_add(*b))
+}
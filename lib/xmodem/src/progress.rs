@@ -0,0 +1,16 @@
+/// Enum used to report the progress of a transfer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// Waiting for receiver to send NAK.
+    Waiting,
+    /// Download/upload has started.
+    Started,
+    /// Packet `.0` was transmitted/received.
+    Packet(u8),
+}
+
+/// Type for progress callbacks.
+pub type ProgressFn = fn(Progress);
+
+/// Noop progress callback.
+pub fn noop(_: Progress) {}
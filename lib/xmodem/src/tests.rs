@@ -0,0 +1,342 @@
+use std::io::{self, Cursor};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use crate::{Progress, Xmodem, ACK, CAN, EOT, NAK, PACKET_SIZE, RETRIES, SOH};
+
+/// One end of an in-memory, bidirectional byte stream.
+struct Pipe(Sender<u8>, Receiver<u8>);
+
+/// Returns two connected ends of an in-memory pipe.
+fn pipe() -> (Pipe, Pipe) {
+    let (tx1, rx1) = channel();
+    let (tx2, rx2) = channel();
+    (Pipe(tx1, rx2), Pipe(tx2, rx1))
+}
+
+impl io::Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // A hung-up peer looks like EOF.
+        match self.1.recv() {
+            Ok(byte) => buf[0] = byte,
+            Err(_) => return Ok(0),
+        }
+
+        let mut read = 1;
+        while read < buf.len() {
+            match self.1.try_recv() {
+                Ok(byte) => buf[read] = byte,
+                Err(_) => break,
+            }
+            read += 1;
+        }
+        Ok(read)
+    }
+}
+
+impl io::Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.0
+                .send(byte)
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "peer hung up"))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Wraps a stream and flips every bit of the outgoing bytes at the given
+/// offsets, simulating line noise.
+struct Corrupt<T> {
+    inner: T,
+    offsets: Vec<usize>,
+    written: usize,
+}
+
+impl<T> Corrupt<T> {
+    fn new(inner: T, offsets: Vec<usize>) -> Corrupt<T> {
+        Corrupt { inner, offsets, written: 0 }
+    }
+}
+
+impl<T: io::Read> io::Read for Corrupt<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<T: io::Write> io::Write for Corrupt<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            let byte = if self.offsets.contains(&self.written) { !byte } else { byte };
+            self.inner.write_all(&[byte])?;
+            self.written += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A stream with scripted input that records everything written to it.
+struct Script {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Script {
+    fn new(input: Vec<u8>) -> Script {
+        Script { input: Cursor::new(input), output: Vec::new() }
+    }
+}
+
+impl io::Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl io::Write for Script {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns `len` bytes of recognizable test data.
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + i / 256) as u8).collect()
+}
+
+/// Returns the wire encoding of packet `num` carrying `payload`.
+fn packet(num: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![SOH, num, !num];
+    packet.extend_from_slice(payload);
+    packet.push(payload.iter().fold(0u8, |a, b| a.wrapping_add(*b)));
+    packet
+}
+
+/// Runs a complete transfer of `input` over `tx` and `rx` on two threads and
+/// returns both results along with the received bytes.
+fn transfer<T, R>(input: Vec<u8>, tx: T, rx: R) -> (io::Result<usize>, io::Result<usize>, Vec<u8>)
+where
+    T: io::Read + io::Write + Send + 'static,
+    R: io::Read + io::Write + Send + 'static,
+{
+    let tx_thread = thread::spawn(move || Xmodem::transmit(&input[..], tx));
+    let rx_thread = thread::spawn(move || {
+        let mut output = Vec::new();
+        let result = Xmodem::receive(rx, &mut output);
+        (result, output)
+    });
+
+    let sent = tx_thread.join().expect("transmitter thread panicked");
+    let (received, output) = rx_thread.join().expect("receiver thread panicked");
+    (sent, received, output)
+}
+
+#[test]
+fn loop_back() {
+    for &len in &[1, 127, 128, 129, 512, 1000, 128 * 300] {
+        let input = data(len);
+        let (tx, rx) = pipe();
+        let (sent, received, output) = transfer(input.clone(), tx, rx);
+
+        assert_eq!(sent.expect("transmit failed"), len);
+
+        let padded = (len + PACKET_SIZE - 1) / PACKET_SIZE * PACKET_SIZE;
+        assert_eq!(received.expect("receive failed"), padded);
+        assert_eq!(&output[..len], &input[..]);
+        assert!(output[len..].iter().all(|&b| b == 0));
+    }
+}
+
+#[test]
+fn empty_transfer() {
+    let (tx, rx) = pipe();
+    let (sent, received, output) = transfer(Vec::new(), tx, rx);
+
+    assert_eq!(sent.unwrap(), 0);
+    assert_eq!(received.unwrap(), 0);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn corrupted_payload_is_retransmitted() {
+    let input = data(PACKET_SIZE * 3);
+    let (tx, rx) = pipe();
+
+    // Corrupt a data byte of the first packet and the checksum of the
+    // (retransmitted) first packet's successor.
+    let first_packet_len = 3 + PACKET_SIZE + 1;
+    let tx = Corrupt::new(tx, vec![10, 2 * first_packet_len + first_packet_len - 1]);
+    let (sent, received, output) = transfer(input.clone(), tx, rx);
+
+    assert_eq!(sent.unwrap(), input.len());
+    assert_eq!(received.unwrap(), input.len());
+    assert_eq!(output, input);
+}
+
+#[test]
+fn corrupted_packet_number_cancels() {
+    let (tx, rx) = pipe();
+    // Byte 2 is the complement of the packet number.
+    let tx = Corrupt::new(tx, vec![2]);
+    let (sent, received, _) = transfer(data(PACKET_SIZE), tx, rx);
+
+    assert_eq!(received.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(sent.unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+}
+
+#[test]
+fn gives_up_after_retries() {
+    let (tx, rx) = pipe();
+    // Corrupt the checksum of every attempt at the first packet.
+    let packet_len = 3 + PACKET_SIZE + 1;
+    let offsets = (0..RETRIES).map(|i| i * packet_len + packet_len - 1).collect();
+    let tx = Corrupt::new(tx, offsets);
+    let (sent, received, _) = transfer(data(PACKET_SIZE), tx, rx);
+
+    assert_eq!(sent.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    assert!(received.is_err());
+}
+
+#[test]
+fn receiver_cancel_aborts_transmitter() {
+    let mut script = Script::new(vec![NAK, CAN]);
+    let result = Xmodem::transmit(&data(PACKET_SIZE)[..], &mut script);
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+}
+
+#[test]
+fn transmitter_cancel_aborts_receiver() {
+    let mut script = Script::new(vec![CAN]);
+    let result = Xmodem::receive(&mut script, Vec::new());
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+    assert_eq!(script.output, vec![NAK]);
+}
+
+#[test]
+fn receiver_wire_format() {
+    let payload = data(PACKET_SIZE);
+    let mut input = packet(1, &payload);
+    input.extend_from_slice(&[EOT, EOT]);
+
+    let mut script = Script::new(input);
+    let mut output = Vec::new();
+    let received = Xmodem::receive(&mut script, &mut output).unwrap();
+
+    assert_eq!(received, PACKET_SIZE);
+    assert_eq!(output, payload);
+    assert_eq!(script.output, vec![NAK, ACK, NAK, ACK]);
+}
+
+#[test]
+fn full_output_cancels() {
+    let payload = data(PACKET_SIZE * 2);
+    let mut input = packet(1, &payload[..PACKET_SIZE]);
+    input.extend(packet(2, &payload[PACKET_SIZE..]));
+
+    let mut script = Script::new(input);
+    let mut output = [0u8; PACKET_SIZE];
+    let result = Xmodem::receive(&mut script, &mut output[..]);
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::WriteZero);
+    assert_eq!(&output[..], &payload[..PACKET_SIZE]);
+    assert_eq!(script.output, vec![NAK, ACK, ACK, CAN]);
+}
+
+#[test]
+fn transmitter_wire_format() {
+    let payload = data(PACKET_SIZE);
+    let mut script = Script::new(vec![NAK, ACK, NAK, ACK]);
+    let sent = Xmodem::transmit(&payload[..], &mut script).unwrap();
+
+    let mut expected = packet(1, &payload);
+    expected.extend_from_slice(&[EOT, EOT]);
+    assert_eq!(sent, PACKET_SIZE);
+    assert_eq!(script.output, expected);
+}
+
+#[test]
+fn transmitter_skips_noise_before_nak() {
+    let payload = data(PACKET_SIZE);
+    let mut input = b"boot: waiting for transfer\r\n".to_vec();
+    input.extend_from_slice(&[NAK, ACK, NAK, ACK]);
+
+    let mut script = Script::new(input);
+    let sent = Xmodem::transmit(&payload[..], &mut script).unwrap();
+
+    assert_eq!(sent, PACKET_SIZE);
+    assert_eq!(&script.output[..3], &[SOH, 1, !1]);
+}
+
+#[test]
+fn read_packet_rejects_bad_checksum() {
+    let payload = data(PACKET_SIZE);
+    let mut bad = packet(1, &payload);
+    *bad.last_mut().unwrap() ^= 0xff;
+
+    let mut xmodem = Xmodem::new(Script::new(bad));
+    let mut buf = [0u8; PACKET_SIZE];
+    let err = xmodem.read_packet(&mut buf).unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    assert_eq!(xmodem.inner.output, vec![NAK, NAK]);
+}
+
+#[test]
+fn packet_numbers_wrap() {
+    // 300 packets run the 8-bit packet number past 255 back to 0.
+    let input = data(PACKET_SIZE * 300);
+    let (tx, rx) = pipe();
+    let (sent, received, output) = transfer(input.clone(), tx, rx);
+
+    assert_eq!(sent.unwrap(), input.len());
+    assert_eq!(received.unwrap(), input.len());
+    assert_eq!(output, input);
+}
+
+#[test]
+fn progress_callbacks() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static WAITING: AtomicUsize = AtomicUsize::new(0);
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static PACKETS: AtomicUsize = AtomicUsize::new(0);
+
+    fn progress(p: Progress) {
+        match p {
+            Progress::Waiting => WAITING.fetch_add(1, Ordering::SeqCst),
+            Progress::Started => STARTED.fetch_add(1, Ordering::SeqCst),
+            Progress::Packet(_) => PACKETS.fetch_add(1, Ordering::SeqCst),
+        };
+    }
+
+    let input = data(PACKET_SIZE * 4);
+    let (tx, rx) = pipe();
+    let tx_thread = thread::spawn(move || Xmodem::transmit_with_progress(&input[..], tx, progress));
+    let rx_thread = thread::spawn(move || Xmodem::receive_with_progress(rx, Vec::new(), progress));
+
+    tx_thread.join().unwrap().unwrap();
+    rx_thread.join().unwrap().unwrap();
+
+    assert_eq!(WAITING.load(Ordering::SeqCst), 1);
+    assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+    assert_eq!(PACKETS.load(Ordering::SeqCst), 8);
+}
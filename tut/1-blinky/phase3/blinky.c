@@ -1,12 +1,17 @@
 #define GPIO_BASE (0x3F000000 + 0x200000)
+#define TIMER_BASE (0x3F000000 + 0x3000)
 
 volatile unsigned *GPIO_FSEL1 = (volatile unsigned *)(GPIO_BASE + 0x04);
 volatile unsigned *GPIO_SET0  = (volatile unsigned *)(GPIO_BASE + 0x1C);
 volatile unsigned *GPIO_CLR0  = (volatile unsigned *)(GPIO_BASE + 0x28);
 
+// free-running 1MHz counter; the low word wraps every ~71 minutes
+volatile unsigned *TIMER_CLO  = (volatile unsigned *)(TIMER_BASE + 0x04);
+
 static void spin_sleep_us(unsigned int us) {
-  for (unsigned int i = 0; i < us * 6; i++) {
-    asm volatile("nop");
+  unsigned int start = *TIMER_CLO;
+  // unsigned subtraction stays correct across a wrap of the counter
+  while (*TIMER_CLO - start < us) {
   }
 }
 
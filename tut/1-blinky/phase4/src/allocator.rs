@@ -0,0 +1,106 @@
+//! The kernel heap.
+//!
+//! `ALLOCATOR` is the global allocator behind `Box`, `Vec` and friends. It
+//! manages all the RAM from the end of the kernel image to the end of the
+//! ARM's share of memory, as reported by `bootinfo::memory_map`, using one
+//! of the allocators in the `allocator` crate: the power-of-two bin
+//! allocator by default, or the bump allocator, which never reuses memory,
+//! with the `bump-allocator` feature.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt;
+
+use allocator::LocalAlloc;
+
+#[cfg(not(feature = "bump-allocator"))]
+use allocator::bin::Allocator as Imp;
+#[cfg(feature = "bump-allocator")]
+use allocator::bump::Allocator as Imp;
+
+use crate::bootinfo;
+use crate::mutex::Mutex;
+
+/// The global allocator.
+#[cfg_attr(not(test), global_allocator)]
+pub static ALLOCATOR: Allocator = Allocator::uninitialized();
+
+/// A thread-safe wrapper around the heap's allocator.
+pub struct Allocator(Mutex<Option<Imp>>);
+
+impl Allocator {
+    /// Returns an uninitialized `Allocator`.
+    ///
+    /// The allocator must be initialized by calling `initialize()` before the
+    /// first memory allocation. Failure to do will result in panics.
+    pub const fn uninitialized() -> Allocator {
+        Allocator(Mutex::new(None))
+    }
+
+    /// Initializes the memory allocator with the free memory after the
+    /// kernel image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system's memory map could not be retrieved.
+    pub fn initialize(&self) {
+        let (start, end) = bootinfo::memory_map().expect("failed to find memory map");
+        *self.0.lock() = Some(Imp::new(start, end));
+    }
+
+    /// Runs `f` on the allocator with IRQs masked. Exception handlers
+    /// allocate and free too, e.g. when paging in memory or reaping a
+    /// process, so a process must not be preempted while holding the lock.
+    fn critical<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Imp) -> R,
+    {
+        let masked = aarch64::irq_masked();
+        aarch64::disable_irq();
+
+        let result = f(self.0.lock().as_mut().expect("allocator uninitialized"));
+
+        if !masked {
+            aarch64::enable_irq();
+        }
+        result
+    }
+}
+
+unsafe impl GlobalAlloc for Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.critical(|imp| imp.alloc(layout))
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.critical(|imp| imp.dealloc(ptr, layout))
+    }
+}
+
+impl fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.try_lock() {
+            Some(guard) => match &*guard {
+                Some(imp) => imp.fmt(f),
+                None => f.write_str("allocator uninitialized"),
+            },
+            None => f.write_str("allocator locked"),
+        }
+    }
+}
+
+/// Called when an allocation fails. Reports what was asked for and the
+/// state of the heap, then panics.
+#[cfg(not(test))]
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    kprintln!();
+    kprintln!("out of memory: failed to allocate {} bytes aligned to {}", layout.size(), layout.align());
+    kprintln!("{:?}", ALLOCATOR);
+    panic!("allocation failed")
+}
+
+/// Shell command that shows the state of the heap.
+pub fn heap(_args: &[&str]) -> Result<(), &'static str> {
+    kprintln!("{:?}", ALLOCATOR);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::smp::NCORES;
+
+/// A one-shot rendezvous point for every core.
+///
+/// Like `Mutex`, this avoids atomic read-modify-write instructions, which
+/// don't work until the MMU is on. Each core instead owns a flag that only
+/// it ever sets, and a core waiting on the barrier spins until it sees every
+/// flag set. A flag is never cleared, so a `Barrier` can only be passed once.
+pub struct Barrier {
+    arrived: [AtomicBool; NCORES],
+}
+
+impl Barrier {
+    /// Creates a barrier no core has arrived at yet.
+    pub const fn new() -> Barrier {
+        Barrier {
+            arrived: [
+                AtomicBool::new(false),
+                AtomicBool::new(false),
+                AtomicBool::new(false),
+                AtomicBool::new(false),
+            ],
+        }
+    }
+
+    /// Records that `core` has arrived without waiting for the others.
+    pub fn arrive(&self, core: usize) {
+        self.arrived[core].store(true, Ordering::Release);
+    }
+
+    /// Returns `true` once every core has arrived.
+    pub fn is_complete(&self) -> bool {
+        self.arrived.iter().all(|flag| flag.load(Ordering::Acquire))
+    }
+
+    /// Records that `core` has arrived and blocks until every other core has
+    /// too.
+    pub fn wait(&self, core: usize) {
+        self.arrive(core);
+        while !self.is_complete() {
+            core::sync::atomic::spin_loop_hint();
+        }
+    }
+}
+
+impl Default for Barrier {
+    fn default() -> Barrier {
+        Barrier::new()
+    }
+}
@@ -0,0 +1,129 @@
+//! What the firmware tells the kernel about the board.
+//!
+//! There are two sources. Newer firmware, and QEMU, pass a flattened device
+//! tree whose address `_start` hands to `init`; older firmware leaves an
+//! ATAGS list at 0x100 instead (see `pi::atags`). The device tree is used
+//! when there is one.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use fdt::Fdt;
+use pi::atags::{Atag, Atags};
+use pi::common::IO_BASE;
+
+/// The end of RAM assumed if the firmware doesn't report it: the ARM's share
+/// with the default GPU memory split.
+const DEFAULT_RAM_END: usize = 0x3B40_0000;
+
+/// The address of the device tree blob, or 0 if there is none.
+static DTB: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the device tree blob address the firmware passed in `x0`. Values
+/// that can't be a blob in RAM, like the 0 firmware passes without one, are
+/// ignored.
+pub fn init(dtb: usize) {
+    if dtb != 0 && dtb % 8 == 0 && dtb < IO_BASE {
+        DTB.store(dtb, Ordering::Relaxed);
+    }
+}
+
+/// Returns the device tree the firmware passed, if it passed a valid one.
+pub fn fdt() -> Option<Fdt<'static>> {
+    match DTB.load(Ordering::Relaxed) {
+        0 => None,
+        dtb => unsafe { Fdt::from_addr(dtb).ok() },
+    }
+}
+
+/// Returns the (start address, end address) of the device tree blob.
+fn dtb_range() -> Option<(usize, usize)> {
+    let size = fdt()?.size();
+    let dtb = DTB.load(Ordering::Relaxed);
+    Some((dtb, dtb + size))
+}
+
+/// Returns the first address past the kernel image.
+fn text_end() -> usize {
+    extern "C" {
+        static __text_end: u8;
+    }
+
+    unsafe { &__text_end as *const u8 as usize }
+}
+
+/// Returns the (start address, end address) of the free memory after the
+/// kernel image, or `None` if the RAM the firmware reports ends before it.
+/// If the device tree blob lies in that memory, only the larger part on
+/// either side of it is returned, so that it isn't overwritten.
+pub fn memory_map() -> Option<(usize, usize)> {
+    let reported = match fdt() {
+        Some(fdt) => fdt.memory().map(|(base, size)| (base + size) as usize),
+        None => Atags::get().memory_map().map(|(_, end)| end),
+    };
+
+    let (mut start, mut end) = (text_end(), reported.unwrap_or(DEFAULT_RAM_END));
+    if let Some((dtb_start, dtb_end)) = dtb_range() {
+        if dtb_end > start && dtb_start < end {
+            if dtb_start.saturating_sub(start) >= end.saturating_sub(dtb_end) {
+                end = dtb_start;
+            } else {
+                start = dtb_end;
+            }
+        }
+    }
+
+    if start >= end {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Returns the kernel command line, from `cmdline.txt` on the boot
+/// partition, if the firmware passed one.
+pub fn cmdline() -> Option<&'static str> {
+    match fdt() {
+        Some(fdt) => fdt.bootargs(),
+        None => Atags::get().cmdline(),
+    }
+}
+
+/// Returns the physical base address of the first device the device tree
+/// lists as compatible with `compatible`, or `None` if there is no device
+/// tree or no such device.
+pub fn peripheral_base(compatible: &str) -> Option<usize> {
+    fdt()?.peripheral_base(compatible).map(|base| base as usize)
+}
+
+/// Shell command that lists the ATAGS the firmware passed.
+pub fn atags(_args: &[&str]) -> Result<(), &'static str> {
+    for atag in Atags::get() {
+        match atag {
+            Atag::Core(core) => kprintln!("  core: {:?}", core),
+            Atag::Mem(mem) => kprintln!("  mem:  {:#010x}..{:#010x}", mem.start, mem.start as u64 + mem.size as u64),
+            Atag::Cmd(cmd) => kprintln!("  cmd:  {}", cmd),
+            Atag::Unknown(tag) => kprintln!("  unknown tag {:#010x}", tag),
+        }
+    }
+    Ok(())
+}
+
+/// Shell command that summarizes the device tree, or looks up the base
+/// address of the device compatible with its argument.
+pub fn fdt_command(args: &[&str]) -> Result<(), &'static str> {
+    let fdt = fdt().ok_or("no device tree")?;
+    match args {
+        [_] => {
+            kprintln!("  blob:     {:#x}", DTB.load(Ordering::Relaxed));
+            if let Some((base, size)) = fdt.memory() {
+                kprintln!("  memory:   {:#010x}..{:#010x}", base, base + size);
+            }
+            kprintln!("  bootargs: {}", fdt.bootargs().unwrap_or(""));
+        }
+        [_, compatible] => match fdt.peripheral_base(compatible) {
+            Some(base) => kprintln!("  {}: {:#010x}", compatible, base),
+            None => return Err("no such device"),
+        },
+        _ => return Err("usage: fdt [compatible]"),
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use core::fmt;
+
+use pi::interrupt::{Controller, Interrupt};
+use pi::uart::MiniUart;
+use shim::io;
+
+use crate::mutex::{Mutex, MutexGuard};
+
+/// A global singleton allowing read/write access to the console.
+pub struct Console {
+    inner: Option<MiniUart>,
+}
+
+impl Console {
+    /// Creates a new instance of `Console`. The underlying device is only
+    /// initialized on first use.
+    const fn new() -> Console {
+        Console { inner: None }
+    }
+
+    /// Returns a mutable borrow to the inner `MiniUart`, initializing it as
+    /// needed.
+    fn inner(&mut self) -> &mut MiniUart {
+        self.inner.get_or_insert_with(|| {
+            let mut uart = MiniUart::new();
+            uart.set_rx_interrupt(true);
+            uart
+        })
+    }
+
+    /// Reads a byte from the UART device, blocking until a byte is available.
+    pub fn read_byte(&mut self) -> u8 {
+        self.inner().read_byte()
+    }
+
+    /// Reads a byte from the UART device if one is ready.
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        let uart = self.inner();
+        if uart.has_byte() {
+            Some(uart.read_byte())
+        } else {
+            None
+        }
+    }
+
+    /// Writes the byte `byte` to the UART device. A `\n` is sent as `\r\n`
+    /// so terminals return to the start of the line.
+    pub fn write_byte(&mut self, byte: u8) {
+        if byte == b'\n' {
+            self.inner().write_byte(b'\r');
+        }
+        self.inner().write_byte(byte);
+    }
+}
+
+impl io::Read for Console {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner().read(buf)
+    }
+}
+
+impl io::Write for Console {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.write_byte(byte);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Global `Console` singleton.
+pub static CONSOLE: Mutex<Console> = Mutex::new(Console::new());
+
+/// Somewhere to report a failure that may happen while the console is
+/// locked, say in the middle of a `kprint!`: the console if it is free, or
+/// else a newly initialized mini UART. Waiting for the console could
+/// deadlock instead.
+pub enum Output<'a> {
+    Console(MutexGuard<'a, Console>),
+    Uart(MiniUart),
+}
+
+impl<'a> Output<'a> {
+    /// Returns the console if it is free, or else a new mini UART.
+    pub fn new() -> Output<'a> {
+        match CONSOLE.try_lock() {
+            Some(console) => Output::Console(console),
+            None => Output::Uart(MiniUart::new()),
+        }
+    }
+}
+
+impl<'a> fmt::Write for Output<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            match self {
+                Output::Console(console) => console.write_byte(byte),
+                Output::Uart(uart) => {
+                    if byte == b'\n' {
+                        uart.write_byte(b'\r');
+                    }
+                    uart.write_byte(byte);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a byte from the console, blocking until a byte is available.
+///
+/// Unlike `Console::read_byte`, the console is only locked while checking
+/// for a byte, so other processes can write to it meanwhile. In between, the
+/// core sleeps in `wfi`: the AUX interrupt is enabled to wake it, and
+/// `irq::aux` disables it again once it has.
+pub fn read_byte() -> u8 {
+    let masked = aarch64::irq_masked();
+
+    // Check and sleep with IRQs masked: a byte that arrives in between still
+    // wakes `wfi` rather than being handled, and missed, before it.
+    aarch64::disable_irq();
+    let byte = loop {
+        if let Some(byte) = CONSOLE.lock().try_read_byte() {
+            break byte;
+        }
+
+        Controller::new().enable(Interrupt::AUX);
+        aarch64::wfi();
+        if !masked {
+            // Briefly unmask to take whatever woke us.
+            aarch64::enable_irq();
+            aarch64::disable_irq();
+        }
+    };
+
+    if !masked {
+        aarch64::enable_irq();
+    }
+    byte
+}
+
+/// Internal function called by the `kprint[ln]!` macros.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+
+    let mut console = CONSOLE.lock();
+    let _ = console.write_fmt(args);
+}
+
+/// Like `println!`, but for kernel-space.
+#[macro_export]
+macro_rules! kprintln {
+    () => (kprint!("\n"));
+    ($fmt:expr) => (kprint!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (kprint!(concat!($fmt, "\n"), $($arg)*));
+}
+
+/// Like `print!`, but for kernel-space.
+#[macro_export]
+macro_rules! kprint {
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
+}
@@ -0,0 +1,114 @@
+//! A breakpoint debugger on the serial console.
+//!
+//! A `brk #n` instruction anywhere in the kernel traps into `debug`, which
+//! runs a shell with the interrupted context's trap frame at hand. Besides
+//! its own commands, the debugger passes everything else through to the
+//! kernel shell, so `peek`, `poke` and friends work as usual.
+
+use crate::shell::{self, MAX_ARGS, MAX_LINE_LEN};
+use crate::traps::TrapFrame;
+
+/// The width of a `brk` instruction, which ELR points at when it traps.
+const BRK_LEN: u64 = 4;
+
+/// The number of bytes `mem` dumps when no length is given.
+const DEFAULT_DUMP_LEN: usize = 64;
+
+/// Commands only the debugger understands.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("regs", "", "dump the saved registers"),
+    ("mem", "<addr> [len]", "hex dump memory"),
+    ("continue", "", "resume after the breakpoint"),
+    ("help", "", "list the available commands"),
+];
+
+/// Runs the debugger shell for the `brk #imm` whose trap frame is `tf`.
+/// Returns, with ELR stepped past the `brk`, when the user types `continue`.
+pub fn debug(imm: u16, tf: &mut TrapFrame) {
+    kprintln!();
+    kprintln!("breakpoint #{} at {:#x}", imm, tf.elr);
+
+    let mut line_buf = [0u8; MAX_LINE_LEN];
+    loop {
+        let mut args_buf = [""; MAX_ARGS];
+        let args = match shell::prompt("(debug) ", &mut line_buf, &mut args_buf) {
+            Some(args) => args,
+            None => continue,
+        };
+
+        let result = match args {
+            ["continue"] | ["c"] => {
+                tf.elr += BRK_LEN;
+                return;
+            }
+            ["regs"] => regs(tf),
+            ["mem", ..] => mem(args),
+            ["help"] => help(),
+            _ => {
+                shell::run(args);
+                Ok(())
+            }
+        };
+
+        if let Err(msg) = result {
+            kprintln!("{}: {}", args[0], msg);
+        }
+    }
+}
+
+fn regs(tf: &TrapFrame) -> Result<(), &'static str> {
+    kprintln!("elr   {:#018x}  spsr  {:#018x}", tf.elr, tf.spsr);
+    kprintln!("sp    {:#018x}  tpidr {:#018x}", tf.sp, tf.tpidr);
+    for (i, pair) in tf.x.chunks(2).enumerate() {
+        match pair {
+            [a, b] => kprintln!("x{:<4} {:#018x}  x{:<4} {:#018x}", 2 * i, a, 2 * i + 1, b),
+            [a] => kprintln!("x{:<4} {:#018x}", 2 * i, a),
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+/// Prints `len` bytes starting at `addr`, 16 to a line with an ASCII column.
+fn mem(args: &[&str]) -> Result<(), &'static str> {
+    let (addr, len) = match args {
+        [_, addr] => (shell::parse_usize(addr)?, DEFAULT_DUMP_LEN),
+        [_, addr, len] => (shell::parse_usize(addr)?, shell::parse_usize(len)?),
+        _ => return Err("usage: mem <addr> [len]"),
+    };
+
+    let end = addr.saturating_add(len);
+    for line in (addr..end).step_by(16) {
+        let line_end = core::cmp::min(line.saturating_add(16), end);
+        let bytes = unsafe { core::slice::from_raw_parts(line as *const u8, line_end - line) };
+
+        kprint!("{:#010x}: ", line);
+        for i in 0..16 {
+            match bytes.get(i) {
+                Some(byte) => kprint!("{:02x} ", byte),
+                None => kprint!("   "),
+            }
+        }
+        kprint!(" ");
+        for &byte in bytes {
+            let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+            kprint!("{}", c);
+        }
+        kprintln!();
+    }
+    Ok(())
+}
+
+fn help() -> Result<(), &'static str> {
+    for (name, usage, help) in COMMANDS {
+        kprintln!("  {:<8} {:<16} {}", name, usage, help);
+    }
+    kprintln!("  any other command runs in the kernel shell");
+    Ok(())
+}
+
+/// Shell command that traps into the debugger.
+pub fn brk(_args: &[&str]) -> Result<(), &'static str> {
+    unsafe { asm!("brk #0" :::: "volatile") };
+    Ok(())
+}
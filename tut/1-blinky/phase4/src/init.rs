@@ -1,9 +1,12 @@
-use core::mem::zeroed;
-use core::ptr::write_volatile;
+use volatile::prelude::*;
+use volatile::Volatile;
 
 mod panic;
 
-use crate::kmain;
+use crate::allocator::ALLOCATOR;
+use crate::bootinfo;
+use crate::vm;
+use crate::{kmain, kmain_secondary};
 
 global_asm!(include_str!("init/init.s"));
 
@@ -17,13 +20,24 @@ unsafe fn zeros_bss() {
     let end: *mut u64 = &mut __bss_end;
 
     while iter < end {
-        write_volatile(iter, zeroed());
+        Volatile::from_ptr(iter).write(0);
         iter = iter.add(1);
     }
 }
 
+/// Core 0's entry point from `_start`. `dtb` is the address of the device
+/// tree blob the firmware passed, or 0.
 #[no_mangle]
-unsafe fn kinit() -> ! {
+unsafe fn kinit(dtb: usize) -> ! {
     zeros_bss();
+    bootinfo::init(dtb);
+    ALLOCATOR.initialize();
+    vm::init();
     kmain();
-}
\ No newline at end of file
+}
+
+#[no_mangle]
+unsafe fn kinit_secondary(core: usize) -> ! {
+    vm::enable();
+    kmain_secondary(core);
+}
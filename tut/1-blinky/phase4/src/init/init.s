@@ -1,23 +1,138 @@
 .section .text.init
 
 .global _start
+.global _start_secondary
 
 _start:
-    // read cpu affinity, start core 0, halt rest
+    // keep x0 for kinit: the firmware passes the device tree blob's
+    // address there, if it passes one
+    mov     x19, x0
+
+    // read cpu affinity, start core 0, park the rest
     mrs     x1, mpidr_el1
     and     x1, x1, #3
     cbz     x1, 2f
 
+    // core affinity != 0. the firmware normally keeps these cores in its
+    // own stub, but QEMU starts every core here when booting an ELF, so do
+    // what the stub does: wait for an address in this core's spin table
+    // mailbox at 0xd8 + 8 * core, then jump to it (see smp.rs)
+    mov     x2, #0xd8
+    add     x2, x2, x1, lsl #3
 1:
-    // core affinity != 0, halt it
     wfe
-    b       1b
+    ldr     x3, [x2]
+    cbz     x3, 1b
+    br      x3
 
 2:
+    // drop to EL1 before touching the stack: SP_EL1 isn't set up yet
+    bl      switch_to_el1
+
     // set the stack to start before our boot code
     adr     x1, _start
     mov     sp, x1
 
-    // jump to kinit, which shouldn't return. halt if it does
+    // jump to kinit(dtb), which shouldn't return. halt if it does
+    mov     x0, x19
     bl      kinit
-    b       1b
+5:
+    wfe
+    b       5b
+
+// cores 1-3 jump here once released through the spin table (see smp.rs)
+_start_secondary:
+    bl      switch_to_el1
+
+    // core id is kinit_secondary's argument
+    mrs     x0, mpidr_el1
+    and     x0, x0, #3
+
+    // core n's stack tops out at the end of its slot in .stacks:
+    // __core_stacks_beg + n * __core_stack_size
+    ldr     x1, =__core_stacks_beg
+    ldr     x2, =__core_stack_size
+    madd    x1, x0, x2, x1
+    mov     sp, x1
+
+    // jump to kinit_secondary, which shouldn't return. halt if it does
+    bl      kinit_secondary
+    b       5b
+
+// Returns to the caller at EL1h, stepping down through EL3 and EL2 as
+// needed. The firmware hands us EL2 on hardware; QEMU may start us at EL3.
+// Also installs the exception vectors. Only clobbers x0 and x2; doesn't use
+// the stack.
+switch_to_el1:
+    // read the current exception level into x0 (ref: C5.2.1)
+    mrs     x0, CurrentEL
+    and     x0, x0, #0b1100
+    lsr     x0, x0, #2
+
+    cmp     x0, #3
+    bne     3f
+
+    // EL3: run EL2 in non-secure AArch64 with HVC and SMC disabled
+    // (NS, RES1 bits 4 and 5, SMD, HCE, RW; A53: 4.3.42)
+    mov     x2, #0x5b1
+    msr     SCR_EL3, x2
+
+    // don't trap FP/SIMD to EL3 (A53: 4.3.37)
+    msr     CPTR_EL3, xzr
+
+    // eret into EL2h with DAIF masked, continuing below (ref: C5.2.20)
+    mov     x2, #0x3c9
+    msr     SPSR_EL3, x2
+    adr     x2, 3f
+    msr     ELR_EL3, x2
+    eret
+
+3:
+    mrs     x0, CurrentEL
+    and     x0, x0, #0b1100
+    lsr     x0, x0, #2
+
+    cmp     x0, #2
+    bne     4f
+
+    // EL2: let EL1 and EL0 use the physical timer and counter, with no
+    // virtual offset (ref: D7.5.2, D7.5.13)
+    mrs     x2, CNTHCTL_EL2
+    orr     x2, x2, #0b11
+    msr     CNTHCTL_EL2, x2
+    msr     CNTVOFF_EL2, xzr
+
+    // run EL1 in AArch64 (RW, and SWIO which is RES1 on the A53; A53: 4.3.36)
+    mov     x2, #(1 << 31)
+    orr     x2, x2, #(1 << 1)
+    msr     HCR_EL2, x2
+
+    // don't trap FP/SIMD to EL2 (A53: 4.3.34)
+    msr     CPTR_EL2, xzr
+
+    // put SCTLR_EL1 in a known state: MMU and caches off, RES1 bits set
+    // (RES1: 11, 20, 22, 23, 28, 29; A53: 4.3.30)
+    mov     x2, #0x0800
+    movk    x2, #0x30d0, lsl #16
+    msr     SCTLR_EL1, x2
+
+    // eret into EL1h with DAIF masked, continuing below (ref: C5.2.19)
+    mov     x2, #0x3c5
+    msr     SPSR_EL2, x2
+    adr     x2, 4f
+    msr     ELR_EL2, x2
+    eret
+
+4:
+    // EL1: don't trap FP/SIMD at EL1 or EL0 (A53: 4.3.34). x30 survived
+    // the erets, so this returns to our caller at EL1
+    mrs     x2, CPACR_EL1
+    orr     x2, x2, #(0b11 << 20)
+    msr     CPACR_EL1, x2
+
+    // route exceptions to the vector table in traps/vectors.s
+    ldr     x2, =vectors
+    msr     VBAR_EL1, x2
+    isb
+
+    ret
@@ -1,6 +1,81 @@
+use core::fmt::{self, Write};
 use core::panic::PanicInfo;
 
+use crate::console::Output;
+
+/// The maximum number of frames printed in a backtrace.
+const MAX_BACKTRACE_DEPTH: usize = 16;
+
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    loop {}
+fn panic(info: &PanicInfo) -> ! {
+    let mut out = Output::new();
+    let _ = report(&mut out, info);
+    halt(&mut out)
+}
+
+/// Writes the panic message, its location and a backtrace to `out`.
+fn report(out: &mut Output, info: &PanicInfo) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "---------- PANIC ----------")?;
+    writeln!(out)?;
+
+    if let Some(location) = info.location() {
+        writeln!(out, "FILE: {}", location.file())?;
+        writeln!(out, "LINE: {}", location.line())?;
+        writeln!(out, "COL: {}", location.column())?;
+        writeln!(out)?;
+    }
+
+    match info.message() {
+        Some(message) => writeln!(out, "{}", message)?,
+        None => writeln!(out, "(no message)")?,
+    }
+    writeln!(out)?;
+
+    backtrace(out, aarch64::fp())
+}
+
+/// Prints the return address of each frame by following the AArch64 frame
+/// record chain starting at frame pointer `fp`.
+///
+/// Each frame record is a pair `[previous x29, saved x30]` pointed to by
+/// `x29`. The walk stops at a null or misaligned frame pointer, or one that
+/// doesn't move up the stack, so a corrupted chain can't send us wandering
+/// through memory.
+fn backtrace(out: &mut Output, mut fp: usize) -> fmt::Result {
+    writeln!(out, "BACKTRACE:")?;
+
+    for depth in 0..MAX_BACKTRACE_DEPTH {
+        if fp == 0 || fp % 16 != 0 {
+            return Ok(());
+        }
+
+        let record = fp as *const usize;
+        let (prev_fp, lr) = unsafe { (record.read_volatile(), record.add(1).read_volatile()) };
+        if lr == 0 {
+            return Ok(());
+        }
+
+        writeln!(out, "  #{:<2} {:#018x}", depth, lr)?;
+
+        if prev_fp <= fp {
+            return Ok(());
+        }
+        fp = prev_fp;
+    }
+
+    writeln!(out, "  ...")
+}
+
+/// Stops this core for good, or resets the board when built with the
+/// `reboot-on-panic` feature.
+fn halt(out: &mut Output) -> ! {
+    if cfg!(feature = "reboot-on-panic") {
+        let _ = writeln!(out, "rebooting...");
+        pi::pm::reset();
+    }
+
+    loop {
+        aarch64::wfe();
+    }
 }
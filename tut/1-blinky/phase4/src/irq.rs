@@ -0,0 +1,60 @@
+//! The kernel's interrupt sources.
+//!
+//! The IRQ vector hands every interrupt to `Controller::dispatch`, which calls
+//! the handlers registered here.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use pi::interrupt::{Controller, Interrupt};
+use pi::timer::{self, Channel};
+
+use crate::scheduler;
+
+/// The period of the timer tick.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// Counts of interrupts taken, for `irqs`. Each is only written by its own
+/// handler on core 0, so a plain load and store is enough to update it.
+static TIMER_TICKS: AtomicUsize = AtomicUsize::new(0);
+static AUX_WAKEUPS: AtomicUsize = AtomicUsize::new(0);
+
+fn count(counter: &AtomicUsize) {
+    counter.store(counter.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+/// Registers the kernel's interrupt handlers, starts the timer tick and
+/// unmasks IRQs on this core.
+pub fn init() {
+    let mut controller = Controller::new();
+    controller.register(Interrupt::TIMER1, tick);
+    controller.register(Interrupt::AUX, aux);
+
+    timer::tick_in(Channel::C1, TICK);
+    aarch64::enable_irq();
+}
+
+/// Timer channel 1: re-arms the timer for the next tick, which also
+/// acknowledges this one, and asks for the running process to be switched
+/// out.
+fn tick(_: Interrupt) {
+    timer::tick_in(Channel::C1, TICK);
+    count(&TIMER_TICKS);
+    scheduler::request_preemption();
+}
+
+/// AUX: the mini UART has a byte ready. The byte itself is left for
+/// `console::read_byte`, which enabled this interrupt to be woken from `wfi`;
+/// the interrupt is disabled until it waits again since it stays raised for
+/// as long as the byte is unread.
+fn aux(int: Interrupt) {
+    Controller::new().disable(int);
+    count(&AUX_WAKEUPS);
+}
+
+/// Shell command that reports how many interrupts have been taken.
+pub fn irqs(_args: &[&str]) -> Result<(), &'static str> {
+    kprintln!("timer1: {}", TIMER_TICKS.load(Ordering::Relaxed));
+    kprintln!("aux:    {}", AUX_WAKEUPS.load(Ordering::Relaxed));
+    Ok(())
+}
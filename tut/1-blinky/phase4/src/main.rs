@@ -1,27 +1,183 @@
+#![feature(alloc_error_handler)]
 #![feature(asm)]
 #![feature(global_asm)]
+#![feature(panic_info_message)]
 
 #![cfg_attr(not(test), no_std)]
 #![cfg_attr(not(test), no_main)]
 
+extern crate alloc;
+
+#[macro_use]
+pub mod console;
+pub mod allocator;
+pub mod barrier;
+pub mod bootinfo;
+pub mod debugger;
+pub mod irq;
+pub mod mutex;
+pub mod process;
+pub mod scheduler;
+pub mod shell;
+pub mod smp;
+pub mod traps;
+pub mod vm;
+
 #[cfg(not(test))]
 mod init;
 
-const GPIO_BASE: usize = 0x3F000000 + 0x200000;
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use kernel_api::syscall;
+use pi::gpio::Gpio;
+use pi::timer::spin_sleep;
+
+use process::Process;
+use scheduler::SCHEDULER;
+use shell::{parse_usize, Command};
+
+/// The GPIO pin the LED is wired to.
+const LED_PIN: u8 = 16;
+
+/// How long the LED stays on, and then off, in each blink.
+const BLINK_PERIOD: Duration = Duration::from_millis(500);
+
+/// Blinks the LED `count` times, once by default.
+fn blink(args: &[&str]) -> Result<(), &'static str> {
+    let count = match args {
+        [_] => 1,
+        [_, count] => parse_usize(count)?,
+        _ => return Err("usage: blink [count]"),
+    };
+
+    let mut led = Gpio::new(LED_PIN).into_output();
+    for _ in 0..count {
+        led.set();
+        spin_sleep(BLINK_PERIOD);
+        led.clear();
+        spin_sleep(BLINK_PERIOD);
+    }
+    Ok(())
+}
+
+fn kmain() -> ! {
+    shell::register(Command {
+        name: "atags",
+        usage: "",
+        help: "list the boot information from the firmware",
+        run: bootinfo::atags,
+    })
+    .expect("failed to register `atags`");
+
+    shell::register(Command {
+        name: "blink",
+        usage: "[count]",
+        help: "blink the LED on GPIO 16",
+        run: blink,
+    })
+    .expect("failed to register `blink`");
+
+    shell::register(Command {
+        name: "brk",
+        usage: "",
+        help: "break into the debugger",
+        run: debugger::brk,
+    })
+    .expect("failed to register `brk`");
+
+    shell::register(Command {
+        name: "fdt",
+        usage: "[compatible]",
+        help: "show the device tree, or find a device's base address",
+        run: bootinfo::fdt_command,
+    })
+    .expect("failed to register `fdt`");
+
+    shell::register(Command {
+        name: "heap",
+        usage: "",
+        help: "show the state of the kernel heap",
+        run: allocator::heap,
+    })
+    .expect("failed to register `heap`");
 
-const GPIO_FSEL1: *mut u32 = (GPIO_BASE + 0x04) as *mut u32;
-const GPIO_SET0: *mut u32 = (GPIO_BASE + 0x1C) as *mut u32;
-const GPIO_CLR0: *mut u32 = (GPIO_BASE + 0x28) as *mut u32;
+    shell::register(Command {
+        name: "irqs",
+        usage: "",
+        help: "count the interrupts taken",
+        run: irq::irqs,
+    })
+    .expect("failed to register `irqs`");
 
-#[inline(never)]
-fn spin_sleep_ms(ms: usize) {
-    for _ in 0..(ms * 6000) {
-        unsafe { asm!("nop" :::: "volatile"); }
+    shell::register(Command {
+        name: "ps",
+        usage: "",
+        help: "list the processes",
+        run: scheduler::ps,
+    })
+    .expect("failed to register `ps`");
+
+    smp::start_secondary_cores();
+    smp::STARTED.wait(0);
+    kprintln!("{} cores online at EL{}", smp::NCORES, aarch64::current_el());
+    if let Some(cmdline) = bootinfo::cmdline() {
+        kprintln!("command line: {}", cmdline);
+    }
+
+    irq::init();
+
+    let shell = Process::kernel("shell", run_shell).expect("no stack for the shell");
+    let heartbeat = Process::kernel("heartbeat", heartbeat).expect("no stack for heartbeat");
+    let hello = Process::user("hello", hello).expect("no memory for hello");
+    SCHEDULER.add(shell).expect("process table full");
+    SCHEDULER.add(heartbeat).expect("process table full");
+    SCHEDULER.add(hello).expect("process table full");
+    SCHEDULER.start()
+}
+
+/// Runs the shell as a kernel process.
+fn run_shell() -> ! {
+    shell::shell("> ")
+}
+
+/// Blinks the LED forever, time-sliced with the shell. It sleeps through
+/// the kernel rather than spinning, so it only takes the CPU to toggle the
+/// LED. The GPIO registers are only mapped for EL1, so this is a kernel
+/// process.
+fn heartbeat() -> ! {
+    let mut led = Gpio::new(LED_PIN).into_output();
+    loop {
+        led.set();
+        let _ = syscall::sleep(BLINK_PERIOD);
+        led.clear();
+        let _ = syscall::sleep(BLINK_PERIOD);
     }
 }
 
-unsafe fn kmain() -> ! {
-    // FIXME: STEP 1: Set GPIO Pin 16 as output.
-    // FIXME: STEP 2: Continuously set and clear GPIO 16.
-    loop {}
+/// Writes to the console through the `write` system call.
+struct SyscallWriter;
+
+impl fmt::Write for SyscallWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(syscall::write);
+        Ok(())
+    }
+}
+
+/// Greets the console from EL0 and exits. Its stack is in its own user
+/// region, so its first push faults in a page.
+fn hello() -> ! {
+    let _ = writeln!(SyscallWriter, "hello from user process {}", syscall::getpid());
+    syscall::exit()
+}
+
+/// Entry point for cores 1-3 once `smp::start_secondary_cores` releases
+/// them. The console lock isn't safe to share between cores yet, so they
+/// only report in and then park.
+fn kmain_secondary(core: usize) -> ! {
+    smp::STARTED.wait(core);
+    loop {
+        aarch64::wfe();
+    }
 }
@@ -0,0 +1,109 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A mutual exclusion primitive for protecting shared kernel state.
+///
+/// Once the MMU is on, the lock is taken with an atomic compare-and-swap and
+/// is safe to share between cores. Before then, exclusive load/store
+/// instructions don't work, so it is taken with a plain load and store; only
+/// core 0 runs kernel code at that point. Either way IRQs are masked while
+/// taking the lock so that a timer tick can't switch to another process in
+/// between.
+#[repr(align(32))]
+pub struct Mutex<T> {
+    data: UnsafeCell<T>,
+    lock: AtomicBool,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// An RAII guard for a locked `Mutex`. The lock is released when the guard
+/// is dropped.
+pub struct MutexGuard<'a, T: 'a> {
+    lock: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Creates a new, unlocked mutex holding `val`.
+    pub const fn new(val: T) -> Mutex<T> {
+        Mutex {
+            lock: AtomicBool::new(false),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Attempts to acquire this lock without blocking. Returns `None` if the
+    /// lock is currently held.
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let masked = aarch64::irq_masked();
+        aarch64::disable_irq();
+
+        let acquired = if aarch64::is_mmu_enabled() {
+            self.lock
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        } else {
+            let free = !self.lock.load(Ordering::Relaxed);
+            if free {
+                self.lock.store(true, Ordering::Relaxed);
+            }
+            free
+        };
+
+        if !masked {
+            aarch64::enable_irq();
+        }
+
+        if acquired {
+            Some(MutexGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires this lock, spinning until it is available.
+    #[inline(never)]
+    pub fn lock(&self) -> MutexGuard<T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+        }
+    }
+
+    fn unlock(&self) {
+        self.lock.store(false, Ordering::Release);
+    }
+}
+
+impl<'a, T: 'a> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.unlock()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
+            None => f.debug_struct("Mutex").field("data", &"<locked>").finish(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+mod stack;
+mod state;
+
+pub use self::stack::{Stack, MAX_STACKS, STACK_SIZE};
+pub use self::state::{EventPoll, State};
+
+use crate::traps::TrapFrame;
+use crate::vm::{self, UserPageTable, USER_STACK_TOP};
+
+/// Type alias for the type of a process ID.
+pub type Id = u64;
+
+/// `SPSR` for a process running at EL0 with its own `SP_EL0` and nothing
+/// masked.
+const SPSR_EL0T: u64 = 0b0000;
+
+/// `SPSR` for a process running at EL1 on `SP_EL0` with nothing masked.
+const SPSR_EL1T: u64 = 0b0100;
+
+/// A structure that represents the complete state of a process.
+#[derive(Debug)]
+pub struct Process {
+    /// A name for display purposes.
+    pub name: &'static str,
+    /// The saved trap frame of a process, restored when it is scheduled.
+    pub context: TrapFrame,
+    /// The stack of a kernel process. User processes have theirs in their
+    /// own address space instead.
+    pub stack: Option<Stack>,
+    /// The translation table of a user process. Kernel processes run on the
+    /// kernel's.
+    pub vmap: Option<UserPageTable>,
+    /// The scheduling state of the process.
+    pub state: State,
+}
+
+impl Process {
+    /// Creates a process that runs `entry` at EL0 in its own address space.
+    /// Its stack starts at `USER_STACK_TOP` and, like the rest of its user
+    /// region, is paged in as it is touched. Returns `None` if there is no
+    /// memory for its translation table.
+    pub fn user(name: &'static str, entry: fn() -> !) -> Option<Process> {
+        let vmap = UserPageTable::new()?;
+
+        Some(Process {
+            name,
+            context: Process::context(entry, SPSR_EL0T, USER_STACK_TOP, vmap.baddr()),
+            stack: None,
+            vmap: Some(vmap),
+            state: State::Ready,
+        })
+    }
+
+    /// Creates a process that runs `entry` at EL1, so with access to the
+    /// kernel's privileged operations, on a fresh stack. Returns `None` if no
+    /// stack is free.
+    pub fn kernel(name: &'static str, entry: fn() -> !) -> Option<Process> {
+        let stack = Stack::new()?;
+
+        Some(Process {
+            name,
+            context: Process::context(entry, SPSR_EL1T, stack.top(), vm::kernel_baddr()),
+            stack: Some(stack),
+            vmap: None,
+            state: State::Ready,
+        })
+    }
+
+    /// Returns the trap frame a process starts from: the first `eret` into
+    /// the process "returns" to `entry` with an empty stack at `sp`,
+    /// translating through the table at `ttbr0`.
+    fn context(entry: fn() -> !, spsr: u64, sp: usize, ttbr0: u64) -> TrapFrame {
+        TrapFrame {
+            elr: entry as usize as u64,
+            spsr,
+            sp: sp as u64,
+            ttbr0,
+            ..TrapFrame::default()
+        }
+    }
+
+    /// Returns the ID of this process, which the scheduler keeps in its
+    /// `TPIDR_EL0`.
+    pub fn id(&self) -> Id {
+        self.context.tpidr
+    }
+
+    /// Handles a translation fault at `addr` by mapping a fresh page there
+    /// if it lies in the process's user region. Returns `false` if the fault
+    /// can't be resolved.
+    pub fn page_fault(&mut self, addr: usize) -> bool {
+        match &mut self.vmap {
+            Some(vmap) => vmap.map_on_demand(addr),
+            None => false,
+        }
+    }
+
+    /// Returns `true` if this process is ready to be scheduled.
+    ///
+    /// This function returns `true` only if one of the following holds:
+    ///
+    ///   * The state is currently `Ready`.
+    ///
+    ///   * An event being waited for has arrived, in which case the state is
+    ///     changed to `Ready`.
+    pub fn is_ready(&mut self) -> bool {
+        let ready = match self.state {
+            State::Ready => true,
+            State::Waiting(event) => (event.poll)(self, event.data),
+            State::Running | State::Dead => false,
+        };
+
+        if ready {
+            self.state = State::Ready;
+        }
+        ready
+    }
+}
@@ -0,0 +1,60 @@
+use core::cell::UnsafeCell;
+
+use crate::mutex::Mutex;
+
+/// The size of every process stack.
+pub const STACK_SIZE: usize = 32 * 1024;
+
+/// The number of process stacks, and so the most processes that can exist
+/// at once.
+pub const MAX_STACKS: usize = 8;
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct Slot([u8; STACK_SIZE]);
+
+/// The memory for every stack, handed out a slot at a time by `Stack::new`.
+struct Slots(UnsafeCell<[Slot; MAX_STACKS]>);
+
+// Each slot is only ever accessed through the one `Stack` that owns it.
+unsafe impl Sync for Slots {}
+
+static SLOTS: Slots = Slots(UnsafeCell::new([Slot([0; STACK_SIZE]); MAX_STACKS]));
+
+/// Which slots are owned by a `Stack`.
+static IN_USE: Mutex<[bool; MAX_STACKS]> = Mutex::new([false; MAX_STACKS]);
+
+/// A process stack: one of a fixed pool of `STACK_SIZE`-byte, 16-byte
+/// aligned regions. The slot is returned to the pool when the `Stack` is
+/// dropped.
+#[derive(Debug)]
+pub struct Stack {
+    slot: usize,
+}
+
+impl Stack {
+    /// Takes a free stack from the pool, or returns `None` if there is none.
+    pub fn new() -> Option<Stack> {
+        let mut in_use = IN_USE.lock();
+        let slot = in_use.iter().position(|used| !used)?;
+        in_use[slot] = true;
+        Some(Stack { slot })
+    }
+
+    /// Returns the lowest address of the stack.
+    pub fn bottom(&self) -> usize {
+        unsafe { (*SLOTS.0.get())[self.slot].0.as_ptr() as usize }
+    }
+
+    /// Returns the address one past the highest address of the stack, which
+    /// is where a stack pointer starts.
+    pub fn top(&self) -> usize {
+        self.bottom() + STACK_SIZE
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        IN_USE.lock()[self.slot] = false;
+    }
+}
@@ -0,0 +1,38 @@
+use core::fmt;
+
+use crate::process::Process;
+
+/// A condition a waiting process is blocked on. `poll(process, data)`
+/// returns `true` once the process can run again; `data` carries whatever the
+/// condition needs, such as a deadline. The poll function may update the
+/// process's trap frame, e.g. to set a system call's return value.
+#[derive(Clone, Copy)]
+pub struct EventPoll {
+    pub poll: fn(&mut Process, u64) -> bool,
+    pub data: u64,
+}
+
+/// The scheduling state of a process.
+#[derive(Clone, Copy)]
+pub enum State {
+    /// The process is ready to be scheduled.
+    Ready,
+    /// The process is waiting on an event to occur before it can be
+    /// scheduled.
+    Waiting(EventPoll),
+    /// The process is currently running.
+    Running,
+    /// The process is currently dead (ready to be reclaimed).
+    Dead,
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            State::Ready => f.pad("Ready"),
+            State::Waiting(_) => f.pad("Waiting"),
+            State::Running => f.pad("Running"),
+            State::Dead => f.pad("Dead"),
+        }
+    }
+}
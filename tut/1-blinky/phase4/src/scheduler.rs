@@ -0,0 +1,247 @@
+//! A preemptive round-robin scheduler.
+//!
+//! Every process gets the CPU for one timer tick (`irq::TICK`) before the
+//! next ready one is switched in. The tick handler only asks for a switch
+//! with `request_preemption`; the IRQ path in `traps` performs it once all
+//! handlers have run, since only it has the interrupted process's trap
+//! frame. Only core 0 runs processes.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use pi::interrupt::Controller;
+use stack_vec::StackVec;
+
+use crate::mutex::Mutex;
+use crate::process::{Id, Process, State, MAX_STACKS};
+use crate::traps::TrapFrame;
+
+/// The most processes that can exist at once: one per stack.
+pub const MAX_PROCESSES: usize = MAX_STACKS;
+
+/// Set by the timer tick to ask for the running process to be switched out.
+static PREEMPT: AtomicBool = AtomicBool::new(false);
+
+/// Asks for the running process to be preempted once the current interrupt
+/// has been handled.
+pub fn request_preemption() {
+    PREEMPT.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether a preemption was requested, clearing the request.
+pub fn take_preemption() -> bool {
+    let requested = PREEMPT.load(Ordering::Relaxed);
+    PREEMPT.store(false, Ordering::Relaxed);
+    requested
+}
+
+/// The global scheduler.
+pub static SCHEDULER: GlobalScheduler = GlobalScheduler::uninitialized();
+
+/// A wrapper around the scheduler that makes it a global singleton, usable
+/// from both process and exception context.
+pub struct GlobalScheduler(Mutex<Option<Scheduler>>);
+
+impl GlobalScheduler {
+    /// Returns an uninitialized wrapper around a local scheduler.
+    pub const fn uninitialized() -> GlobalScheduler {
+        GlobalScheduler(Mutex::new(None))
+    }
+
+    /// Runs `f` on the scheduler, initializing it if needed, with IRQs
+    /// masked so that the timer tick can't try to take the lock while it is
+    /// held.
+    pub fn critical<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Scheduler) -> R,
+    {
+        let masked = aarch64::irq_masked();
+        aarch64::disable_irq();
+
+        let result = {
+            let mut guard = self.0.lock();
+            f(guard.get_or_insert_with(Scheduler::new))
+        };
+
+        if !masked {
+            aarch64::enable_irq();
+        }
+        result
+    }
+
+    /// Adds a process to the scheduler's queue and returns that process's
+    /// ID, or `None` if the process table is full.
+    pub fn add(&self, process: Process) -> Option<Id> {
+        self.critical(move |scheduler| scheduler.add(process))
+    }
+
+    /// Performs a context switch using `tf` by setting the state of the
+    /// current process to `new_state`, saving `tf` into the current process,
+    /// and restoring the next process's trap frame into `tf`. Returns the ID
+    /// of the process switched to, or `None` if no process was running, in
+    /// which case `tf` is left alone.
+    ///
+    /// Must be called from exception context, with IRQs masked.
+    pub fn switch(&self, new_state: State, tf: &mut TrapFrame) -> Option<Id> {
+        if !self.critical(|scheduler| scheduler.schedule_out(new_state, tf)) {
+            return None;
+        }
+        Some(self.switch_to(tf))
+    }
+
+    /// Lets the running process handle a translation fault at `addr`.
+    /// Returns `false` if there is no running process or it can't resolve
+    /// the fault.
+    pub fn page_fault(&self, addr: usize) -> bool {
+        self.critical(|scheduler| match scheduler.running_mut() {
+            Some(process) => process.page_fault(addr),
+            None => false,
+        })
+    }
+
+    /// Loops until a process is ready and restores its trap frame into `tf`.
+    fn switch_to(&self, tf: &mut TrapFrame) -> Id {
+        loop {
+            if let Some(id) = self.critical(|scheduler| scheduler.switch_to(tf)) {
+                PREEMPT.store(false, Ordering::Relaxed);
+                return id;
+            }
+
+            // Nothing is ready. IRQs are masked here, but one still wakes
+            // the core; handle it ourselves since it may be what a waiting
+            // process is waiting for.
+            aarch64::wfi();
+            Controller::new().dispatch();
+        }
+    }
+
+    /// Starts executing processes in user space using timer interrupt based
+    /// preemptive scheduling. This method should not return under normal
+    /// conditions.
+    pub fn start(&self) -> ! {
+        extern "C" {
+            fn context_launch(tf: *const TrapFrame) -> !;
+        }
+
+        // The first process is entered with an `eret` from a trap frame, as
+        // if returning from an exception; nothing may interrupt until then.
+        aarch64::disable_irq();
+
+        let mut tf = TrapFrame::default();
+        self.switch_to(&mut tf);
+
+        // `context_launch` reuses the boot stack, which `tf` lives on, so
+        // hand it the running process's own copy of the frame instead.
+        let first = self.critical(|scheduler| scheduler.running().map(|p| &p.context as *const TrapFrame));
+        unsafe { context_launch(first.expect("no running process")) }
+    }
+}
+
+/// The process table and round-robin state.
+pub struct Scheduler {
+    processes: [Option<Process>; MAX_PROCESSES],
+    /// The slot of the running process, if any.
+    current: Option<usize>,
+    /// The slot to start looking for the next process to run from.
+    next: usize,
+    last_id: Id,
+}
+
+impl Scheduler {
+    /// Returns a new `Scheduler` with an empty process table.
+    fn new() -> Scheduler {
+        Scheduler {
+            processes: Default::default(),
+            current: None,
+            next: 0,
+            last_id: 0,
+        }
+    }
+
+    /// Adds a process to the scheduler's queue and returns that process's
+    /// ID, or `None` if the process table is full.
+    ///
+    /// The process's ID is assigned here and stored in its `TPIDR_EL0`.
+    fn add(&mut self, mut process: Process) -> Option<Id> {
+        let slot = self.processes.iter().position(Option::is_none)?;
+        self.last_id += 1;
+        process.context.tpidr = self.last_id;
+        self.processes[slot] = Some(process);
+        Some(self.last_id)
+    }
+
+    /// Saves `tf` into the running process and sets its state to
+    /// `new_state`. Returns `false` if no process is running.
+    fn schedule_out(&mut self, new_state: State, tf: &TrapFrame) -> bool {
+        let slot = match self.current.take() {
+            Some(slot) => slot,
+            None => return false,
+        };
+
+        if let Some(process) = &mut self.processes[slot] {
+            process.state = new_state;
+            process.context = *tf;
+        }
+        true
+    }
+
+    /// Finds the next process that is ready, starting after the one that ran
+    /// last, marks it `Running` and restores its trap frame into `tf`. Dead
+    /// processes found along the way are reclaimed. Returns `None` if no
+    /// process is ready.
+    fn switch_to(&mut self, tf: &mut TrapFrame) -> Option<Id> {
+        for i in 0..MAX_PROCESSES {
+            let slot = (self.next + i) % MAX_PROCESSES;
+            let entry = &mut self.processes[slot];
+
+            if let Some(Process { state: State::Dead, .. }) = entry {
+                *entry = None;
+            }
+
+            if let Some(process) = entry {
+                if process.is_ready() {
+                    process.state = State::Running;
+                    *tf = process.context;
+                    self.current = Some(slot);
+                    self.next = (slot + 1) % MAX_PROCESSES;
+                    return Some(process.id());
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the running process, if any.
+    fn running(&self) -> Option<&Process> {
+        self.processes[self.current?].as_ref()
+    }
+
+    /// Returns the running process, if any.
+    fn running_mut(&mut self) -> Option<&mut Process> {
+        self.processes[self.current?].as_mut()
+    }
+
+    /// Calls `f` on every process.
+    pub fn for_each<F: FnMut(&Process)>(&self, f: F) {
+        self.processes.iter().flatten().for_each(f);
+    }
+}
+
+/// Shell command that lists the processes.
+pub fn ps(_args: &[&str]) -> Result<(), &'static str> {
+    // Copy the rows out before printing: a preempted process may hold the
+    // console, and it can't run again while `critical` masks the timer.
+    let mut buf = [(0, State::Dead, ""); MAX_PROCESSES];
+    let mut rows = StackVec::new(&mut buf);
+    SCHEDULER.critical(|scheduler| {
+        scheduler.for_each(|p| {
+            // There is a row for every process slot, so this can't fail.
+            let _ = rows.push((p.id(), p.state, p.name));
+        });
+    });
+
+    kprintln!("  {:<4} {:<8} {}", "ID", "STATE", "NAME");
+    for (id, state, name) in &rows {
+        kprintln!("  {:<4} {:<8?} {}", id, state, name);
+    }
+    Ok(())
+}
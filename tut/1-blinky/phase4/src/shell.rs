@@ -0,0 +1,272 @@
+use core::str;
+
+use stack_vec::StackVec;
+
+use crate::console::{self, CONSOLE};
+use crate::mutex::Mutex;
+
+/// The maximum number of bytes accepted on a single command line.
+pub const MAX_LINE_LEN: usize = 512;
+
+/// The maximum number of whitespace-separated arguments, including the
+/// command name itself.
+pub const MAX_ARGS: usize = 64;
+
+/// The maximum number of commands that can be registered at runtime in
+/// addition to the built-ins.
+const MAX_COMMANDS: usize = 32;
+
+const BELL: u8 = 0x07;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// The signature of a shell command. `args[0]` is the command name. An
+/// `Err` is reported to the user as `name: message`.
+pub type Handler = fn(args: &[&str]) -> Result<(), &'static str>;
+
+/// A command the shell can dispatch to.
+#[derive(Clone, Copy)]
+pub struct Command {
+    /// The name the command is invoked by.
+    pub name: &'static str,
+    /// Argument synopsis shown by `help`, e.g. `<addr> [count]`.
+    pub usage: &'static str,
+    /// A one-line description shown by `help`.
+    pub help: &'static str,
+    /// The function that runs the command.
+    pub run: Handler,
+}
+
+/// Error type for `Line` parse failures.
+#[derive(Debug)]
+enum Error {
+    Empty,
+    TooManyArgs,
+}
+
+/// A command line split into its arguments.
+struct Line<'a> {
+    args: StackVec<'a, &'a str>,
+}
+
+impl<'a> Line<'a> {
+    /// Parse a command from a string `s` using `buf` as storage for the
+    /// arguments.
+    ///
+    /// Returns `Err(Error::TooManyArgs)` if there are more arguments than
+    /// `buf` can hold. Returns `Err(Error::Empty)` if no arguments are
+    /// provided.
+    fn parse(s: &'a str, buf: &'a mut [&'a str]) -> Result<Line<'a>, Error> {
+        let mut args = StackVec::new(buf);
+        for arg in s.split(' ').filter(|a| !a.is_empty()) {
+            args.push(arg).map_err(|_| Error::TooManyArgs)?;
+        }
+
+        if args.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        Ok(Line { args })
+    }
+
+    /// Returns all arguments, starting with the command name.
+    fn into_args(self) -> &'a [&'a str] {
+        self.args.into_slice()
+    }
+}
+
+/// Commands available in every shell.
+const BUILTINS: &[Command] = &[
+    Command { name: "echo", usage: "[args...]", help: "print the arguments", run: echo },
+    Command { name: "help", usage: "", help: "list the available commands", run: help },
+    Command { name: "uptime", usage: "", help: "show the time since boot", run: uptime },
+    Command { name: "peek", usage: "<addr> [count]", help: "dump 32-bit words at a physical address", run: peek },
+    Command { name: "poke", usage: "<addr> <value>", help: "write a 32-bit word to a physical address", run: poke },
+    Command { name: "reboot", usage: "", help: "reset the board", run: reboot },
+];
+
+/// Commands registered at runtime by other modules.
+static COMMANDS: Mutex<[Option<Command>; MAX_COMMANDS]> = Mutex::new([None; MAX_COMMANDS]);
+
+/// Makes `command` available to every shell.
+///
+/// Fails if a command with the same name already exists or if the table is
+/// full.
+pub fn register(command: Command) -> Result<(), &'static str> {
+    if find(command.name).is_some() {
+        return Err("a command with this name already exists");
+    }
+
+    let mut commands = COMMANDS.lock();
+    match commands.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(command);
+            Ok(())
+        }
+        None => Err("command table is full"),
+    }
+}
+
+/// Returns the built-in or registered command named `name`.
+fn find(name: &str) -> Option<Command> {
+    if let Some(command) = BUILTINS.iter().find(|c| c.name == name) {
+        return Some(*command);
+    }
+
+    COMMANDS.lock().iter().flatten().find(|c| c.name == name).copied()
+}
+
+/// Reads one line of input into `line`, echoing it and handling backspace
+/// and delete. Non-printable input and input past the capacity of `line`
+/// ring the bell.
+fn read_line(line: &mut StackVec<u8>) {
+    loop {
+        let byte = console::read_byte();
+        match byte {
+            b'\r' | b'\n' => {
+                kprint!("\n");
+                return;
+            }
+            BACKSPACE | DELETE => {
+                if line.pop().is_some() {
+                    kprint!("\x08 \x08");
+                } else {
+                    CONSOLE.lock().write_byte(BELL);
+                }
+            }
+            b' '..=b'~' if line.push(byte).is_ok() => CONSOLE.lock().write_byte(byte),
+            _ => CONSOLE.lock().write_byte(BELL),
+        }
+    }
+}
+
+/// Prints `prefix`, reads a line of input and splits it into arguments,
+/// using `line_buf` and `args_buf` as storage. Returns `None` if the line is
+/// empty or can't be parsed; parse errors are reported to the user.
+pub fn prompt<'a>(prefix: &str, line_buf: &'a mut [u8], args_buf: &'a mut [&'a str]) -> Option<&'a [&'a str]> {
+    let mut line = StackVec::new(line_buf);
+
+    kprint!("{}", prefix);
+    read_line(&mut line);
+
+    // Only printable ASCII is ever pushed onto `line`.
+    let input = str::from_utf8(line.into_slice()).unwrap_or("");
+    match Line::parse(input, args_buf) {
+        Ok(line) => Some(line.into_args()),
+        Err(Error::TooManyArgs) => {
+            kprintln!("error: too many arguments");
+            None
+        }
+        Err(Error::Empty) => None,
+    }
+}
+
+/// Runs the built-in or registered command named by `args[0]`, reporting
+/// any error, or an unknown command, to the user.
+pub fn run(args: &[&str]) {
+    match find(args[0]) {
+        Some(command) => {
+            if let Err(msg) = (command.run)(args) {
+                kprintln!("{}: {}", command.name, msg);
+            }
+        }
+        None => kprintln!("unknown command: {}", args[0]),
+    }
+}
+
+/// Starts a shell using `prefix` as the prefix for each line. This function
+/// never returns.
+pub fn shell(prefix: &str) -> ! {
+    let mut line_buf = [0u8; MAX_LINE_LEN];
+    loop {
+        let mut args_buf = [""; MAX_ARGS];
+        if let Some(args) = prompt(prefix, &mut line_buf, &mut args_buf) {
+            run(args);
+        }
+    }
+}
+
+/// Parses `s` as a decimal number, or as hexadecimal if prefixed by `0x`.
+pub fn parse_usize(s: &str) -> Result<usize, &'static str> {
+    let result = if s.starts_with("0x") || s.starts_with("0X") {
+        usize::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse()
+    };
+    result.map_err(|_| "invalid number")
+}
+
+fn echo(args: &[&str]) -> Result<(), &'static str> {
+    for (i, arg) in args[1..].iter().enumerate() {
+        if i > 0 {
+            kprint!(" ");
+        }
+        kprint!("{}", arg);
+    }
+    kprintln!();
+    Ok(())
+}
+
+fn help(_args: &[&str]) -> Result<(), &'static str> {
+    let print = |c: &Command| kprintln!("  {:<8} {:<16} {}", c.name, c.usage, c.help);
+
+    BUILTINS.iter().for_each(print);
+    COMMANDS.lock().iter().flatten().for_each(print);
+    Ok(())
+}
+
+fn uptime(_args: &[&str]) -> Result<(), &'static str> {
+    let now = pi::timer::current_time();
+    let secs = now.as_secs();
+    kprintln!(
+        "up {}h {:02}m {:02}.{:03}s",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60,
+        now.subsec_millis()
+    );
+    Ok(())
+}
+
+/// Parses a physical address argument, which must be word aligned.
+fn parse_addr(s: &str) -> Result<*mut u32, &'static str> {
+    let addr = parse_usize(s)?;
+    if addr % 4 != 0 {
+        return Err("address must be 4-byte aligned");
+    }
+    Ok(addr as *mut u32)
+}
+
+fn peek(args: &[&str]) -> Result<(), &'static str> {
+    let (addr, count) = match args {
+        [_, addr] => (parse_addr(addr)?, 1),
+        [_, addr, count] => (parse_addr(addr)?, parse_usize(count)?),
+        _ => return Err("usage: peek <addr> [count]"),
+    };
+
+    for i in 0..count {
+        let ptr = unsafe { addr.add(i) };
+        let value = unsafe { ptr.read_volatile() };
+        kprintln!("{:#010x}: {:#010x}", ptr as usize, value);
+    }
+    Ok(())
+}
+
+fn poke(args: &[&str]) -> Result<(), &'static str> {
+    let (addr, value) = match args {
+        [_, addr, value] => (parse_addr(addr)?, parse_usize(value)?),
+        _ => return Err("usage: poke <addr> <value>"),
+    };
+
+    if value > u32::max_value() as usize {
+        return Err("value does not fit in 32 bits");
+    }
+
+    unsafe { addr.write_volatile(value as u32) };
+    Ok(())
+}
+
+fn reboot(_args: &[&str]) -> Result<(), &'static str> {
+    kprintln!("rebooting...");
+    pi::pm::reset()
+}
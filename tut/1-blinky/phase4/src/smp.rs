@@ -0,0 +1,54 @@
+//! Bringing up the secondary cores.
+//!
+//! The firmware starts only core 0 at the kernel. Cores 1-3 wait in its
+//! stub, each polling its own "spin table" mailbox at `0xd8 + 8 * core` and
+//! jumping to whatever address appears there. Releasing a core is just a
+//! matter of writing `_start_secondary` into its mailbox and waking it with
+//! `sev`. QEMU doesn't run the stub when booting an ELF kernel, and starts
+//! every core at `_start` instead; `_start` then polls the same mailboxes
+//! itself. `_start_secondary` gives each core its own stack from `.stacks`
+//! in `layout.ld` and calls `kinit_secondary(core)`, which turns on the
+//! core's MMU with the kernel's page table before anything else.
+
+use volatile::prelude::*;
+use volatile::Volatile;
+
+use crate::barrier::Barrier;
+
+/// The number of cores on the BCM2837.
+pub const NCORES: usize = 4;
+
+/// Address of core 0's spin table mailbox. Core `n`'s is `8 * n` bytes on.
+const SPIN_TABLE_BASE: usize = 0xd8;
+
+/// Passed by every core, including core 0, once it is up and running kernel
+/// code.
+pub static STARTED: Barrier = Barrier::new();
+
+/// Returns the index of the core this is running on.
+pub fn core_id() -> usize {
+    aarch64::affinity()
+}
+
+/// Releases cores 1-3 from the firmware's spin table. Each starts running
+/// `kinit_secondary` on its own stack.
+///
+/// Call this once, from core 0. Wait on `STARTED` to know when they're up.
+pub fn start_secondary_cores() {
+    extern "C" {
+        fn _start_secondary();
+    }
+
+    for core in 1..NCORES {
+        let mailbox = (SPIN_TABLE_BASE + 8 * core) as *mut usize;
+        unsafe { Volatile::from_ptr(mailbox).write(_start_secondary as *const () as usize) };
+
+        // The waiting cores poll with their MMU, and so their caches, off:
+        // push the write out of ours.
+        aarch64::clean_dcache_line(mailbox as usize);
+    }
+
+    // Make the mailbox writes visible before waking the cores to read them.
+    aarch64::dsb();
+    aarch64::sev();
+}
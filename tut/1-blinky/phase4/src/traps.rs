@@ -0,0 +1,126 @@
+mod frame;
+mod syndrome;
+mod syscall;
+
+use core::fmt::{self, Write};
+
+use pi::interrupt::Controller;
+
+use crate::console::{Output, CONSOLE};
+use crate::debugger;
+use crate::process::State;
+use crate::scheduler::{self, SCHEDULER};
+
+pub use self::frame::TrapFrame;
+pub use self::syndrome::{Fault, Syndrome};
+
+use self::syscall::handle_syscall;
+
+#[cfg(not(test))]
+global_asm!(include_str!("traps/vectors.s"));
+
+/// Where an exception was taken from, as determined by which group of the
+/// vector table it was routed through.
+#[repr(u16)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Source {
+    CurrentSpEl0 = 0,
+    CurrentSpElx = 1,
+    LowerAArch64 = 2,
+    LowerAArch32 = 3,
+}
+
+/// The kind of an exception, as determined by which entry of its group it
+/// was routed through.
+#[repr(u16)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Kind {
+    Synchronous = 0,
+    Irq = 1,
+    Fiq = 2,
+    SError = 3,
+}
+
+/// Identifies the vector table entry an exception came through. Each entry
+/// in `vectors.s` builds one of these in `x0`.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Info {
+    pub source: Source,
+    pub kind: Kind,
+}
+
+/// This function is called when an exception occurs. The `info` parameter
+/// specifies the source and kind of exception that has occurred. The `esr` is
+/// the value of the exception syndrome register. Finally, `tf` is a pointer
+/// to the trap frame for the exception, which is restored on return.
+#[no_mangle]
+pub extern "C" fn handle_exception(info: Info, esr: u32, tf: &mut TrapFrame) {
+    if info.kind == Kind::Synchronous {
+        match Syndrome::from(esr) {
+            Syndrome::Brk(imm) => return debugger::debug(imm, tf),
+            Syndrome::Svc(num) => return handle_syscall(num, tf),
+            syndrome if info.source == Source::LowerAArch64 => return user_fault(syndrome, tf),
+            syndrome => unexpected(info, Some(syndrome), tf),
+        }
+    }
+
+    if info.kind == Kind::Irq {
+        Controller::new().dispatch();
+        if scheduler::take_preemption() {
+            SCHEDULER.switch(State::Ready, tf);
+        }
+        return;
+    }
+
+    unexpected(info, None, tf)
+}
+
+/// Handles a synchronous exception from a user process: a translation
+/// fault in its user region pages in the missing page, and anything else
+/// kills the process.
+fn user_fault(syndrome: Syndrome, tf: &mut TrapFrame) {
+    let addr = aarch64::far();
+    if let Syndrome::DataAbort { kind: Fault::Translation, .. } = syndrome {
+        if SCHEDULER.page_fault(addr) {
+            return;
+        }
+    }
+
+    // Another process may hold the console; the report isn't worth waiting
+    // for with IRQs masked.
+    if let Some(mut console) = CONSOLE.try_lock() {
+        let _ = writeln!(
+            console,
+            "process {} killed: {:?} at {:#x} (address {:#x})",
+            tf.tpidr, syndrome, tf.elr, addr
+        );
+    }
+    SCHEDULER.switch(State::Dead, tf);
+}
+
+/// Reports an exception the kernel has no handler for and panics. The
+/// exception may have been taken with the console locked, so the report
+/// doesn't wait for it.
+fn unexpected(info: Info, syndrome: Option<Syndrome>, tf: &TrapFrame) -> ! {
+    let mut out = Output::new();
+    let _ = report(&mut out, info, syndrome, tf);
+    drop(out);
+
+    panic!("unhandled exception")
+}
+
+/// Writes what `unexpected` knows about an exception to `out`.
+fn report(out: &mut Output, info: Info, syndrome: Option<Syndrome>, tf: &TrapFrame) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "unexpected {:?} exception from {:?}", info.kind, info.source)?;
+    if let Some(syndrome) = syndrome {
+        writeln!(out, "  syndrome: {:?}", syndrome)?;
+        if let Syndrome::DataAbort { .. } | Syndrome::InstructionAbort { .. } = syndrome {
+            writeln!(out, "  address:  {:#x}", aarch64::far())?;
+        }
+    }
+    writeln!(out, "  elr:      {:#x}", tf.elr)?;
+    writeln!(out, "  spsr:     {:#x}", tf.spsr)?;
+    writeln!(out, "  sp:       {:#x}", tf.sp)
+}
@@ -0,0 +1,20 @@
+/// The state of the interrupted context, as saved by the exception vectors
+/// in `vectors.s`. The field order must match the offsets used there.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug)]
+pub struct TrapFrame {
+    /// Exception link register: where `eret` resumes.
+    pub elr: u64,
+    /// Saved program status register: the PSTATE `eret` restores.
+    pub spsr: u64,
+    /// The interrupted context's stack pointer, `SP_EL0`.
+    pub sp: u64,
+    /// The thread ID register, `TPIDR_EL0`.
+    pub tpidr: u64,
+    /// General purpose registers `x0` through `x30`.
+    pub x: [u64; 31],
+    /// The translation table base the context runs on, `TTBR0_EL1`.
+    pub ttbr0: u64,
+    /// FP/SIMD registers `q0` through `q31`.
+    pub q: [u128; 32],
+}
@@ -0,0 +1,86 @@
+/// The kind of a data or instruction abort, from the fault status code in
+/// the low six bits of the ISS.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Fault {
+    AddressSize,
+    Translation,
+    AccessFlag,
+    Permission,
+    Alignment,
+    TlbConflict,
+    Other(u8),
+}
+
+impl From<u32> for Fault {
+    fn from(val: u32) -> Fault {
+        use self::Fault::*;
+
+        match val & 0b111111 {
+            0b000000..=0b000011 => AddressSize,
+            0b000100..=0b000111 => Translation,
+            0b001000..=0b001011 => AccessFlag,
+            0b001100..=0b001111 => Permission,
+            0b100001 => Alignment,
+            0b110000 => TlbConflict,
+            other => Other(other as u8),
+        }
+    }
+}
+
+/// The cause of a synchronous exception, decoded from `ESR_EL1`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Syndrome {
+    Unknown,
+    WfiWfe,
+    SimdFp,
+    IllegalExecutionState,
+    Svc(u16),
+    Hvc(u16),
+    Smc(u16),
+    MsrMrsSystem,
+    InstructionAbort { kind: Fault, level: u8 },
+    PCAlignmentFault,
+    DataAbort { kind: Fault, level: u8 },
+    SpAlignmentFault,
+    TrappedFpu,
+    SError,
+    Breakpoint,
+    Step,
+    Watchpoint,
+    Brk(u16),
+    Other(u32),
+}
+
+/// Converts a raw syndrome value (ESR) into a `Syndrome` (ref: D1.10.4).
+impl From<u32> for Syndrome {
+    fn from(esr: u32) -> Syndrome {
+        use self::Syndrome::*;
+
+        let class = esr >> 26;
+        let iss = esr & ((1 << 25) - 1);
+        let imm16 = iss as u16;
+        let abort_level = (iss & 0b11) as u8;
+
+        match class {
+            0b000000 => Unknown,
+            0b000001 => WfiWfe,
+            0b000111 => SimdFp,
+            0b001110 => IllegalExecutionState,
+            0b010001 | 0b010101 => Svc(imm16),
+            0b010010 | 0b010110 => Hvc(imm16),
+            0b010011 | 0b010111 => Smc(imm16),
+            0b011000 => MsrMrsSystem,
+            0b100000 | 0b100001 => InstructionAbort { kind: Fault::from(iss), level: abort_level },
+            0b100010 => PCAlignmentFault,
+            0b100100 | 0b100101 => DataAbort { kind: Fault::from(iss), level: abort_level },
+            0b100110 => SpAlignmentFault,
+            0b101000 | 0b101100 => TrappedFpu,
+            0b101111 => SError,
+            0b110000 | 0b110001 => Breakpoint,
+            0b110010 | 0b110011 => Step,
+            0b110100 | 0b110101 => Watchpoint,
+            0b111100 => Brk(imm16),
+            _ => Other(esr),
+        }
+    }
+}
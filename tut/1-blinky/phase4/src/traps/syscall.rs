@@ -0,0 +1,109 @@
+use core::time::Duration;
+
+use kernel_api::*;
+use pi::timer;
+
+use crate::console::CONSOLE;
+use crate::process::{EventPoll, Process, State};
+use crate::scheduler::SCHEDULER;
+use crate::traps::TrapFrame;
+
+/// Sets a successful system call's return value.
+fn succeed(tf: &mut TrapFrame, value: u64) {
+    tf.x[0] = value;
+    tf.x[7] = 0;
+}
+
+/// Fails a system call with `err`.
+fn fail(tf: &mut TrapFrame, err: OsError) {
+    tf.x[7] = err as u64;
+}
+
+/// Sleep for `ms` milliseconds.
+///
+/// This system call takes one parameter: the number of milliseconds to sleep.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the approximate true elapsed time from when `sleep` was called
+/// to when `sleep` returned.
+fn sys_sleep(ms: u64, tf: &mut TrapFrame) {
+    fn poll(process: &mut Process, start: u64) -> bool {
+        let ms = process.context.x[0];
+        let elapsed = timer::current_time() - Duration::from_micros(start);
+        if elapsed < Duration::from_millis(ms) {
+            return false;
+        }
+
+        succeed(&mut process.context, elapsed.as_millis() as u64);
+        true
+    }
+
+    if ms == 0 {
+        return succeed(tf, 0);
+    }
+
+    let start = timer::current_time().as_micros() as u64;
+    let event = EventPoll { poll, data: start };
+    if SCHEDULER.switch(State::Waiting(event), tf).is_none() {
+        fail(tf, OsError::NoProcess);
+    }
+}
+
+/// Returns the time since boot in microseconds.
+fn sys_time(tf: &mut TrapFrame) {
+    succeed(tf, timer::current_time().as_micros() as u64);
+}
+
+/// Kills the current process.
+fn sys_exit(tf: &mut TrapFrame) {
+    if SCHEDULER.switch(State::Dead, tf).is_none() {
+        fail(tf, OsError::NoProcess);
+    }
+}
+
+/// Writes a byte to the console.
+///
+/// The console may be held by the process this one preempted, which can't
+/// run again until this one gives up the CPU, so if it's locked the process
+/// waits for it rather than spinning.
+fn sys_write(byte: u8, tf: &mut TrapFrame) {
+    fn poll(process: &mut Process, byte: u64) -> bool {
+        match CONSOLE.try_lock() {
+            Some(mut console) => {
+                console.write_byte(byte as u8);
+                succeed(&mut process.context, 0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    if let Some(mut console) = CONSOLE.try_lock() {
+        console.write_byte(byte);
+        return succeed(tf, 0);
+    }
+
+    let event = EventPoll { poll, data: byte as u64 };
+    if SCHEDULER.switch(State::Waiting(event), tf).is_none() {
+        fail(tf, OsError::NoProcess);
+    }
+}
+
+/// Returns the current process's ID.
+fn sys_getpid(tf: &mut TrapFrame) {
+    let pid = tf.tpidr;
+    succeed(tf, pid);
+}
+
+/// Dispatches the system call `num`, made with `svc #num`, using the
+/// arguments in and storing the results to `tf`.
+pub fn handle_syscall(num: u16, tf: &mut TrapFrame) {
+    match num as usize {
+        NR_SLEEP => sys_sleep(tf.x[0], tf),
+        NR_TIME => sys_time(tf),
+        NR_EXIT => sys_exit(tf),
+        NR_WRITE => sys_write(tf.x[0] as u8, tf),
+        NR_GETPID => sys_getpid(tf),
+        _ => fail(tf, OsError::InvalidSyscall),
+    }
+}
@@ -0,0 +1,174 @@
+// Layout of the TrapFrame (frame.rs) built on the SP_EL1 stack
+.equ TF_ELR,    0
+.equ TF_SP,     16
+.equ TF_X,      32
+.equ TF_TTBR0,  280
+.equ TF_Q,      288
+.equ TF_SIZE,   800
+
+// one vector table entry: save x0 and x1, pass the entry's `Info` in x0
+.macro HANDLER source, kind
+    .align 7
+    sub     sp, sp, #TF_SIZE
+    stp     x0, x1, [sp, #TF_X]
+    mov     x0, #\source
+    movk    x0, #\kind, lsl #16
+    b       context_switch
+.endm
+
+.section .text
+
+// Saves the rest of the TrapFrame, calls
+// handle_exception(info: Info, esr: u32, tf: &mut TrapFrame), then restores
+// the (possibly modified) TrapFrame and returns from the exception.
+context_switch:
+    stp     x2, x3, [sp, #(TF_X + 16 * 1)]
+    stp     x4, x5, [sp, #(TF_X + 16 * 2)]
+    stp     x6, x7, [sp, #(TF_X + 16 * 3)]
+    stp     x8, x9, [sp, #(TF_X + 16 * 4)]
+    stp     x10, x11, [sp, #(TF_X + 16 * 5)]
+    stp     x12, x13, [sp, #(TF_X + 16 * 6)]
+    stp     x14, x15, [sp, #(TF_X + 16 * 7)]
+    stp     x16, x17, [sp, #(TF_X + 16 * 8)]
+    stp     x18, x19, [sp, #(TF_X + 16 * 9)]
+    stp     x20, x21, [sp, #(TF_X + 16 * 10)]
+    stp     x22, x23, [sp, #(TF_X + 16 * 11)]
+    stp     x24, x25, [sp, #(TF_X + 16 * 12)]
+    stp     x26, x27, [sp, #(TF_X + 16 * 13)]
+    stp     x28, x29, [sp, #(TF_X + 16 * 14)]
+    mrs     x1, TTBR0_EL1
+    stp     x30, x1, [sp, #(TF_X + 16 * 15)]
+
+    add     x1, sp, #TF_Q
+    stp     q0, q1, [x1], #32
+    stp     q2, q3, [x1], #32
+    stp     q4, q5, [x1], #32
+    stp     q6, q7, [x1], #32
+    stp     q8, q9, [x1], #32
+    stp     q10, q11, [x1], #32
+    stp     q12, q13, [x1], #32
+    stp     q14, q15, [x1], #32
+    stp     q16, q17, [x1], #32
+    stp     q18, q19, [x1], #32
+    stp     q20, q21, [x1], #32
+    stp     q22, q23, [x1], #32
+    stp     q24, q25, [x1], #32
+    stp     q26, q27, [x1], #32
+    stp     q28, q29, [x1], #32
+    stp     q30, q31, [x1], #32
+
+    mrs     x1, ELR_EL1
+    mrs     x2, SPSR_EL1
+    stp     x1, x2, [sp, #TF_ELR]
+    mrs     x1, SP_EL0
+    mrs     x2, TPIDR_EL0
+    stp     x1, x2, [sp, #TF_SP]
+
+    mrs     x1, ESR_EL1
+    mov     x2, sp
+    bl      handle_exception
+
+// Restores the TrapFrame at sp, pops it and returns from the exception.
+context_restore:
+    // switch address spaces if the frame runs on another translation table.
+    // No ASIDs are used, so the old table's TLB entries must go
+    ldr     x1, [sp, #TF_TTBR0]
+    mrs     x2, TTBR0_EL1
+    cmp     x1, x2
+    b.eq    1f
+    msr     TTBR0_EL1, x1
+    isb
+    tlbi    vmalle1
+    dsb     nsh
+    isb
+1:
+    ldp     x1, x2, [sp, #TF_ELR]
+    msr     ELR_EL1, x1
+    msr     SPSR_EL1, x2
+    ldp     x1, x2, [sp, #TF_SP]
+    msr     SP_EL0, x1
+    msr     TPIDR_EL0, x2
+
+    add     x1, sp, #TF_Q
+    ldp     q0, q1, [x1], #32
+    ldp     q2, q3, [x1], #32
+    ldp     q4, q5, [x1], #32
+    ldp     q6, q7, [x1], #32
+    ldp     q8, q9, [x1], #32
+    ldp     q10, q11, [x1], #32
+    ldp     q12, q13, [x1], #32
+    ldp     q14, q15, [x1], #32
+    ldp     q16, q17, [x1], #32
+    ldp     q18, q19, [x1], #32
+    ldp     q20, q21, [x1], #32
+    ldp     q22, q23, [x1], #32
+    ldp     q24, q25, [x1], #32
+    ldp     q26, q27, [x1], #32
+    ldp     q28, q29, [x1], #32
+    ldp     q30, q31, [x1], #32
+
+    ldp     x0, x1, [sp, #TF_X]
+    ldp     x2, x3, [sp, #(TF_X + 16 * 1)]
+    ldp     x4, x5, [sp, #(TF_X + 16 * 2)]
+    ldp     x6, x7, [sp, #(TF_X + 16 * 3)]
+    ldp     x8, x9, [sp, #(TF_X + 16 * 4)]
+    ldp     x10, x11, [sp, #(TF_X + 16 * 5)]
+    ldp     x12, x13, [sp, #(TF_X + 16 * 6)]
+    ldp     x14, x15, [sp, #(TF_X + 16 * 7)]
+    ldp     x16, x17, [sp, #(TF_X + 16 * 8)]
+    ldp     x18, x19, [sp, #(TF_X + 16 * 9)]
+    ldp     x20, x21, [sp, #(TF_X + 16 * 10)]
+    ldp     x22, x23, [sp, #(TF_X + 16 * 11)]
+    ldp     x24, x25, [sp, #(TF_X + 16 * 12)]
+    ldp     x26, x27, [sp, #(TF_X + 16 * 13)]
+    ldp     x28, x29, [sp, #(TF_X + 16 * 14)]
+    ldr     x30, [sp, #(TF_X + 16 * 15)]
+
+    add     sp, sp, #TF_SIZE
+    eret
+
+// Starts running the TrapFrame at x0, which must not be on the stack, from an
+// empty kernel stack: copies the frame to the top of core 0's boot stack
+// (which ends at _start), points sp at it and restores it. Never returns.
+//
+// fn context_launch(tf: *const TrapFrame) -> !
+.global context_launch
+context_launch:
+    ldr     x1, =_start
+    sub     x1, x1, #TF_SIZE
+    mov     sp, x1
+
+    mov     x2, #TF_SIZE
+1:
+    ldp     x3, x4, [x0], #16
+    stp     x3, x4, [x1], #16
+    subs    x2, x2, #16
+    b.ne    1b
+
+    b       context_restore
+
+// the table VBAR_EL1 points at: four groups of four entries, by where the
+// exception came from and then by kind; numbering matches `Source` and
+// `Kind` in traps.rs (ref: D1.10.2)
+.align 11
+.global vectors
+vectors:
+    HANDLER 0, 0
+    HANDLER 0, 1
+    HANDLER 0, 2
+    HANDLER 0, 3
+
+    HANDLER 1, 0
+    HANDLER 1, 1
+    HANDLER 1, 2
+    HANDLER 1, 3
+
+    HANDLER 2, 0
+    HANDLER 2, 1
+    HANDLER 2, 2
+    HANDLER 2, 3
+
+    HANDLER 3, 0
+    HANDLER 3, 1
+    HANDLER 3, 2
+    HANDLER 3, 3
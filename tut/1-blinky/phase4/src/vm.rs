@@ -0,0 +1,68 @@
+//! Virtual memory.
+//!
+//! Every core runs with the MMU and caches on, translating through the
+//! lower half of the address space (`TTBR0_EL1`) with 64KiB pages. The
+//! kernel and its processes run on `KernPageTable`, an identity map of RAM
+//! and the peripherals. User processes each get a `UserPageTable` instead,
+//! which adds a private, demand-paged region at `USER_BASE` to the same
+//! identity map. The table a context runs on is part of its trap frame, so
+//! switching processes switches address spaces.
+
+mod frame;
+mod pagetable;
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use aarch64::sctlr;
+use aarch64::vmsa::{L2_REGION_SIZE, MAIR_VALUE, TCR_VALUE};
+
+pub use self::pagetable::{KernPageTable, PageTable, UserPageTable};
+
+/// The start of every user process's private region.
+pub const USER_BASE: usize = 0x1_0000_0000;
+
+/// The size of the user region: everything one L3 table maps.
+pub const USER_SIZE: usize = L2_REGION_SIZE;
+
+/// Where a user process's stack pointer starts: the top of its region.
+pub const USER_STACK_TOP: usize = USER_BASE + USER_SIZE;
+
+/// The base address of the kernel's table, once `init` has built it.
+static KERNEL_BADDR: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the base address of the kernel's translation table.
+///
+/// # Panics
+///
+/// Panics if `init` hasn't been called.
+pub fn kernel_baddr() -> u64 {
+    match KERNEL_BADDR.load(Ordering::Acquire) {
+        0 => panic!("kernel page table not initialized"),
+        baddr => baddr,
+    }
+}
+
+/// Builds the kernel's identity map and turns on the MMU and caches on this
+/// core.
+///
+/// Call this once, from core 0, before any other core is started. The table
+/// lives for as long as the kernel runs; `KernPageTable` never frees it.
+pub fn init() {
+    let table = KernPageTable::new().expect("no memory for the kernel page table");
+    KERNEL_BADDR.store(table.baddr(), Ordering::Release);
+
+    unsafe { enable() };
+}
+
+/// Turns on the MMU and caches on this core, translating through the
+/// kernel's table.
+///
+/// # Safety
+///
+/// `init` must have run, and this core's MMU must still be off.
+pub unsafe fn enable() {
+    aarch64::set_mair_el1(MAIR_VALUE);
+    aarch64::set_tcr_el1(TCR_VALUE);
+    aarch64::set_ttbr0_el1(kernel_baddr());
+    aarch64::set_sctlr_el1(aarch64::sctlr_el1() | sctlr::M | sctlr::C | sctlr::I);
+}
@@ -0,0 +1,27 @@
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use alloc::alloc::{alloc_zeroed, dealloc};
+
+use aarch64::vmsa::PAGE_SIZE;
+
+/// The layout of a page frame: one page, aligned to its size.
+fn layout() -> Layout {
+    unsafe { Layout::from_size_align_unchecked(PAGE_SIZE, PAGE_SIZE) }
+}
+
+/// Allocates a zeroed, `PAGE_SIZE`-aligned page frame from the kernel heap.
+/// Returns `None` when the heap is exhausted.
+pub fn alloc() -> Option<NonNull<u8>> {
+    NonNull::new(unsafe { alloc_zeroed(layout()) })
+}
+
+/// Returns a frame from `alloc` to the kernel heap.
+///
+/// # Safety
+///
+/// `frame` must have come from `alloc`, must not already have been freed and
+/// must no longer be in use, including by any live translation table entry.
+pub unsafe fn free(frame: NonNull<u8>) {
+    dealloc(frame.as_ptr(), layout());
+}
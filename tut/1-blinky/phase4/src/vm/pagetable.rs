@@ -0,0 +1,270 @@
+use core::fmt;
+use core::ptr::NonNull;
+
+use aarch64::vmsa::*;
+use pi::common::IO_BASE;
+
+use super::{frame, kernel_baddr, USER_BASE, USER_SIZE};
+
+/// The end of the physical address space the kernel maps: RAM, the
+/// peripherals from `IO_BASE` and the ARM local peripherals above them.
+const KERNEL_MAP_END: usize = 0x4004_0000;
+
+/// The number of L3 tables, and so L2 entries, the kernel map uses.
+const KERNEL_L3_TABLES: usize = (KERNEL_MAP_END + L2_REGION_SIZE - 1) / L2_REGION_SIZE;
+
+/// The L2 entry covering the user region.
+const USER_L2_INDEX: usize = USER_BASE / L2_REGION_SIZE;
+
+#[repr(C, align(65536))]
+struct L2Table {
+    entries: [RawL2Entry; ENTRIES],
+}
+
+#[repr(C, align(65536))]
+struct L3Table {
+    entries: [RawL3Entry; ENTRIES],
+}
+
+/// Returns the L2 and L3 indices of the page containing `va`.
+fn indices(va: usize) -> (usize, usize) {
+    ((va / L2_REGION_SIZE) % ENTRIES, (va / PAGE_SIZE) % ENTRIES)
+}
+
+/// Returns an L3 entry mapping the page at `pa` as normal memory if `attr`
+/// is `ATTR_MEM`, or as device memory if it is `ATTR_DEV`, with permissions
+/// `ap`. The page is executable only if `exec` is set, and then only at the
+/// levels `ap` lets read it.
+fn page_entry(pa: usize, attr: u64, ap: u64, exec: bool) -> RawL3Entry {
+    let sh = if attr == ATTR_DEV { RawL3Entry::SH_OSH } else { RawL3Entry::SH_ISH };
+
+    let mut entry = RawL3Entry::new();
+    entry
+        .set_masked(pa as u64, RawL3Entry::ADDR)
+        .set_value(RawL3Entry::TYPE_PAGE, RawL3Entry::TYPE)
+        .set_value(attr, RawL3Entry::ATTR)
+        .set_value(ap, RawL3Entry::AP)
+        .set_value(sh, RawL3Entry::SH)
+        .set_value(1, RawL3Entry::AF)
+        .set_value(1, RawL3Entry::VALID);
+    if !exec {
+        entry.set_masked(RawL3Entry::PXN | RawL3Entry::UXN, RawL3Entry::PXN | RawL3Entry::UXN);
+    }
+    entry
+}
+
+/// A translation table for the lower half of the address space: one L2
+/// table and the L3 tables its entries point to. Every table fills exactly
+/// one page frame.
+pub struct PageTable {
+    l2: NonNull<L2Table>,
+}
+
+// A table's frames are owned by the `PageTable` alone.
+unsafe impl Send for PageTable {}
+
+impl PageTable {
+    /// Returns a table with nothing mapped, or `None` if there is no frame
+    /// for it.
+    fn new() -> Option<PageTable> {
+        Some(PageTable { l2: frame::alloc()?.cast() })
+    }
+
+    /// Returns the physical base address of the L2 table, for `TTBR0_EL1`.
+    pub fn baddr(&self) -> u64 {
+        self.l2.as_ptr() as u64
+    }
+
+    fn l2(&self) -> &L2Table {
+        unsafe { self.l2.as_ref() }
+    }
+
+    fn l2_mut(&mut self) -> &mut L2Table {
+        unsafe { self.l2.as_mut() }
+    }
+
+    /// Points L2 entry `index` at the L3 table in `frame`.
+    fn set_l3(&mut self, index: usize, frame: NonNull<u8>) {
+        let mut entry = RawL2Entry::new();
+        entry
+            .set_masked(frame.as_ptr() as u64, RawL2Entry::ADDR)
+            .set_value(RawL2Entry::TYPE_TABLE, RawL2Entry::TYPE)
+            .set_value(1, RawL2Entry::VALID);
+        self.l2_mut().entries[index] = entry;
+    }
+
+    /// Returns the address of the L3 table L2 entry `index` points to, if
+    /// it is valid.
+    fn l3_ptr(&self, index: usize) -> Option<*mut L3Table> {
+        let entry = self.l2().entries[index];
+        if !entry.is_valid() {
+            return None;
+        }
+        Some(entry.get_masked(RawL2Entry::ADDR) as *mut L3Table)
+    }
+
+    fn l3(&self, index: usize) -> Option<&L3Table> {
+        self.l3_ptr(index).map(|l3| unsafe { &*l3 })
+    }
+
+    fn l3_mut(&mut self, index: usize) -> Option<&mut L3Table> {
+        self.l3_ptr(index).map(|l3| unsafe { &mut *l3 })
+    }
+
+    /// Returns the L3 entry for the page containing `va`, or `None` if no L3
+    /// table covers it.
+    pub fn entry(&self, va: usize) -> Option<RawL3Entry> {
+        let (l2, l3) = indices(va);
+        Some(self.l3(l2)?.entries[l3])
+    }
+
+    /// Sets the L3 entry for the page containing `va`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no L3 table covers `va`.
+    fn set_entry(&mut self, va: usize, entry: RawL3Entry) {
+        let (l2, l3) = indices(va);
+        self.l3_mut(l2).expect("no L3 table for address").entries[l3] = entry;
+    }
+
+    /// Translates `va` to a physical address, or returns `None` if it isn't
+    /// mapped.
+    pub fn translate(&self, va: usize) -> Option<usize> {
+        let entry = self.entry(va)?;
+        if !entry.is_valid() {
+            return None;
+        }
+        Some(entry.get_masked(RawL3Entry::ADDR) as usize + va % PAGE_SIZE)
+    }
+}
+
+impl fmt::Debug for PageTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PageTable {{ baddr: {:#x} }}", self.baddr())
+    }
+}
+
+/// The kernel's identity map, which every core runs on and every process
+/// table shares.
+///
+/// RAM below `IO_BASE` is normal, cacheable memory, and the peripherals from
+/// there up are device memory. The kernel image's code and read-only data
+/// are read-only and executable at both EL1 and EL0, so that user processes
+/// can run functions linked into the kernel; everything else is only
+/// accessible from EL1 and never executable.
+pub struct KernPageTable(PageTable);
+
+impl KernPageTable {
+    /// Builds the kernel's identity map. Returns `None` if there aren't
+    /// enough frames for it.
+    pub fn new() -> Option<KernPageTable> {
+        extern "C" {
+            static __text_beg: u8;
+            static __rodata_end: u8;
+        }
+
+        let text = unsafe { &__text_beg as *const u8 as usize..&__rodata_end as *const u8 as usize };
+
+        let mut table = PageTable::new()?;
+        for index in 0..KERNEL_L3_TABLES {
+            table.set_l3(index, frame::alloc()?);
+        }
+
+        for pa in (0..KERNEL_MAP_END).step_by(PAGE_SIZE) {
+            let entry = if pa >= IO_BASE {
+                page_entry(pa, ATTR_DEV, RawL3Entry::AP_KERN_RW, false)
+            } else if text.contains(&pa) {
+                page_entry(pa, ATTR_MEM, RawL3Entry::AP_USER_RO, true)
+            } else {
+                page_entry(pa, ATTR_MEM, RawL3Entry::AP_KERN_RW, false)
+            };
+            table.set_entry(pa, entry);
+        }
+
+        Some(KernPageTable(table))
+    }
+
+    /// Returns the physical base address of the L2 table, for `TTBR0_EL1`.
+    pub fn baddr(&self) -> u64 {
+        self.0.baddr()
+    }
+}
+
+/// A process's translation table: the kernel's identity map plus a private
+/// region of `USER_SIZE` bytes at `USER_BASE`, readable and writable from
+/// EL0. Pages in the user region are only allocated when first touched; see
+/// `map_on_demand`. Dropping the table frees them along with the table.
+pub struct UserPageTable(PageTable);
+
+impl UserPageTable {
+    /// Returns a table with the kernel mapped and an empty user region, or
+    /// `None` if there are no frames for it.
+    pub fn new() -> Option<UserPageTable> {
+        let mut table = PageTable::new()?;
+
+        // Share the kernel's L3 tables rather than copying them.
+        let kernel = unsafe { &*(kernel_baddr() as *const L2Table) };
+        table.l2_mut().entries[..KERNEL_L3_TABLES].copy_from_slice(&kernel.entries[..KERNEL_L3_TABLES]);
+
+        // Build the table in `table` so that it is freed if this fails.
+        let mut table = UserPageTable(table);
+        table.0.set_l3(USER_L2_INDEX, frame::alloc()?);
+        Some(table)
+    }
+
+    /// Returns the physical base address of the L2 table, for `TTBR0_EL1`.
+    pub fn baddr(&self) -> u64 {
+        self.0.baddr()
+    }
+
+    /// Returns `true` if `va` lies in the user region.
+    pub fn is_user(va: usize) -> bool {
+        (USER_BASE..USER_BASE + USER_SIZE).contains(&va)
+    }
+
+    /// Maps a fresh, zeroed page at the page containing `va`. Returns `false`
+    /// if `va` is outside the user region, is already mapped or there is no
+    /// frame left for it.
+    pub fn map_on_demand(&mut self, va: usize) -> bool {
+        if !UserPageTable::is_user(va) || self.0.translate(va).is_some() {
+            return false;
+        }
+
+        let frame = match frame::alloc() {
+            Some(frame) => frame,
+            None => return false,
+        };
+
+        let entry = page_entry(frame.as_ptr() as usize, ATTR_MEM, RawL3Entry::AP_USER_RW, false);
+        self.0.set_entry(va, entry);
+
+        // The entry was invalid before, so no TLB can hold it; it only has
+        // to reach memory before the access is retried.
+        aarch64::dsb();
+        true
+    }
+}
+
+impl Drop for UserPageTable {
+    fn drop(&mut self) {
+        // Don't free tables out from under the translation in use.
+        if aarch64::ttbr0_el1() == self.baddr() {
+            unsafe { aarch64::set_ttbr0_el1(kernel_baddr()) };
+        }
+
+        if let Some(l3) = self.0.l3(USER_L2_INDEX) {
+            for entry in l3.entries.iter().filter(|e| e.is_valid()) {
+                unsafe { frame::free(NonNull::new_unchecked(entry.get_masked(RawL3Entry::ADDR) as *mut u8)) };
+            }
+            unsafe { frame::free(NonNull::from(l3).cast()) };
+        }
+        unsafe { frame::free(self.0.l2.cast()) };
+    }
+}
+
+impl fmt::Debug for UserPageTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UserPageTable {{ baddr: {:#x} }}", self.baddr())
+    }
+}